@@ -1,34 +1,487 @@
-use clap::Parser;
-use log;
+use clap::{Parser, Subcommand};
+use ddsi_log_parser::{
+    DdsiInputConfig, EventFormat, ExportFormat, InputOptions, Outcome, SortOrder, SummaryExport,
+    SummaryOptions, DEFAULT_FLAP_CYCLES, DEFAULT_FLAP_WINDOW,
+};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
 use std::process;
 
-/// Process ddsi log file and generate summary.
+/// Exit code when a command found differences or problems.
+const EXIT_FINDINGS: i32 = 1;
+/// Exit code when a command failed.
+const EXIT_ERROR: i32 = 2;
+
+/// Process ddsi log files.
+///
+/// Exits with 0 when nothing was found, 1 when check or diff found problems
+/// or differences and 2 on errors.
 #[derive(Parser)]
 struct Cli {
-    /// ddsi log file to process.
-    #[clap(short = 'f', long = "filename")]
-    filename: String,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Summarize the topology and the analyses of the logs.
+    Summary(SummaryCli),
+    /// Export the topology as a discovery graph or timeline.
+    Export(ExportCli),
+    /// Write every recognized log entry as an event, e.g. to query the
+    /// discovery history of many logs. Only --since and --until select the
+    /// events, --filter is not supported.
+    Events(EventsCli),
+    /// Report participants, readers and writers that appeared, disappeared
+    /// or changed QoS/locators between two logs, or one log at two times.
+    /// --at replaces --until, --jobs and --sort do not apply.
+    Diff(DiffCli),
+    /// Check rules against the topology, by default that there are no
+    /// incompatible QoS, flapping endpoints, readers falling behind or
+    /// problems reported in the logs.
+    Check(CheckCli),
+    /// Report the size of the topology and statistics over the log lines.
+    Stats(StatsCli),
+    /// List the endpoints matching a filter expression.
+    Query(QueryCli),
+    /// Follow logs as they grow, reporting new problems. Saved topologies
+    /// and --jobs are not supported, --sort and --no-progress do not apply.
+    Watch(WatchCli),
+    /// Browse the topology in an interactive terminal user interface.
+    #[cfg(feature = "tui")]
+    Tui(InputArgs),
+}
+
+/// Input options shared by all the commands processing ddsi logs.
+#[derive(clap::Args)]
+struct InputArgs {
+    /// ddsi log file, or topology saved as json, to process. Repeat to
+    /// process the logs of several nodes together.
+    #[clap(short = 'f', long = "filename", multiple_occurrences = true)]
+    filenames: Vec<String>,
+
+    /// Order of participants and endpoints: first-seen (default), host, guid
+    /// or topic.
+    #[clap(long = "sort")]
+    sort: Option<SortOrder>,
+
+    /// Only process the endpoints matching this expression, e.g.
+    /// 'topic =~ "logevent_.*" && host == "172.17.0.4" && reliability == best_effort'.
+    #[clap(long = "filter")]
+    filter: Option<String>,
+
+    /// Ignore log entries logged before this time, in seconds since epoch.
+    #[clap(long = "since")]
+    since: Option<f64>,
+
+    /// Ignore log entries logged after this time, in seconds since epoch.
+    #[clap(long = "until")]
+    until: Option<f64>,
+
+    /// Fail on log entries inconsistent with the topology instead of
+    /// warning about them on stderr.
+    #[clap(long = "strict")]
+    strict: bool,
+
+    /// Number of logs parsed in parallel, 1 by default.
+    #[clap(short = 'j', long = "jobs")]
+    jobs: Option<usize>,
+
+    /// Do not show the progress of the parse of the logs. It is only shown
+    /// when stderr is a terminal anyway.
+    #[clap(long = "no-progress")]
+    no_progress: bool,
+
+    /// Only report errors, neither progress nor what is being processed.
+    #[clap(short = 'q', long = "quiet")]
+    quiet: bool,
+}
+
+#[derive(clap::Args)]
+struct FlapArgs {
+    /// Width, in seconds, of the window used for flapping detection and
+    /// churn rates, 60 by default.
+    #[clap(long = "flap-window")]
+    flap_window: Option<f64>,
+
+    /// Number of creation/deletion cycles within the flapping window for an
+    /// endpoint to be reported as flapping, 3 by default.
+    #[clap(long = "flap-cycles")]
+    flap_cycles: Option<usize>,
+}
+
+#[derive(clap::Args)]
+struct SummaryCli {
+    #[clap(flatten)]
+    input: InputArgs,
+
+    /// json file of summary options, overridden by the ones given on the
+    /// command line.
+    #[clap(long = "config")]
+    config: Option<String>,
 
     /// Name of the output file.
+    #[clap(short = 'o', long = "output", required_unless_present = "config")]
+    output: Option<String>,
+
+    /// Save the merged topology as json to this file instead of
+    /// ddsi_topology.json.
+    #[clap(long = "json")]
+    json: Option<String>,
+
+    /// Do not save the merged topology as json.
+    #[clap(long = "no-json", conflicts_with = "json")]
+    no_json: bool,
+
+    /// Also export the merged topology, given as <format>=<file>, e.g.
+    /// dot=graph.dot. Repeat for several exports.
+    #[clap(long = "export", multiple_occurrences = true)]
+    exports: Vec<SummaryExport>,
+
+    /// Also save the merged topology, the problems found and the events
+    /// of the logs to this sqlite database, replacing it if it exists.
+    #[clap(long = "sqlite")]
+    sqlite: Option<String>,
+
+    #[clap(flatten)]
+    flap: FlapArgs,
+}
+
+#[derive(clap::Args)]
+struct ExportCli {
+    #[clap(flatten)]
+    input: InputArgs,
+
+    /// Export format: dot, mermaid or chrome-trace.
+    #[clap(long = "format", default_value = "dot")]
+    format: ExportFormat,
+
+    /// Name of the output file, the export is printed if not given.
+    #[clap(short = 'o', long = "output")]
+    output: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct EventsCli {
+    #[clap(flatten)]
+    input: InputArgs,
+
+    /// Event format: ndjson, parquet or arrow. parquet and arrow require
+    /// building with the parquet feature.
+    #[clap(long = "format", default_value = "ndjson")]
+    format: EventFormat,
+
+    /// Name of the output file, ndjson events are printed if not given.
     #[clap(short = 'o', long = "output")]
-    output: String,
+    output: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct DiffCli {
+    #[clap(flatten)]
+    input: InputArgs,
+
+    /// Take the topology at this time (in seconds since epoch). Give it once
+    /// for both inputs, or twice for before and after.
+    #[clap(long = "at", max_occurrences = 2)]
+    at: Vec<f64>,
+
+    /// Name of the output file, the diff is printed if not given.
+    #[clap(short = 'o', long = "output")]
+    output: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct CheckCli {
+    #[clap(flatten)]
+    input: InputArgs,
+
+    /// Name of the output file, the report is printed if not given.
+    #[clap(short = 'o', long = "output")]
+    output: Option<String>,
+
+    /// json file of rules to check. Without it, any finding of the built-in
+    /// checks fails.
+    #[clap(long = "rules")]
+    rules: Option<String>,
+
+    /// Write a JUnit XML report to this file.
+    #[clap(long = "junit")]
+    junit: Option<String>,
+
+    #[clap(flatten)]
+    flap: FlapArgs,
+}
+
+#[derive(clap::Args)]
+struct StatsCli {
+    #[clap(flatten)]
+    input: InputArgs,
+
+    /// Name of the output file, the statistics are printed if not given.
+    #[clap(short = 'o', long = "output")]
+    output: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct QueryCli {
+    #[clap(flatten)]
+    input: InputArgs,
+
+    /// Filter expression selecting the endpoints to list, e.g.
+    /// 'kind == writer && durability == transient'.
+    query: String,
+
+    /// Name of the output file, the endpoints are printed if not given.
+    #[clap(short = 'o', long = "output")]
+    output: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct WatchCli {
+    #[clap(flatten)]
+    input: InputArgs,
+
+    /// Time, in seconds, between checks for new lines.
+    #[clap(long = "interval", default_value = "1")]
+    interval: f64,
+
+    /// Serve Prometheus metrics of the logs on this address, e.g.
+    /// 0.0.0.0:9100, at /metrics.
+    #[clap(long = "metrics")]
+    metrics: Option<String>,
+}
+
+impl DdsiInputConfig for InputArgs {
+    fn get_filenames(&self) -> &[String] {
+        &self.filenames
+    }
+
+    fn get_sort(&self) -> SortOrder {
+        self.sort.unwrap_or_default()
+    }
+
+    fn get_filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    fn get_since(&self) -> Option<f64> {
+        self.since
+    }
+
+    fn get_until(&self) -> Option<f64> {
+        self.until
+    }
+
+    fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    fn get_jobs(&self) -> usize {
+        self.jobs.unwrap_or(1)
+    }
+
+    fn get_progress(&self) -> bool {
+        !self.no_progress
+    }
+
+    fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+}
+
+impl InputArgs {
+    /// Override the options with the ones given on the command line.
+    fn apply(&self, options: &mut InputOptions) {
+        if !self.filenames.is_empty() {
+            options.filenames = self.filenames.clone();
+        }
+        if let Some(sort) = self.sort {
+            options.sort = sort;
+        }
+        if self.filter.is_some() {
+            options.filter = self.filter.clone();
+        }
+        if self.since.is_some() {
+            options.since = self.since;
+        }
+        if self.until.is_some() {
+            options.until = self.until;
+        }
+        if let Some(jobs) = self.jobs {
+            options.jobs = jobs;
+        }
+        options.strict |= self.strict;
+        options.progress &= !self.no_progress;
+        options.quiet |= self.quiet;
+    }
+}
+
+impl SummaryCli {
+    /// Summary options from the configuration file, if any, overridden by
+    /// the command line.
+    fn to_options(&self) -> Result<SummaryOptions, Box<dyn Error>> {
+        let mut options = match &self.config {
+            Some(config) => SummaryOptions::from_json(BufReader::new(File::open(config)?))?,
+            None => SummaryOptions::default(),
+        };
+        self.input.apply(&mut options.input);
+        if let Some(output) = &self.output {
+            options.output = output.clone();
+        }
+        if self.json.is_some() {
+            options.json = self.json.clone();
+        }
+        if self.no_json {
+            options.json = None;
+        }
+        options.exports.extend(self.exports.iter().cloned());
+        if self.sqlite.is_some() {
+            options.sqlite = self.sqlite.clone();
+        }
+        if let Some(flap_window) = self.flap.flap_window {
+            options.flap_window = flap_window;
+        }
+        if let Some(flap_cycles) = self.flap.flap_cycles {
+            options.flap_cycles = flap_cycles;
+        }
+        Ok(options)
+    }
+}
+
+impl ddsi_log_parser::DdsiExportConfig for ExportCli {
+    fn get_input(&self) -> &dyn DdsiInputConfig {
+        &self.input
+    }
+
+    fn get_format(&self) -> ExportFormat {
+        self.format
+    }
+
+    fn get_output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+}
+
+impl ddsi_log_parser::DdsiEventsConfig for EventsCli {
+    fn get_input(&self) -> &dyn DdsiInputConfig {
+        &self.input
+    }
+
+    fn get_format(&self) -> EventFormat {
+        self.format
+    }
+
+    fn get_output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+}
+
+impl ddsi_log_parser::DdsiDiffConfig for DiffCli {
+    fn get_input(&self) -> &dyn DdsiInputConfig {
+        &self.input
+    }
+
+    fn get_at(&self) -> &[f64] {
+        &self.at
+    }
+
+    fn get_output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
 }
 
-impl ddsi_log_parser::DdsiLogConfig for Cli {
-    fn get_filename(&self) -> &str {
-        &self.filename
+impl ddsi_log_parser::DdsiCheckConfig for CheckCli {
+    fn get_input(&self) -> &dyn DdsiInputConfig {
+        &self.input
+    }
+
+    fn get_output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+
+    fn get_rules(&self) -> Option<&str> {
+        self.rules.as_deref()
     }
 
-    fn get_output(&self) -> &str {
-        &self.output
+    fn get_junit(&self) -> Option<&str> {
+        self.junit.as_deref()
+    }
+
+    fn get_flap_window(&self) -> f64 {
+        self.flap.flap_window.unwrap_or(DEFAULT_FLAP_WINDOW)
+    }
+
+    fn get_flap_cycles(&self) -> usize {
+        self.flap.flap_cycles.unwrap_or(DEFAULT_FLAP_CYCLES)
+    }
+}
+
+impl ddsi_log_parser::DdsiStatsConfig for StatsCli {
+    fn get_input(&self) -> &dyn DdsiInputConfig {
+        &self.input
+    }
+
+    fn get_output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+}
+
+impl ddsi_log_parser::DdsiQueryConfig for QueryCli {
+    fn get_input(&self) -> &dyn DdsiInputConfig {
+        &self.input
+    }
+
+    fn get_query(&self) -> &str {
+        &self.query
+    }
+
+    fn get_output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+}
+
+impl ddsi_log_parser::DdsiWatchConfig for WatchCli {
+    fn get_input(&self) -> &dyn DdsiInputConfig {
+        &self.input
+    }
+
+    fn get_interval(&self) -> f64 {
+        self.interval
+    }
+
+    fn get_metrics_address(&self) -> Option<&str> {
+        self.metrics.as_deref()
     }
 }
 
 fn main() {
+    // Entries skipped by non-strict parses are reported as warnings.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
     let args = Cli::parse();
 
-    if let Err(e) = ddsi_log_parser::run(&args) {
-        eprintln!("Application error: {:?}", e);
-        process::exit(1);
+    let result = match &args.command {
+        Command::Summary(summary) => summary
+            .to_options()
+            .and_then(|options| ddsi_log_parser::run(&options)),
+        Command::Export(export) => ddsi_log_parser::run_export(export),
+        Command::Events(events) => ddsi_log_parser::run_events(events),
+        Command::Diff(diff) => ddsi_log_parser::run_diff(diff),
+        Command::Check(check) => ddsi_log_parser::run_check(check),
+        Command::Stats(stats) => ddsi_log_parser::run_stats(stats),
+        Command::Query(query) => ddsi_log_parser::run_query(query),
+        Command::Watch(watch) => ddsi_log_parser::run_watch(watch),
+        #[cfg(feature = "tui")]
+        Command::Tui(input) => ddsi_log_parser::run_tui(input),
+    };
+
+    match result {
+        Ok(Outcome::Clean) => {}
+        Ok(Outcome::Findings) => process::exit(EXIT_FINDINGS),
+        Err(e) => {
+            eprintln!("Application error: {:?}", e);
+            process::exit(EXIT_ERROR);
+        }
     }
 }
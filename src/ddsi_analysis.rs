@@ -0,0 +1,56 @@
+use crate::ddsi_flow::FlowAnalysis;
+use crate::ddsi_log_regex::DdsiLogType;
+use crate::ddsi_problems::Problems;
+use crate::ddsi_reliability::ReliabilityAnalysis;
+use crate::ddsi_statistics::LineStatistics;
+use std::error::Error;
+
+/// Analyses computed over every line of a ddsi log, alongside the topology.
+#[derive(Debug, Default)]
+pub struct LogAnalysis {
+    pub statistics: LineStatistics,
+    pub problems: Problems,
+    pub reliability: ReliabilityAnalysis,
+    pub flow: FlowAnalysis,
+}
+
+impl LogAnalysis {
+    pub fn new() -> LogAnalysis {
+        LogAnalysis::default()
+    }
+
+    /// Account for a log line.
+    ///
+    /// Problems are looked for in every line, as some entries recognized
+    /// for the other analyses, e.g. `write_sample`, also report failures.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The log line.
+    /// * `matched` - Whether the line was recognized as a ddsi log entry.
+    ///
+    pub fn update(&mut self, line: &str, matched: bool) {
+        let is_problem = self.problems.update(line).is_some();
+        self.statistics.update(line, matched, is_problem);
+    }
+
+    /// Update the analyses with a log entry not updating the topology.
+    pub fn update_entry(&mut self, ddsi_log_type: &DdsiLogType) -> Result<(), Box<dyn Error>> {
+        self.reliability.update(ddsi_log_type)?;
+        self.flow.update(ddsi_log_type)
+    }
+
+    /// Name the log analysed, to trace the problems found back to it once
+    /// merged with the analyses of other logs.
+    pub fn set_log(&mut self, log: &str) {
+        self.problems.set_log(log);
+    }
+
+    /// Add the analysis of another log.
+    pub fn merge(&mut self, other: LogAnalysis) {
+        self.statistics.merge(other.statistics);
+        self.problems.merge(other.problems);
+        self.reliability.merge(other.reliability);
+        self.flow.merge(other.flow);
+    }
+}
@@ -0,0 +1,652 @@
+use crate::ddsi_analysis::LogAnalysis;
+use crate::ddsi_churn::ChurnAnalysis;
+use crate::ddsi_filter::Filter;
+use crate::ddsi_participant::{DdsiParticipant, Qos};
+use crate::ddsi_topology::{DdsiTopology, SortOrder};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+
+/// Name of the test suite in JUnit reports.
+const JUNIT_SUITE: &str = "ddsi_log_parser.check";
+
+/// Built-in checks run over a topology and the analysis of its logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckKind {
+    IncompatibleQos,
+    Flapping,
+    ReaderBehind,
+    Problem,
+}
+
+const CHECK_KINDS: [CheckKind; 4] = [
+    CheckKind::IncompatibleQos,
+    CheckKind::Flapping,
+    CheckKind::ReaderBehind,
+    CheckKind::Problem,
+];
+
+impl fmt::Display for CheckKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CheckKind::IncompatibleQos => "incompatible-qos",
+            CheckKind::Flapping => "flapping",
+            CheckKind::ReaderBehind => "reader-behind",
+            CheckKind::Problem => "problem",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Something wrong found in a topology or in the analysis of its logs.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    /// The check that produced the finding.
+    pub check: CheckKind,
+    /// What the finding is about, e.g. an endpoint guid.
+    pub subject: String,
+    pub message: String,
+}
+
+/// Expectations about a topology, loaded from a json rules file such as:
+///
+/// ```json
+/// {"rules": [
+///     {"name": "commands are read", "assert": "count",
+///      "select": "topic == \"Test_command_start\" && kind == reader", "min": 1},
+///     {"name": "data is reliable", "assert": "all",
+///      "select": "kind == writer && partition =~ \"^nile\\..*\\.data$\"",
+///      "require": "reliability == reliable"},
+///     {"name": "no incompatible QoS", "assert": "none", "check": "incompatible-qos"},
+///     {"name": "no lease expiry", "assert": "none", "check": "problem",
+///      "subject": "lease expired"}
+/// ]}
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "RawRule")]
+struct Rule {
+    name: String,
+    assertion: Assertion,
+}
+
+/// A rule as written in the rules file, with the fields of all the
+/// assertions. Unknown fields are rejected here and the fields of the
+/// assertion checked in `Rule::try_from`, as `deny_unknown_fields` does not
+/// work with a flattened `Assertion`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    name: String,
+    assert: AssertionKind,
+    select: Option<Filter>,
+    min: Option<usize>,
+    max: Option<usize>,
+    require: Option<Filter>,
+    check: Option<CheckKind>,
+    subject: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AssertionKind {
+    Count,
+    All,
+    None,
+}
+
+impl fmt::Display for AssertionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            AssertionKind::Count => "count",
+            AssertionKind::All => "all",
+            AssertionKind::None => "none",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl TryFrom<RawRule> for Rule {
+    type Error = String;
+
+    fn try_from(raw: RawRule) -> Result<Self, Self::Error> {
+        let allowed: &[&str] = match raw.assert {
+            AssertionKind::Count => &["select", "min", "max"],
+            AssertionKind::All => &["select", "require"],
+            AssertionKind::None => &["check", "subject"],
+        };
+        let given = [
+            ("select", raw.select.is_some()),
+            ("min", raw.min.is_some()),
+            ("max", raw.max.is_some()),
+            ("require", raw.require.is_some()),
+            ("check", raw.check.is_some()),
+            ("subject", raw.subject.is_some()),
+        ];
+        if let Some((field, _)) = given
+            .iter()
+            .find(|(field, is_given)| *is_given && !allowed.contains(field))
+        {
+            return Err(format!(
+                "rule {}: field `{}` does not apply to assert {}",
+                raw.name, field, raw.assert
+            ));
+        }
+
+        let name = raw.name;
+        let missing = |field: &str| format!("rule {}: missing field `{}`", name, field);
+        let assertion = match raw.assert {
+            AssertionKind::Count => {
+                if raw.min.is_none() && raw.max.is_none() {
+                    return Err(format!(
+                        "rule {}: a count assertion requires `min` or `max`",
+                        name
+                    ));
+                }
+                Assertion::Count {
+                    select: raw.select.ok_or_else(|| missing("select"))?,
+                    min: raw.min,
+                    max: raw.max,
+                }
+            }
+            AssertionKind::All => Assertion::All {
+                select: raw.select.ok_or_else(|| missing("select"))?,
+                require: raw.require.ok_or_else(|| missing("require"))?,
+            },
+            AssertionKind::None => Assertion::None {
+                check: raw.check.ok_or_else(|| missing("check"))?,
+                subject: raw.subject,
+            },
+        };
+        Ok(Rule { name, assertion })
+    }
+}
+
+#[derive(Debug)]
+enum Assertion {
+    /// The number of endpoints matching `select` is within bounds.
+    Count {
+        select: Filter,
+        min: Option<usize>,
+        max: Option<usize>,
+    },
+    /// All the endpoints matching `select` also match `require`.
+    All { select: Filter, require: Filter },
+    /// A built-in check has no finding, about `subject` if given.
+    None {
+        check: CheckKind,
+        subject: Option<String>,
+    },
+}
+
+impl Rules {
+    pub fn from_json<R: Read>(reader: R) -> Result<Rules, Box<dyn Error>> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+impl Default for Rules {
+    /// Expect no finding from any of the built-in checks.
+    fn default() -> Self {
+        Rules {
+            rules: CHECK_KINDS
+                .iter()
+                .map(|check| Rule {
+                    name: format!("no {}", check),
+                    assertion: Assertion::None {
+                        check: *check,
+                        subject: None,
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Outcome of a rule.
+#[derive(Debug, PartialEq)]
+struct RuleResult {
+    name: String,
+    failures: Vec<String>,
+}
+
+/// Outcome of all the rules checked over a topology.
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    results: Vec<RuleResult>,
+}
+
+/// An endpoint of the topology.
+struct Endpoint<'a> {
+    participant: &'a DdsiParticipant,
+    id: String,
+    is_writer: bool,
+    qos: &'a Qos,
+}
+
+impl Endpoint<'_> {
+    fn get_guid(&self) -> String {
+        format!("{}:{}", self.participant.get_system_id(), self.id)
+    }
+
+    fn matches(&self, filter: &Filter) -> bool {
+        filter.matches(self.participant, &self.id, self.is_writer, self.qos)
+    }
+}
+
+impl CheckReport {
+    /// Check rules against a topology.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - The rules to check.
+    /// * `ddsi_topology` - The topology to check.
+    /// * `analysis` - The analysis of the logs, if the topology was parsed
+    ///   from logs.
+    /// * `churn` - The churn analysis of the topology.
+    ///
+    pub fn new(
+        rules: &Rules,
+        ddsi_topology: &DdsiTopology,
+        analysis: Option<&LogAnalysis>,
+        churn: &ChurnAnalysis,
+    ) -> CheckReport {
+        let endpoints = get_endpoints(ddsi_topology);
+        let findings = find_all(&endpoints, analysis, churn);
+
+        let results = rules
+            .rules
+            .iter()
+            .map(|rule| RuleResult {
+                name: rule.name.clone(),
+                failures: rule.assertion.check(&endpoints, &findings),
+            })
+            .collect();
+        CheckReport { results }
+    }
+
+    /// Check if all the rules passed.
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|result| result.failures.is_empty())
+    }
+
+    fn get_n_failed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| !result.failures.is_empty())
+            .count()
+    }
+
+    pub fn summarize(&self) -> String {
+        let n_failed = self.get_n_failed();
+        let mut summary = format!("\t- Rules {}, {} failed:\n", self.results.len(), n_failed);
+
+        for result in &self.results {
+            if result.failures.is_empty() {
+                summary.push_str(&format!("\t\t- PASS {}\n", result.name));
+            } else {
+                summary.push_str(&format!(
+                    "\t\t- FAIL {}, {} failures:\n",
+                    result.name,
+                    result.failures.len()
+                ));
+                for failure in &result.failures {
+                    summary.push_str(&format!("\t\t\t- {}\n", failure));
+                }
+            }
+        }
+        summary
+    }
+
+    /// Render the report as JUnit XML, one test case per rule.
+    pub fn to_junit(&self) -> String {
+        let n_failed = self.get_n_failed();
+        let mut junit = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <testsuites>\n\
+            \t<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            JUNIT_SUITE,
+            self.results.len(),
+            n_failed
+        );
+
+        for result in &self.results {
+            junit.push_str(&format!(
+                "\t\t<testcase classname=\"{}\" name=\"{}\"",
+                JUNIT_SUITE,
+                escape_xml(&result.name)
+            ));
+            if result.failures.is_empty() {
+                junit.push_str("/>\n");
+            } else {
+                junit.push_str(&format!(
+                    ">\n\t\t\t<failure message=\"{} failures\">{}</failure>\n\t\t</testcase>\n",
+                    result.failures.len(),
+                    escape_xml(&result.failures.join("\n"))
+                ));
+            }
+        }
+        junit.push_str("\t</testsuite>\n</testsuites>\n");
+        junit
+    }
+}
+
+impl Assertion {
+    /// Return the failures of the assertion, empty if it holds.
+    fn check(&self, endpoints: &[Endpoint], findings: &[Finding]) -> Vec<String> {
+        match self {
+            Assertion::Count { select, min, max } => {
+                let count = endpoints
+                    .iter()
+                    .filter(|endpoint| endpoint.matches(select))
+                    .count();
+                let mut failures = vec![];
+                if let Some(min) = min.filter(|min| count < *min) {
+                    failures.push(format!(
+                        "{} endpoints match `{}`, expected at least {}",
+                        count, select, min
+                    ));
+                }
+                if let Some(max) = max.filter(|max| count > *max) {
+                    failures.push(format!(
+                        "{} endpoints match `{}`, expected at most {}",
+                        count, select, max
+                    ));
+                }
+                failures
+            }
+            Assertion::All { select, require } => endpoints
+                .iter()
+                .filter(|endpoint| endpoint.matches(select) && !endpoint.matches(require))
+                .map(|endpoint| {
+                    format!(
+                        "{} ({}) does not match `{}`",
+                        endpoint.get_guid(),
+                        endpoint.qos.get_topic(),
+                        require
+                    )
+                })
+                .collect(),
+            Assertion::None { check, subject } => findings
+                .iter()
+                .filter(|finding| {
+                    finding.check == *check
+                        && subject
+                            .as_ref()
+                            .is_none_or(|subject| *subject == finding.subject)
+                })
+                .map(|finding| format!("{}: {}", finding.subject, finding.message))
+                .collect(),
+        }
+    }
+}
+
+fn get_endpoints(ddsi_topology: &DdsiTopology) -> Vec<Endpoint<'_>> {
+    let mut endpoints = vec![];
+
+    for participant_id in ddsi_topology.get_participants_ids(SortOrder::Guid) {
+        let participant = ddsi_topology.get_participant(&participant_id).unwrap();
+
+        for id in participant.get_writers_id(SortOrder::Guid) {
+            let qos = participant.get_writer_qos(&id).unwrap();
+            endpoints.push(Endpoint {
+                participant,
+                id,
+                is_writer: true,
+                qos,
+            });
+        }
+        for id in participant.get_readers_id(SortOrder::Guid) {
+            let qos = participant.get_reader_qos(&id).unwrap();
+            endpoints.push(Endpoint {
+                participant,
+                id,
+                is_writer: false,
+                qos,
+            });
+        }
+    }
+    endpoints
+}
+
+/// Run all the built-in checks.
+fn find_all(
+    endpoints: &[Endpoint],
+    analysis: Option<&LogAnalysis>,
+    churn: &ChurnAnalysis,
+) -> Vec<Finding> {
+    let mut findings = find_incompatible_qos(endpoints);
+
+    for (guid, topic, cycles) in churn.get_flapping() {
+        findings.push(Finding {
+            check: CheckKind::Flapping,
+            subject: guid.clone(),
+            message: format!("{} cycles within the window on {}", cycles, topic),
+        });
+    }
+    for (participant_id, cycles) in churn.get_flapping_participants() {
+        findings.push(Finding {
+            check: CheckKind::Flapping,
+            subject: participant_id.clone(),
+            message: format!("{} cycles of all its endpoints within the window", cycles),
+        });
+    }
+
+    if let Some(analysis) = analysis {
+        for ((writer, reader), pair) in analysis.reliability.get_pairs() {
+            if pair.is_behind() {
+                findings.push(Finding {
+                    check: CheckKind::ReaderBehind,
+                    subject: format!("{} -> {}", writer, reader),
+                    message: format!(
+                        "behind in {} of {} ACKNACKs, max lag {}",
+                        pair.behind, pair.acknacks, pair.max_lag
+                    ),
+                });
+            }
+        }
+
+        for (kind, count) in analysis.problems.get_counts() {
+            findings.push(Finding {
+                check: CheckKind::Problem,
+                subject: kind.to_string(),
+                message: format!("{} occurrences in the logs", count),
+            });
+        }
+    }
+    findings
+}
+
+/// Report every alive writer/reader pair on the same topic and partition
+/// whose QoS do not match.
+fn find_incompatible_qos(endpoints: &[Endpoint]) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut writers: BTreeMap<&String, Vec<&Endpoint>> = BTreeMap::new();
+
+    for writer in endpoints
+        .iter()
+        .filter(|endpoint| endpoint.is_writer && endpoint.qos.is_alive())
+    {
+        writers
+            .entry(writer.qos.get_topic())
+            .or_default()
+            .push(writer);
+    }
+
+    for reader in endpoints
+        .iter()
+        .filter(|endpoint| !endpoint.is_writer && endpoint.qos.is_alive())
+    {
+        let topic_writers = match writers.get(reader.qos.get_topic()) {
+            Some(topic_writers) => topic_writers,
+            None => continue,
+        };
+        for writer in topic_writers {
+            if !writer.qos.partition_matches(reader.qos) {
+                continue;
+            }
+            let incompatible = writer.qos.incompatible_policies(reader.qos);
+            if !incompatible.is_empty() {
+                findings.push(Finding {
+                    check: CheckKind::IncompatibleQos,
+                    subject: format!("{} -> {}", writer.get_guid(), reader.get_guid()),
+                    message: format!(
+                        "incompatible {} on {}",
+                        incompatible.join(", "),
+                        reader.qos.get_topic()
+                    ),
+                });
+            }
+        }
+    }
+    findings
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::{READER_SEDP_ST0, WRITER_QOS};
+
+    fn check(rules: &Rules) -> CheckReport {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+        let mut analysis = LogAnalysis::new();
+
+        // The reader is transient and the writer best effort, incompatible.
+        for line in [
+            READER_SEDP_ST0.replace("durability=0,", "durability=1,"),
+            WRITER_QOS.replace("reliability=1:", "reliability=0:"),
+        ] {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(&line).unwrap())
+                .unwrap();
+        }
+        analysis.update(
+            "2022-01-23T14:11:52+0000 1642947112.403000/      recv: lease expired for participant 745ad3d:7b:1",
+            false,
+        );
+
+        let churn = ChurnAnalysis::new(&ddsi_topology, 60.0, 3).unwrap();
+        CheckReport::new(rules, &ddsi_topology, Some(&analysis), &churn)
+    }
+
+    #[test]
+    fn default_rules() {
+        let report = check(&Rules::default());
+
+        assert!(!report.is_success());
+        assert_eq!(
+            report.summarize(),
+            "\t- Rules 4, 2 failed:\n\
+            \t\t- FAIL no incompatible-qos, 1 failures:\n\
+            \t\t\t- 5bbed783:7b:1:3902 -> 745ad3d:7b:1:3b07: incompatible reliability, durability on Test_command_start_cc827fa3\n\
+            \t\t- PASS no flapping\n\
+            \t\t- PASS no reader-behind\n\
+            \t\t- FAIL no problem, 1 failures:\n\
+            \t\t\t- lease expired: 1 occurrences in the logs\n"
+        );
+    }
+
+    #[test]
+    fn rules_file() {
+        let rules = Rules::from_json(
+            r#"{"rules": [
+                {"name": "commands are read and written", "assert": "count",
+                 "select": "topic =~ \"^Test_command_\"", "min": 2, "max": 2},
+                {"name": "events are read", "assert": "count",
+                 "select": "topic =~ \"^Test_logevent_\" && kind == reader", "min": 1},
+                {"name": "commands are reliable", "assert": "all",
+                 "select": "kind == writer && partition =~ \"^nile\\..*\\.cmd$\"",
+                 "require": "reliability == reliable"},
+                {"name": "no <lease> expiry", "assert": "none", "check": "problem",
+                 "subject": "lease expired"},
+                {"name": "no malformed packet", "assert": "none", "check": "problem",
+                 "subject": "malformed packet"}
+            ]}"#
+            .as_bytes(),
+        )
+        .unwrap();
+        let report = check(&rules);
+
+        assert_eq!(
+            report
+                .results
+                .iter()
+                .map(|result| result.failures.len())
+                .collect::<Vec<usize>>(),
+            [0, 1, 1, 1, 0]
+        );
+        assert_eq!(
+            report.results[2].failures,
+            ["5bbed783:7b:1:3902 (Test_command_start_cc827fa3) does not match `reliability == reliable`"]
+        );
+
+        let junit = report.to_junit();
+        assert!(
+            junit.contains("<testsuite name=\"ddsi_log_parser.check\" tests=\"5\" failures=\"3\">")
+        );
+        assert!(junit.contains(
+            "<testcase classname=\"ddsi_log_parser.check\" name=\"no &lt;lease&gt; expiry\">"
+        ));
+        assert!(junit.contains(
+            "<failure message=\"1 failures\">0 endpoints match `topic =~ &quot;^Test_logevent_&quot; &amp;&amp; kind == reader`, expected at least 1</failure>"
+        ));
+    }
+
+    #[test]
+    fn invalid_rules() {
+        for (invalid, error) in [
+            (
+                r#"{"name": "x", "assert": "count", "select": "colour == red", "min": 1}"#,
+                "colour",
+            ),
+            (
+                r#"{"name": "x", "assert": "none", "check": "everything"}"#,
+                "everything",
+            ),
+            (
+                r#"{"name": "x", "assert": "count", "select": "kind == writer", "mni": 1}"#,
+                "unknown field `mni`",
+            ),
+            (
+                r#"{"name": "x", "assert": "count", "select": "kind == writer"}"#,
+                "rule x: a count assertion requires `min` or `max`",
+            ),
+            (
+                r#"{"name": "x", "assert": "all", "select": "kind == writer", "min": 1}"#,
+                "rule x: field `min` does not apply to assert all",
+            ),
+            (
+                r#"{"name": "x", "assert": "all", "select": "kind == writer"}"#,
+                "rule x: missing field `require`",
+            ),
+        ] {
+            let rules = format!(r#"{{"rules": [{}]}}"#, invalid);
+            let result = Rules::from_json(rules.as_bytes());
+            assert!(
+                result
+                    .as_ref()
+                    .is_err_and(|e| e.to_string().contains(error)),
+                "{}: {:?}",
+                invalid,
+                result
+            );
+        }
+    }
+}
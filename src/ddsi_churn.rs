@@ -0,0 +1,457 @@
+use crate::ddsi_participant::{DdsiParticipant, Qos};
+use crate::ddsi_topology::{DdsiTopology, SortOrder};
+use std::collections::BTreeMap;
+use std::{error::Error, fmt};
+
+/// Number of top offenders reported in the summary.
+const N_TOP: usize = 10;
+
+/// Churn and flapping analysis of a topology.
+///
+/// Churn events are the deletions and re-creations of endpoints, the first
+/// creation of an endpoint is not churn. An endpoint is flapping when at
+/// least `min_cycles` of its lifetimes end within `window` seconds.
+///
+/// Participant deletions are not logged, so a participant lifetime spans
+/// from the creation of one of its endpoints until all of them are deleted.
+/// A participant is flapping under the same conditions as an endpoint.
+#[derive(Debug)]
+pub struct ChurnAnalysis {
+    window: f64,
+    min_cycles: usize,
+    flapping: Vec<FlappingEndpoint>,
+    flapping_participants: Vec<FlappingParticipant>,
+    topics: BTreeMap<String, BTreeMap<i64, usize>>,
+    hosts: BTreeMap<String, BTreeMap<i64, usize>>,
+}
+
+#[derive(Debug)]
+pub struct InvalidFlapping {
+    window: f64,
+    min_cycles: usize,
+}
+
+impl Error for InvalidFlapping {}
+
+impl fmt::Display for InvalidFlapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid flapping window of {}s and {} cycles, expected a positive window and at least 1 cycle.",
+            self.window, self.min_cycles
+        )
+    }
+}
+
+#[derive(Debug)]
+struct FlappingEndpoint {
+    guid: String,
+    participant_id: String,
+    topic: String,
+    hostname: String,
+    max_cycles: usize,
+    total_cycles: usize,
+}
+
+#[derive(Debug)]
+struct FlappingParticipant {
+    participant_id: String,
+    hostname: String,
+    max_cycles: usize,
+    total_cycles: usize,
+}
+
+impl ChurnAnalysis {
+    /// Analyse the churn of a topology.
+    ///
+    /// # Arguments
+    ///
+    /// * `ddsi_topology` - The topology to analyse.
+    /// * `window` - Width, in seconds, of the window used both for flapping
+    ///   detection and to bin churn rates.
+    /// * `min_cycles` - Number of creation/deletion cycles within a window
+    ///   for an endpoint to be considered flapping.
+    ///
+    pub fn new(
+        ddsi_topology: &DdsiTopology,
+        window: f64,
+        min_cycles: usize,
+    ) -> Result<ChurnAnalysis, InvalidFlapping> {
+        ChurnAnalysis::check_parameters(window, min_cycles)?;
+
+        let mut analysis = ChurnAnalysis {
+            window,
+            min_cycles,
+            flapping: vec![],
+            flapping_participants: vec![],
+            topics: BTreeMap::new(),
+            hosts: BTreeMap::new(),
+        };
+
+        for participant_id in ddsi_topology.get_participants_ids(SortOrder::Guid) {
+            let participant = ddsi_topology.get_participant(&participant_id).unwrap();
+            let mut lifetimes = vec![];
+
+            for (id, qos) in endpoints(participant) {
+                lifetimes.extend(qos.get_lifetimes());
+                let churn_events = qos.get_created().iter().skip(1).chain(qos.get_deleted());
+
+                for timestamp in churn_events {
+                    let bin = (timestamp / window).floor() as i64;
+                    *analysis
+                        .topics
+                        .entry(qos.get_topic().clone())
+                        .or_default()
+                        .entry(bin)
+                        .or_default() += 1;
+                    *analysis
+                        .hosts
+                        .entry(participant.get_hostname().clone())
+                        .or_default()
+                        .entry(bin)
+                        .or_default() += 1;
+                }
+
+                let cycles_end: Vec<f64> = qos
+                    .get_lifetimes()
+                    .into_iter()
+                    .filter_map(|(_, deleted)| deleted)
+                    .collect();
+                let max_cycles = max_in_window(&cycles_end, window);
+
+                if max_cycles >= min_cycles {
+                    analysis.flapping.push(FlappingEndpoint {
+                        guid: format!("{}:{}", participant_id, id),
+                        participant_id: participant_id.clone(),
+                        topic: qos.get_topic().clone(),
+                        hostname: participant.get_hostname().clone(),
+                        max_cycles,
+                        total_cycles: cycles_end.len(),
+                    });
+                }
+            }
+
+            let cycles_end = participant_lifetimes_end(lifetimes);
+            let max_cycles = max_in_window(&cycles_end, window);
+
+            if max_cycles >= min_cycles {
+                analysis.flapping_participants.push(FlappingParticipant {
+                    participant_id: participant_id.clone(),
+                    hostname: participant.get_hostname().clone(),
+                    max_cycles,
+                    total_cycles: cycles_end.len(),
+                });
+            }
+        }
+
+        analysis.flapping.sort_by(|a, b| {
+            (b.max_cycles, b.total_cycles)
+                .cmp(&(a.max_cycles, a.total_cycles))
+                .then_with(|| a.guid.cmp(&b.guid))
+        });
+        analysis.flapping_participants.sort_by(|a, b| {
+            (b.max_cycles, b.total_cycles)
+                .cmp(&(a.max_cycles, a.total_cycles))
+                .then_with(|| a.participant_id.cmp(&b.participant_id))
+        });
+        Ok(analysis)
+    }
+
+    /// Check that the flapping window is a positive duration and that at
+    /// least one cycle is required, so that inputs are not parsed for
+    /// nothing.
+    pub fn check_parameters(window: f64, min_cycles: usize) -> Result<(), InvalidFlapping> {
+        if window.is_finite() && window > 0.0 && min_cycles >= 1 {
+            Ok(())
+        } else {
+            Err(InvalidFlapping { window, min_cycles })
+        }
+    }
+
+    /// Check if any churn was found.
+    pub fn is_empty(&self) -> bool {
+        self.topics.is_empty()
+    }
+
+    /// Return the flapping endpoints as (guid, topic, cycles within the
+    /// window), the most flapping first.
+    pub fn get_flapping(&self) -> Vec<(&String, &String, usize)> {
+        self.flapping
+            .iter()
+            .map(|endpoint| (&endpoint.guid, &endpoint.topic, endpoint.max_cycles))
+            .collect()
+    }
+
+    /// Return the flapping participants as (participant id, cycles within
+    /// the window), the most flapping first.
+    pub fn get_flapping_participants(&self) -> Vec<(&String, usize)> {
+        self.flapping_participants
+            .iter()
+            .map(|participant| (&participant.participant_id, participant.max_cycles))
+            .collect()
+    }
+
+    pub fn summarize(&self) -> String {
+        let mut summary = format!(
+            "\t- Churn, {} flapping participants and {} flapping endpoints ({} cycles within {}s):\n",
+            self.flapping_participants.len(),
+            self.flapping.len(),
+            self.min_cycles,
+            self.window
+        );
+
+        for participant in self.flapping_participants.iter().take(N_TOP) {
+            summary.push_str(&format!(
+                "\t\t- {}@{}: {} cycles within {}s, {} in total\n",
+                participant.participant_id,
+                participant.hostname,
+                participant.max_cycles,
+                self.window,
+                participant.total_cycles
+            ));
+        }
+
+        for endpoint in self.flapping.iter().take(N_TOP) {
+            summary.push_str(&format!(
+                "\t\t- {} ({}@{}): {} cycles within {}s, {} in total\n",
+                endpoint.guid,
+                endpoint.topic,
+                endpoint.hostname,
+                endpoint.max_cycles,
+                self.window,
+                endpoint.total_cycles
+            ));
+        }
+
+        let mut participants: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        for endpoint in &self.flapping {
+            *participants
+                .entry((&endpoint.participant_id, &endpoint.hostname))
+                .or_default() += 1;
+        }
+        let mut participants: Vec<((&str, &str), usize)> = participants.into_iter().collect();
+        participants.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        summary.push_str(&format!(
+            "\t\t- Participants with flapping endpoints {}:\n",
+            participants.len()
+        ));
+        for ((participant_id, hostname), n_endpoints) in participants.into_iter().take(N_TOP) {
+            summary.push_str(&format!(
+                "\t\t\t- {}@{}: {} endpoints\n",
+                participant_id, hostname, n_endpoints
+            ));
+        }
+
+        summary.push_str(&self.summarize_rates("topics", &self.topics));
+        summary.push_str(&self.summarize_rates("hosts", &self.hosts));
+        summary
+    }
+
+    fn summarize_rates(
+        &self,
+        name: &str,
+        rates: &BTreeMap<String, BTreeMap<i64, usize>>,
+    ) -> String {
+        let mut top: Vec<(&String, usize, f64, usize, usize)> = rates
+            .iter()
+            .map(|(key, bins)| {
+                let series = self.to_series(bins);
+                let total = series.iter().map(|(_, count)| count).sum();
+                let (peak_start, peak) = series
+                    .iter()
+                    .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.total_cmp(&a.0)))
+                    .copied()
+                    .unwrap_or_default();
+                (key, total, peak_start, peak, series.len())
+            })
+            .collect();
+        top.sort_by(|a, b| (b.3, b.1).cmp(&(a.3, a.1)).then_with(|| a.0.cmp(b.0)));
+
+        let mut summary = format!("\t\t- Top {} by churn:\n", name);
+        for (key, total, peak_start, peak, n_windows) in top.into_iter().take(N_TOP) {
+            summary.push_str(&format!(
+                "\t\t\t- {}: {} events over {} windows, peak {} events/{}s at {}\n",
+                key, total, n_windows, peak, self.window, peak_start
+            ));
+        }
+        summary
+    }
+
+    /// Return a churn rate over time, as the number of churn events per
+    /// window, indexed by window start time.
+    fn to_series(&self, bins: &BTreeMap<i64, usize>) -> Vec<(f64, usize)> {
+        bins.iter()
+            .map(|(bin, count)| (*bin as f64 * self.window, *count))
+            .collect()
+    }
+}
+
+/// Return all the readers and writers of a participant.
+fn endpoints(participant: &DdsiParticipant) -> Vec<(String, &Qos)> {
+    let readers = participant
+        .get_readers_id(SortOrder::Guid)
+        .into_iter()
+        .map(|id| {
+            let qos = participant.get_reader_qos(&id).unwrap();
+            (id, qos)
+        });
+    let writers = participant
+        .get_writers_id(SortOrder::Guid)
+        .into_iter()
+        .map(|id| {
+            let qos = participant.get_writer_qos(&id).unwrap();
+            (id, qos)
+        });
+    readers.chain(writers).collect()
+}
+
+/// Return the end of the lifetimes of a participant, when all its endpoints
+/// were deleted, from the lifetimes of its endpoints.
+fn participant_lifetimes_end(mut lifetimes: Vec<(f64, Option<f64>)>) -> Vec<f64> {
+    lifetimes.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut ends = vec![];
+    let mut alive_until: Option<Option<f64>> = None;
+    for (created, deleted) in lifetimes {
+        alive_until = match alive_until {
+            // Alive until the end of the log.
+            Some(None) => Some(None),
+            Some(Some(end)) if created <= end => Some(deleted.map(|deleted| deleted.max(end))),
+            Some(Some(end)) => {
+                ends.push(end);
+                Some(deleted)
+            }
+            None => Some(deleted),
+        };
+    }
+    if let Some(Some(end)) = alive_until {
+        ends.push(end);
+    }
+    ends
+}
+
+/// Return the maximum number of sorted timestamps within any window.
+fn max_in_window(timestamps: &[f64], window: f64) -> usize {
+    let mut start = 0;
+    let mut max = 0;
+
+    for (end, timestamp) in timestamps.iter().enumerate() {
+        while timestamp - timestamps[start] > window {
+            start += 1;
+        }
+        max = max.max(end - start + 1);
+    }
+    max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::READER_SEDP_ST0;
+
+    const READER_SEDP_ST3: &str = "2022-01-23T14:11:51+0000 1642947089.995674/dq.builtin: SEDP ST3 745ad3d:7b:1:3b07delete_proxy_reader (745ad3d:7b:1:3b07) - deleting";
+
+    /// Return a topology where the reader is created and deleted `n_cycles`
+    /// times, `period` seconds apart.
+    fn flapping_topology(n_cycles: usize, period: f64) -> DdsiTopology {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        for cycle in 0..n_cycles {
+            let offset = cycle as f64 * period;
+            for (line, timestamp) in [
+                (READER_SEDP_ST0, "1642947089.895283"),
+                (READER_SEDP_ST3, "1642947089.995674"),
+            ] {
+                let shifted = format!("{:.6}", timestamp.parse::<f64>().unwrap() + offset);
+                let line = line.replace(timestamp, &shifted);
+                ddsi_topology
+                    .update(ddsi_log_regex.parse(&line).unwrap())
+                    .unwrap();
+            }
+        }
+        ddsi_topology
+    }
+
+    #[test]
+    fn flapping_endpoint() {
+        let analysis = ChurnAnalysis::new(&flapping_topology(4, 1.0), 60.0, 3).unwrap();
+
+        assert_eq!(analysis.flapping.len(), 1);
+        assert_eq!(analysis.flapping[0].guid, "745ad3d:7b:1:3b07");
+        assert_eq!(analysis.flapping[0].max_cycles, 4);
+        assert_eq!(analysis.flapping[0].total_cycles, 4);
+
+        // 4 deletions and 3 re-creations.
+        let churn: usize = analysis
+            .to_series(&analysis.topics["Test_command_start_cc827fa3"])
+            .iter()
+            .map(|(_, count)| count)
+            .sum();
+        assert_eq!(churn, 7);
+        assert_eq!(analysis.to_series(&analysis.hosts["172.17.0.4"]).len(), 1);
+    }
+
+    #[test]
+    fn flapping_participant() {
+        let analysis = ChurnAnalysis::new(&flapping_topology(4, 1.0), 60.0, 3).unwrap();
+
+        assert_eq!(
+            analysis.get_flapping_participants(),
+            [(&String::from("745ad3d:7b:1"), 4)]
+        );
+        assert!(analysis.summarize().starts_with(
+            "\t- Churn, 1 flapping participants and 1 flapping endpoints (3 cycles within 60s):\n\
+            \t\t- 745ad3d:7b:1@172.17.0.4: 4 cycles within 60s, 4 in total\n"
+        ));
+    }
+
+    #[test]
+    fn participant_lifetimes() {
+        // Overlapping endpoints keep the participant alive.
+        assert_eq!(
+            participant_lifetimes_end(vec![(0.0, Some(2.0)), (1.0, Some(3.0)), (5.0, Some(6.0))]),
+            [3.0, 6.0]
+        );
+        // An endpoint alive at the end of the log ends no lifetime.
+        assert_eq!(
+            participant_lifetimes_end(vec![(0.0, Some(2.0)), (1.0, None), (5.0, Some(6.0))]),
+            Vec::<f64>::new()
+        );
+        assert_eq!(
+            participant_lifetimes_end(vec![(0.0, Some(2.0)), (3.0, None)]),
+            [2.0]
+        );
+    }
+
+    #[test]
+    fn slow_cycles_are_not_flapping() {
+        let analysis = ChurnAnalysis::new(&flapping_topology(4, 100.0), 60.0, 3).unwrap();
+
+        assert!(analysis.flapping.is_empty());
+        assert!(analysis.flapping_participants.is_empty());
+        assert!(!analysis.is_empty());
+        assert_eq!(
+            analysis
+                .to_series(&analysis.topics["Test_command_start_cc827fa3"])
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn invalid_flapping() {
+        for (window, min_cycles) in [(0.0, 3), (-1.0, 3), (f64::NAN, 3), (60.0, 0)] {
+            assert!(ChurnAnalysis::new(&flapping_topology(4, 1.0), window, min_cycles).is_err());
+        }
+    }
+
+    #[test]
+    fn max_timestamps_in_window() {
+        assert_eq!(max_in_window(&[], 10.0), 0);
+        assert_eq!(max_in_window(&[0.0, 5.0, 10.0, 30.0], 10.0), 3);
+        assert_eq!(max_in_window(&[0.0, 20.0, 40.0], 10.0), 1);
+    }
+}
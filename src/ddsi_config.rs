@@ -0,0 +1,169 @@
+use crate::ddsi_topology::DdsiTopology;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Value reported for configuration entries a host did not log.
+const UNSET: &str = "<unset>";
+
+/// Configuration entries whose value differs between several logs.
+#[derive(Debug, Default)]
+pub struct ConfigDifferences {
+    /// Logs compared, along with the address of their host.
+    logs: Vec<(String, String)>,
+    differences: BTreeMap<String, BTreeMap<String, Option<String>>>,
+}
+
+impl ConfigDifferences {
+    /// Compare the configurations logged by the nodes of several logs, given
+    /// as (log name, topology). Logs without configuration are ignored.
+    pub fn new(logs: &[(&str, &DdsiTopology)]) -> ConfigDifferences {
+        let configs: Vec<(&str, &DdsiTopology, &BTreeMap<String, String>)> = logs
+            .iter()
+            .filter_map(|(log, ddsi_topology)| {
+                Some((*log, *ddsi_topology, ddsi_topology.get_config()?))
+            })
+            .collect();
+
+        let keys: BTreeSet<&String> = configs
+            .iter()
+            .flat_map(|(_, _, config)| config.keys())
+            .collect();
+
+        let mut differences = BTreeMap::new();
+        for key in keys {
+            let values: BTreeMap<String, Option<String>> = configs
+                .iter()
+                .map(|(log, _, config)| (String::from(*log), config.get(key).cloned()))
+                .collect();
+
+            let distinct: BTreeSet<&Option<String>> = values.values().collect();
+            if distinct.len() > 1 {
+                differences.insert(key.clone(), values);
+            }
+        }
+
+        ConfigDifferences {
+            logs: configs
+                .into_iter()
+                .map(|(log, ddsi_topology, _)| {
+                    (String::from(log), ddsi_topology.get_own_ip().clone())
+                })
+                .collect(),
+            differences,
+        }
+    }
+
+    /// Check if the configuration of more than one log was compared.
+    pub fn is_comparable(&self) -> bool {
+        self.logs.len() > 1
+    }
+
+    pub fn summarize(&self) -> String {
+        let logs: Vec<String> = self
+            .logs
+            .iter()
+            .map(|(log, host)| format!("{} ({})", log, host))
+            .collect();
+        let mut summary = format!(
+            "\t- Configuration differences between {} logs [{}], {} entries:\n",
+            self.logs.len(),
+            logs.join(", "),
+            self.differences.len()
+        );
+
+        for (key, values) in &self.differences {
+            summary.push_str(&format!("\t\t- {}:\n", key));
+            for (log, value) in values {
+                summary.push_str(&format!(
+                    "\t\t\t- {}: {}\n",
+                    log,
+                    value.as_deref().unwrap_or(UNSET)
+                ));
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+
+    fn topology(own_ip: &str, config: &[&str]) -> DdsiTopology {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        let own_ip = format!(
+            "2022-01-23T14:08:13+0000 1642946893.209853/      main: ownip: {}",
+            own_ip
+        );
+        let config = config.iter().map(|entry| {
+            format!(
+                "2022-01-23T14:08:13+0000 1642946893.209000/      main: config: {}",
+                entry
+            )
+        });
+
+        for line in std::iter::once(own_ip).chain(config) {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(&line).unwrap())
+                .unwrap();
+        }
+        ddsi_topology
+    }
+
+    #[test]
+    fn config_differences() {
+        let [first, second, third] = [
+            topology(
+                "172.17.0.3",
+                &[
+                    "Domain/Discovery/Ports/Base: 7400 {}",
+                    "Domain/General/NetworkInterfaceAddress/#text: eth0 {0}",
+                    "Domain/Tracing/Verbosity: finest {0}",
+                ],
+            ),
+            topology(
+                "172.17.0.4",
+                &[
+                    "Domain/Discovery/Ports/Base: 7400 {}",
+                    "Domain/General/NetworkInterfaceAddress/#text: auto {}",
+                ],
+            ),
+            topology("172.17.0.5", &[]),
+        ];
+
+        let differences =
+            ConfigDifferences::new(&[("a.log", &first), ("b.log", &second), ("c.log", &third)]);
+
+        assert!(differences.is_comparable());
+        assert_eq!(differences.differences.len(), 2);
+        assert_eq!(
+            differences.summarize(),
+            "\t- Configuration differences between 2 logs [a.log (172.17.0.3), b.log (172.17.0.4)], 2 entries:\n\
+            \t\t- Domain/General/NetworkInterfaceAddress/#text:\n\
+            \t\t\t- a.log: eth0\n\
+            \t\t\t- b.log: auto\n\
+            \t\t- Domain/Tracing/Verbosity:\n\
+            \t\t\t- a.log: finest\n\
+            \t\t\t- b.log: <unset>\n"
+        );
+    }
+
+    #[test]
+    fn config_differences_on_same_host() {
+        let first = topology("unkwnown", &["Domain/Tracing/Verbosity: finest {0}"]);
+        let second = topology("unkwnown", &["Domain/Tracing/Verbosity: config {0}"]);
+
+        let differences = ConfigDifferences::new(&[("a.log", &first), ("b.log", &second)]);
+
+        assert!(differences.is_comparable());
+        assert_eq!(
+            differences.differences["Domain/Tracing/Verbosity"],
+            BTreeMap::from([
+                (String::from("a.log"), Some(String::from("finest"))),
+                (String::from("b.log"), Some(String::from("config")))
+            ])
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use serde::ser::{Serialize, Serializer};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Number of threads reported in the summary.
+const N_TOP: usize = 10;
+
+/// CPU time consumed by the ddsi2 threads of each log, as series of
+/// (timestamp, cumulative CPU time) samples from `thread_cputime` lines.
+///
+/// Series are kept per log rather than per host, as the counters of two
+/// processes on the same host are unrelated.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(from = "SerializedCpuTime")]
+pub struct ThreadsCpuTime {
+    logs: BTreeMap<String, LogCpuTime>,
+}
+
+/// CPU time samples of the threads of a log.
+#[derive(Debug, Default, Clone, serde::Serialize, Deserialize)]
+struct LogCpuTime {
+    /// Address of the host of the log.
+    host: String,
+    threads: BTreeMap<String, Vec<(f64, f64)>>,
+}
+
+/// CPU time as serialized by the current and previous schema versions.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerializedCpuTime {
+    Logs(BTreeMap<String, LogCpuTime>),
+    /// Schema version 1, keyed by host.
+    Hosts(BTreeMap<String, BTreeMap<String, Vec<(f64, f64)>>>),
+}
+
+impl From<SerializedCpuTime> for ThreadsCpuTime {
+    fn from(serialized: SerializedCpuTime) -> Self {
+        let logs = match serialized {
+            SerializedCpuTime::Logs(logs) => logs,
+            SerializedCpuTime::Hosts(hosts) => hosts
+                .into_iter()
+                .map(|(host, threads)| (host.clone(), LogCpuTime { host, threads }))
+                .collect(),
+        };
+        ThreadsCpuTime { logs }
+    }
+}
+
+/// CPU usage of a thread between two samples.
+#[derive(Debug, PartialEq)]
+pub struct CpuUsage {
+    pub start: f64,
+    pub end: f64,
+    /// Fraction of one CPU used over the interval.
+    pub usage: f64,
+}
+
+impl ThreadsCpuTime {
+    /// Add a CPU time sample of a thread of the log being parsed, until it
+    /// is named with `set_log`.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Host running the thread.
+    /// * `thread` - Name of the thread.
+    /// * `timestamp` - Time of the sample, in seconds.
+    /// * `cputime` - CPU time consumed by the thread so far, in seconds.
+    ///
+    pub fn add(&mut self, host: &str, thread: &str, timestamp: f64, cputime: f64) {
+        let log = self.logs.entry(String::new()).or_default();
+        log.host = String::from(host);
+        log.threads
+            .entry(String::from(thread))
+            .or_default()
+            .push((timestamp, cputime));
+    }
+
+    /// Name the log the samples added so far were parsed from.
+    pub fn set_log(&mut self, log: &str) {
+        if let Some(samples) = self.logs.remove("") {
+            self.logs.insert(String::from(log), samples);
+        }
+    }
+
+    /// Add the samples parsed from another log.
+    pub fn merge(&mut self, other: ThreadsCpuTime) {
+        for (log, other_log) in other.logs {
+            let own_log = self.logs.entry(log).or_default();
+            own_log.host = other_log.host;
+            for (thread, samples) in other_log.threads {
+                let own_samples = own_log.threads.entry(thread).or_default();
+                own_samples.extend(samples);
+                own_samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+                own_samples.dedup();
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.logs.is_empty()
+    }
+
+    /// Return the CPU usage series of every thread, as (log, host, thread,
+    /// usage), sorted by log and thread.
+    pub fn get_usage(&self) -> Vec<(&String, &String, &String, Vec<CpuUsage>)> {
+        let mut usage = vec![];
+
+        for (log, log_cputime) in &self.logs {
+            for (thread, samples) in &log_cputime.threads {
+                let series = samples
+                    .windows(2)
+                    .filter(|pair| pair[1].0 > pair[0].0)
+                    .map(|pair| CpuUsage {
+                        start: pair[0].0,
+                        end: pair[1].0,
+                        usage: cputime_delta(pair[0].1, pair[1].1) / (pair[1].0 - pair[0].0),
+                    })
+                    .collect();
+                usage.push((log, &log_cputime.host, thread, series));
+            }
+        }
+        usage
+    }
+
+    pub fn summarize(&self) -> String {
+        let mut threads: Vec<(String, f64, f64, Option<&CpuUsage>)> = vec![];
+        let usage = self.get_usage();
+
+        for (log, host, thread, series) in &usage {
+            let samples = &self.logs[*log].threads[*thread];
+            let (first, last) = (samples[0], samples[samples.len() - 1]);
+            let total = samples
+                .windows(2)
+                .map(|pair| cputime_delta(pair[0].1, pair[1].1))
+                .sum();
+            let peak = series.iter().max_by(|a, b| a.usage.total_cmp(&b.usage));
+            threads.push((
+                format!("{}/{}", get_label(log, host), thread),
+                total,
+                last.0 - first.0,
+                peak,
+            ));
+        }
+        threads.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut summary = format!("\t- Thread CPU time, {} threads:\n", threads.len());
+        for (thread, total, duration, peak) in threads.into_iter().take(N_TOP) {
+            summary.push_str(&format!(
+                "\t\t- {}: {:.3}s over {:.3}s",
+                thread, total, duration
+            ));
+            if duration > 0.0 {
+                summary.push_str(&format!(" ({:.1}%)", 100.0 * total / duration));
+            }
+            if let Some(peak) = peak {
+                summary.push_str(&format!(
+                    ", peak {:.1}% between {:.6} and {:.6}",
+                    100.0 * peak.usage,
+                    peak.start,
+                    peak.end
+                ));
+            }
+            summary.push('\n');
+        }
+        summary
+    }
+}
+
+/// Return the label of the threads of a log, naming its host.
+fn get_label(log: &str, host: &str) -> String {
+    if log.is_empty() {
+        String::from(host)
+    } else {
+        format!("{} ({})", log, host)
+    }
+}
+
+/// Return the CPU time consumed between two samples of a thread. The
+/// counter going backwards means it was reset, e.g. by a restart of the
+/// process, so the time consumed is counted from zero.
+fn cputime_delta(before: f64, after: f64) -> f64 {
+    if after >= before {
+        after - before
+    } else {
+        after
+    }
+}
+
+impl Serialize for ThreadsCpuTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.logs.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_usage() {
+        let mut cputime = ThreadsCpuTime::default();
+        cputime.add("172.17.0.3", "recv", 10.0, 1.0);
+        cputime.add("172.17.0.3", "recv", 12.0, 2.0);
+        cputime.add("172.17.0.3", "recv", 14.0, 2.5);
+        cputime.add("172.17.0.3", "tev", 10.0, 0.0);
+
+        let usage = cputime.get_usage();
+
+        assert_eq!(usage.len(), 2);
+        assert_eq!(
+            usage[0].3,
+            vec![
+                CpuUsage {
+                    start: 10.0,
+                    end: 12.0,
+                    usage: 0.5
+                },
+                CpuUsage {
+                    start: 12.0,
+                    end: 14.0,
+                    usage: 0.25
+                },
+            ]
+        );
+        assert!(usage[1].3.is_empty());
+
+        let summary = cputime.summarize();
+        assert!(summary.starts_with(
+            "\t- Thread CPU time, 2 threads:\n\
+            \t\t- 172.17.0.3/recv: 1.500s over 4.000s (37.5%), peak 50.0% between 10.000000 and 12.000000\n"
+        ));
+    }
+
+    #[test]
+    fn counter_reset() {
+        let mut cputime = ThreadsCpuTime::default();
+        cputime.add("172.17.0.3", "recv", 10.0, 5.0);
+        cputime.add("172.17.0.3", "recv", 12.0, 6.0);
+        cputime.add("172.17.0.3", "recv", 14.0, 0.5);
+
+        let usage = cputime.get_usage();
+
+        assert_eq!(usage[0].3[1].usage, 0.25);
+        assert!(cputime
+            .summarize()
+            .contains("172.17.0.3/recv: 1.500s over 4.000s"));
+    }
+
+    #[test]
+    fn merge_logs_of_same_host() {
+        let mut cputime = ThreadsCpuTime::default();
+        cputime.add("172.17.0.3", "recv", 10.0, 100.0);
+        cputime.add("172.17.0.3", "recv", 14.0, 102.0);
+        cputime.set_log("a.log");
+        let mut other = ThreadsCpuTime::default();
+        other.add("172.17.0.3", "recv", 12.0, 1.0);
+        other.add("172.17.0.3", "recv", 16.0, 1.5);
+        other.set_log("b.log");
+
+        cputime.merge(other);
+
+        // The lower counter of the other process is not taken for a reset.
+        let usage = cputime.get_usage();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].3[0].usage, 0.5);
+        assert_eq!(usage[1].3[0].usage, 0.125);
+        let summary = cputime.summarize();
+        assert!(summary.contains("\t\t- a.log (172.17.0.3)/recv: 2.000s over 4.000s (50.0%)"));
+        assert!(summary.contains("\t\t- b.log (172.17.0.3)/recv: 0.500s over 4.000s (12.5%)"));
+    }
+
+    #[test]
+    fn load_cputime_keyed_by_host() {
+        let cputime: ThreadsCpuTime =
+            serde_json::from_str(r#"{"172.17.0.3": {"recv": [[10.0, 1.0], [12.0, 2.0]]}}"#)
+                .unwrap();
+
+        let usage = cputime.get_usage();
+        assert_eq!(usage[0].1, "172.17.0.3");
+        assert_eq!(usage[0].3[0].usage, 0.5);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::ddsi_participant::{DdsiParticipant, Qos};
+use crate::ddsi_topology::{DdsiTopology, SortOrder};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Differences between two topologies, e.g. a good and a broken run of the
+/// same scenario or the same log at two different times.
+#[derive(Debug, Default)]
+pub struct DdsiTopologyDiff {
+    participants_appeared: Vec<String>,
+    participants_disappeared: Vec<String>,
+    readers: EndpointsDiff,
+    writers: EndpointsDiff,
+}
+
+/// Differences between the readers or writers of two topologies.
+///
+/// Endpoints are identified by their full id (system id and reader/writer
+/// id) and only endpoints that are alive are compared.
+#[derive(Debug, Default)]
+struct EndpointsDiff {
+    appeared: Vec<(String, String)>,
+    disappeared: Vec<(String, String)>,
+    changed: Vec<(String, Vec<String>)>,
+}
+
+impl DdsiTopologyDiff {
+    /// Compute the differences going from the `before` to the `after`
+    /// topology.
+    pub fn new(before: &DdsiTopology, after: &DdsiTopology) -> DdsiTopologyDiff {
+        let mut diff = DdsiTopologyDiff::default();
+
+        let participants_ids: BTreeSet<String> = before
+            .get_participants_ids(SortOrder::Guid)
+            .into_iter()
+            .chain(after.get_participants_ids(SortOrder::Guid))
+            .collect();
+
+        for participant_id in participants_ids {
+            let participant_before = before.get_participant(&participant_id);
+            let participant_after = after.get_participant(&participant_id);
+
+            match (participant_before, participant_after) {
+                (None, Some(_)) => diff.participants_appeared.push(participant_id.clone()),
+                (Some(_), None) => diff.participants_disappeared.push(participant_id.clone()),
+                _ => {}
+            }
+
+            diff.readers.update(
+                &participant_id,
+                participant_before.map(|participant| readers(participant)),
+                participant_after.map(|participant| readers(participant)),
+            );
+            diff.writers.update(
+                &participant_id,
+                participant_before.map(|participant| writers(participant)),
+                participant_after.map(|participant| writers(participant)),
+            );
+        }
+        diff
+    }
+
+    /// Check if the topologies are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.participants_appeared.is_empty()
+            && self.participants_disappeared.is_empty()
+            && self.readers.is_empty()
+            && self.writers.is_empty()
+    }
+
+    pub fn summarize(&self) -> String {
+        let mut summary = format!(
+            "\t- Participants appeared {}: {:?}.\n\
+            \t- Participants disappeared {}: {:?}.\n",
+            self.participants_appeared.len(),
+            self.participants_appeared,
+            self.participants_disappeared.len(),
+            self.participants_disappeared,
+        );
+        summary.push_str(&self.readers.summarize("Readers"));
+        summary.push_str(&self.writers.summarize("Writers"));
+        summary
+    }
+}
+
+impl EndpointsDiff {
+    fn update(
+        &mut self,
+        participant_id: &str,
+        before: Option<BTreeMap<String, &Qos>>,
+        after: Option<BTreeMap<String, &Qos>>,
+    ) {
+        let before = before.unwrap_or_default();
+        let after = after.unwrap_or_default();
+
+        for (id, qos) in &after {
+            match before.get(id) {
+                Some(before_qos) => {
+                    let changes = qos_changes(before_qos, qos);
+                    if !changes.is_empty() {
+                        self.changed
+                            .push((format!("{}:{}", participant_id, id), changes));
+                    }
+                }
+                None => self
+                    .appeared
+                    .push((format!("{}:{}", participant_id, id), describe(qos))),
+            }
+        }
+
+        for (id, qos) in &before {
+            if !after.contains_key(id) {
+                self.disappeared
+                    .push((format!("{}:{}", participant_id, id), describe(qos)));
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.appeared.is_empty() && self.disappeared.is_empty() && self.changed.is_empty()
+    }
+
+    fn summarize(&self, name: &str) -> String {
+        let mut summary = format!("\t- {} appeared {}:\n", name, self.appeared.len());
+        for (id, description) in &self.appeared {
+            summary.push_str(&format!("\t\t- {}: {}\n", id, description));
+        }
+
+        summary.push_str(&format!(
+            "\t- {} disappeared {}:\n",
+            name,
+            self.disappeared.len()
+        ));
+        for (id, description) in &self.disappeared {
+            summary.push_str(&format!("\t\t- {}: {}\n", id, description));
+        }
+
+        summary.push_str(&format!("\t- {} changed {}:\n", name, self.changed.len()));
+        for (id, changes) in &self.changed {
+            summary.push_str(&format!("\t\t- {}:\n", id));
+            for change in changes {
+                summary.push_str(&format!("\t\t\t- {}\n", change));
+            }
+        }
+        summary
+    }
+}
+
+/// Return the alive readers of a participant, by id.
+fn readers(participant: &DdsiParticipant) -> BTreeMap<String, &Qos> {
+    participant
+        .get_readers_id(SortOrder::Guid)
+        .into_iter()
+        .filter_map(|id| {
+            let qos = participant.get_reader_qos(&id)?;
+            qos.is_alive().then_some((id, qos))
+        })
+        .collect()
+}
+
+/// Return the alive writers of a participant, by id.
+fn writers(participant: &DdsiParticipant) -> BTreeMap<String, &Qos> {
+    participant
+        .get_writers_id(SortOrder::Guid)
+        .into_iter()
+        .filter_map(|id| {
+            let qos = participant.get_writer_qos(&id)?;
+            qos.is_alive().then_some((id, qos))
+        })
+        .collect()
+}
+
+fn describe(qos: &Qos) -> String {
+    format!("{} ({})", qos.get_topic(), qos.get_partition())
+}
+
+/// Return a human readable list of the differences between two QoS.
+fn qos_changes(before: &Qos, after: &Qos) -> Vec<String> {
+    let mut changes = vec![];
+
+    if before.get_topic() != after.get_topic() {
+        changes.push(format!(
+            "topic: {} -> {}",
+            before.get_topic(),
+            after.get_topic()
+        ));
+    }
+    if before.get_partition() != after.get_partition() {
+        changes.push(format!(
+            "partition: {} -> {}",
+            before.get_partition(),
+            after.get_partition()
+        ));
+    }
+
+    let policies: BTreeSet<&String> = before
+        .get_policies()
+        .keys()
+        .chain(after.get_policies().keys())
+        .collect();
+
+    for policy in policies {
+        let before_value = before.get_policies().get(policy);
+        let after_value = after.get_policies().get(policy);
+        if before_value != after_value {
+            changes.push(format!(
+                "{}: {} -> {}",
+                policy,
+                before_value.map_or("-", |value| value.as_str()),
+                after_value.map_or("-", |value| value.as_str()),
+            ));
+        }
+    }
+
+    if before.get_locators() != after.get_locators() {
+        changes.push(format!(
+            "locators: {:?} -> {:?}",
+            before.get_locators(),
+            after.get_locators()
+        ));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::{DURABILITY_WRITER_QOS, READER_SEDP_ST0};
+
+    const READER_SEDP_ST3: &str = "2022-01-23T14:11:51+0000 1642947111.745674/dq.builtin: SEDP ST3 745ad3d:7b:1:3b07delete_proxy_reader (745ad3d:7b:1:3b07) - deleting";
+
+    fn topology(lines: &[&str]) -> DdsiTopology {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        for line in lines {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(line).unwrap())
+                .unwrap();
+        }
+        ddsi_topology
+    }
+
+    #[test]
+    fn diff_same_topology() {
+        let before = topology(&[READER_SEDP_ST0, DURABILITY_WRITER_QOS]);
+        let after = topology(&[READER_SEDP_ST0, DURABILITY_WRITER_QOS]);
+
+        assert!(DdsiTopologyDiff::new(&before, &after).is_empty());
+    }
+
+    #[test]
+    fn diff_appeared_and_disappeared() {
+        let before = topology(&[READER_SEDP_ST0]);
+        let after = topology(&[READER_SEDP_ST0, READER_SEDP_ST3, DURABILITY_WRITER_QOS]);
+
+        let diff = DdsiTopologyDiff::new(&before, &after);
+
+        assert_eq!(diff.participants_appeared, ["428f812:7b:1"]);
+        assert!(diff.participants_disappeared.is_empty());
+        assert_eq!(diff.writers.appeared[0].0, "428f812:7b:1:2302");
+        assert_eq!(diff.readers.disappeared[0].0, "745ad3d:7b:1:3b07");
+        assert!(diff.readers.changed.is_empty());
+    }
+
+    #[test]
+    fn diff_changed_qos_and_locators() {
+        let changed = READER_SEDP_ST0
+            .replace("reliability=1:0.100000000", "reliability=0:0.100000000")
+            .replace("172.17.0.4:39948", "172.17.0.5:39948");
+        let before = topology(&[READER_SEDP_ST0]);
+        let after = topology(&[&changed]);
+
+        let diff = DdsiTopologyDiff::new(&before, &after);
+
+        assert_eq!(
+            diff.readers.changed,
+            [(
+                String::from("745ad3d:7b:1:3b07"),
+                vec![
+                    String::from("reliability: 1:0.100000000 -> 0:0.100000000"),
+                    String::from(
+                        "locators: [\"239.255.0.1:7401\", \"172.17.0.4:39948\"] -> [\"239.255.0.1:7401\", \"172.17.0.5:39948\"]"
+                    ),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn diff_recreated_with_changed_qos() {
+        let recreated = READER_SEDP_ST0
+            .replace("1642947089.895283", "1642947120.000000")
+            .replace("history=0:100", "history=1:1");
+        let before = topology(&[READER_SEDP_ST0]);
+        let after = topology(&[READER_SEDP_ST0, READER_SEDP_ST3, &recreated]);
+
+        let diff = DdsiTopologyDiff::new(&before, &after);
+
+        assert_eq!(
+            diff.readers.changed,
+            [(
+                String::from("745ad3d:7b:1:3b07"),
+                vec![String::from("history: 0:100 -> 1:1")]
+            )]
+        );
+    }
+}
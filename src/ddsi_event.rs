@@ -0,0 +1,214 @@
+use crate::ddsi_log_regex::{DdsiLogRegex, DdsiLogType, QOS_POLICIES};
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::io::{self, BufRead};
+
+/// Fields of the header of the log entries, reported as the timestamp of
+/// the events.
+const HEADER_FIELDS: [&str; 8] = [
+    "year",
+    "month",
+    "day",
+    "hour",
+    "min",
+    "sec",
+    "timezone",
+    "timestamp",
+];
+
+/// A recognized ddsi log entry, owning the values of its fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DdsiEvent {
+    /// Number of the line of the entry in its log, starting at 1.
+    pub line_number: u64,
+    /// Kind of the entry, e.g. `reader_sedp_st0` or `heartbeat`.
+    pub kind: &'static str,
+    /// Time of the entry, in seconds since epoch.
+    pub timestamp: f64,
+    /// Id of the participant the entry is about.
+    pub system_id: String,
+    /// Values of the other fields of the entry, by name.
+    pub fields: BTreeMap<String, String>,
+}
+
+impl DdsiEvent {
+    /// Create an event from a parsed log entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `ddsi_log_regex` - The regexes the entry was parsed with.
+    /// * `ddsi_log_type` - The parsed entry.
+    /// * `line_number` - Number of the line of the entry in its log.
+    ///
+    pub fn new(
+        ddsi_log_regex: &DdsiLogRegex,
+        ddsi_log_type: &DdsiLogType,
+        line_number: u64,
+    ) -> Result<DdsiEvent, Box<dyn Error>> {
+        let capture = ddsi_log_type.get_capture();
+        let fields = ddsi_log_regex
+            .get_field_names(ddsi_log_type)
+            .filter(|name| !HEADER_FIELDS.contains(name))
+            .filter_map(|name| {
+                capture
+                    .name(name)
+                    .map(|value| (String::from(name), String::from(value.as_str())))
+            })
+            .collect();
+
+        Ok(DdsiEvent {
+            line_number,
+            kind: ddsi_log_type.get_name(),
+            timestamp: ddsi_log_type.get_timestamp()?,
+            system_id: ddsi_log_type.get_system_id(),
+            fields,
+        })
+    }
+
+    /// Return the guid of the reader or writer the entry is about, if any.
+    pub fn get_guid(&self) -> Option<String> {
+        self.fields
+            .get("rw_id")
+            .map(|rw_id| format!("{}:{}", self.system_id, rw_id))
+    }
+
+    /// Return the guid of the destination of a protocol message, if any.
+    pub fn get_dst_guid(&self) -> Option<String> {
+        let dst_system_id = self.fields.get("dst_system_id")?;
+        Some(match self.fields.get("dst_rw_id") {
+            Some(dst_rw_id) => format!("{}:{}", dst_system_id, dst_rw_id),
+            None => dst_system_id.clone(),
+        })
+    }
+
+    /// Return the value of a field, if the entry has it.
+    pub fn get_field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+
+    /// Return the QoS policies reported by the entry, by policy name.
+    pub fn get_policies(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        QOS_POLICIES.iter().filter_map(|(policy, group)| {
+            self.fields
+                .get(*group)
+                .filter(|value| !value.is_empty())
+                .map(|value| (*policy, value.as_str()))
+        })
+    }
+
+    /// Return the locators an endpoint was announced with, empty unless the
+    /// entry is a discovery entry.
+    pub fn get_locators(&self) -> Vec<String> {
+        match (
+            self.fields.get("subnet"),
+            self.fields.get("subnet_port"),
+            self.fields.get("hostname"),
+            self.fields.get("hostname_port"),
+        ) {
+            (Some(subnet), Some(subnet_port), Some(hostname), Some(hostname_port)) => vec![
+                format!("{}:{}", subnet, subnet_port),
+                format!("{}:{}", hostname, hostname_port),
+            ],
+            _ => vec![],
+        }
+    }
+}
+
+/// Iterator over the events of a ddsi log, skipping the lines that are not
+/// recognized. Lines with several entries, e.g. the submessages of a `recv`
+/// line, yield one event per entry.
+pub struct EventReader<R> {
+    lines: io::Lines<R>,
+    ddsi_log_regex: DdsiLogRegex,
+    line_number: u64,
+    pending: VecDeque<DdsiEvent>,
+    since: Option<f64>,
+    until: Option<f64>,
+}
+
+impl<R: BufRead> EventReader<R> {
+    pub fn new(reader: R) -> EventReader<R> {
+        EventReader {
+            lines: reader.lines(),
+            ddsi_log_regex: DdsiLogRegex::new(),
+            line_number: 0,
+            pending: VecDeque::new(),
+            since: None,
+            until: None,
+        }
+    }
+
+    /// Skip the events logged before `since` or after `until`, if given.
+    pub fn with_window(mut self, since: Option<f64>, until: Option<f64>) -> EventReader<R> {
+        self.since = since;
+        self.until = until;
+        self
+    }
+
+    fn is_in_window(&self, event: &DdsiEvent) -> bool {
+        self.since.is_none_or(|since| event.timestamp >= since)
+            && self.until.is_none_or(|until| event.timestamp <= until)
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<R> {
+    type Item = Result<DdsiEvent, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            self.line_number += 1;
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error.into())),
+            };
+            for ddsi_log_type in self.ddsi_log_regex.parse_all(&line) {
+                match DdsiEvent::new(&self.ddsi_log_regex, &ddsi_log_type, self.line_number) {
+                    Ok(event) if !self.is_in_window(&event) => {}
+                    Ok(event) => self.pending.push_back(event),
+                    Err(error) => return Some(Err(error)),
+                }
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_events() {
+        let log = "2022-01-20T13:24:36+0000 1642685076.168332/dq.builtin: thread_cputime 1260.618874505\n\
+            not a ddsi log entry\n\
+            2022-01-23T14:11:52+0000 1642947112.000000/      recv: HEARTBEAT(#1:1..10 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107:)\n\
+            2022-01-23T14:11:52+0000 1642947112.100000/      recv: INFOTS(1642947112.099000) GAP(3..5/0: 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107) HEARTBEAT(#2:5..10 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107:)\n";
+
+        let events = EventReader::new(log.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].line_number, 1);
+        assert_eq!(events[0].kind, "thread_cputime");
+        assert_eq!(events[0].timestamp, 1642685076.168332);
+        assert_eq!(events[0].fields["thread"], "dq.builtin");
+        assert_eq!(events[0].fields["cputime"], "1260.618874505");
+        assert!(!events[0].fields.contains_key("year"));
+        assert_eq!(events[1].line_number, 3);
+        assert_eq!(events[1].kind, "heartbeat");
+        assert_eq!(events[1].system_id, "745ad3d:7b:1");
+        assert_eq!(events[2].line_number, 4);
+        assert_eq!(events[2].kind, "gap");
+        assert_eq!(events[3].line_number, 4);
+        assert_eq!(events[3].kind, "heartbeat");
+        assert_eq!(events[3].fields["count"], "2");
+
+        let events = EventReader::new(log.as_bytes())
+            .with_window(Some(1642947112.0), Some(1642947112.05))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].line_number, 3);
+    }
+}
@@ -0,0 +1,505 @@
+use crate::ddsi_event::{DdsiEvent, EventReader};
+use crate::ddsi_parser::ParserOptions;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::{fmt, str::FromStr};
+
+/// Formats the events of ddsi logs can be written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFormat {
+    /// Parquet dataset, one row per event.
+    Parquet,
+    /// Arrow IPC file, one row per event.
+    Arrow,
+    /// Newline delimited json, one object per event.
+    Ndjson,
+}
+
+#[derive(Debug)]
+pub struct UnknownEventFormat(String);
+
+impl Error for UnknownEventFormat {}
+
+impl fmt::Display for UnknownEventFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unknown event format {}, expected one of parquet, arrow or ndjson.",
+            self.0
+        )
+    }
+}
+
+impl FromStr for EventFormat {
+    type Err = UnknownEventFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "parquet" => Ok(EventFormat::Parquet),
+            "arrow" => Ok(EventFormat::Arrow),
+            "ndjson" => Ok(EventFormat::Ndjson),
+            _ => Err(UnknownEventFormat(String::from(s))),
+        }
+    }
+}
+
+impl fmt::Display for EventFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventFormat::Parquet => write!(f, "parquet"),
+            EventFormat::Arrow => write!(f, "arrow"),
+            EventFormat::Ndjson => write!(f, "ndjson"),
+        }
+    }
+}
+
+/// Write the events of ddsi logs to a file, returning the number of events
+/// written.
+///
+/// # Arguments
+///
+/// * `filenames` - The ddsi logs to read the events of, in this order.
+/// * `options` - Options of the parse, only the time window applies.
+/// * `format` - Format of the output.
+/// * `output` - Name of the file to write the events to, ndjson events are
+///   printed if not given.
+///
+pub fn write_events(
+    filenames: &[String],
+    options: &ParserOptions,
+    format: EventFormat,
+    output: Option<&str>,
+) -> Result<usize, Box<dyn Error>> {
+    match (format, output) {
+        (EventFormat::Ndjson, Some(output)) => {
+            write_ndjson(filenames, options, BufWriter::new(File::create(output)?))
+        }
+        (EventFormat::Ndjson, None) => {
+            match write_ndjson(filenames, options, BufWriter::new(io::stdout().lock())) {
+                // The events are piped to a command that stopped reading them,
+                // e.g. head.
+                Err(error)
+                    if error
+                        .downcast_ref::<io::Error>()
+                        .is_some_and(|error| error.kind() == io::ErrorKind::BrokenPipe) =>
+                {
+                    Ok(0)
+                }
+                result => result,
+            }
+        }
+        (EventFormat::Parquet | EventFormat::Arrow, Some(output)) => {
+            columnar::write(filenames, options, format, output)
+        }
+        (EventFormat::Parquet | EventFormat::Arrow, None) => {
+            Err(format!("The {} event format needs an output file.", format).into())
+        }
+    }
+}
+
+/// Open the events of a ddsi log, logged in the time window of the options.
+fn read_events(
+    filename: &str,
+    options: &ParserOptions,
+) -> Result<EventReader<BufReader<File>>, Box<dyn Error>> {
+    let file = File::open(filename).map_err(|error| format!("{}: {}", filename, error))?;
+    Ok(EventReader::new(BufReader::new(file)).with_window(options.since, options.until))
+}
+
+/// An event as written to ndjson, with the same fields as the columns of
+/// the parquet and arrow datasets.
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    source: &'a str,
+    line_number: u64,
+    kind: &'a str,
+    timestamp: f64,
+    participant: &'a str,
+    guid: Option<String>,
+    dst_guid: Option<String>,
+    topic: Option<&'a str>,
+    partition: Option<&'a str>,
+    qos: BTreeMap<&'static str, &'a str>,
+    locators: Vec<String>,
+    fields: &'a BTreeMap<String, String>,
+}
+
+impl<'a> EventRecord<'a> {
+    fn new(source: &'a str, event: &'a DdsiEvent) -> EventRecord<'a> {
+        EventRecord {
+            source,
+            line_number: event.line_number,
+            kind: event.kind,
+            timestamp: event.timestamp,
+            participant: &event.system_id,
+            guid: event.get_guid(),
+            dst_guid: event.get_dst_guid(),
+            topic: event.get_field("topic"),
+            partition: event.get_field("partition"),
+            qos: event.get_policies().collect(),
+            locators: event.get_locators(),
+            fields: &event.fields,
+        }
+    }
+}
+
+/// Write the events of ddsi logs as newline delimited json, flushing after
+/// each log so that the events can be piped to other tools as they are read.
+fn write_ndjson<W: Write>(
+    filenames: &[String],
+    options: &ParserOptions,
+    mut writer: W,
+) -> Result<usize, Box<dyn Error>> {
+    let mut n_events = 0;
+    for filename in filenames {
+        for event in read_events(filename, options)? {
+            let record = serde_json::to_string(&EventRecord::new(filename, &event?))?;
+            writeln!(writer, "{}", record)?;
+            n_events += 1;
+        }
+        writer.flush()?;
+    }
+    Ok(n_events)
+}
+
+#[cfg(feature = "parquet")]
+mod columnar {
+    use super::{read_events, EventFormat};
+    use crate::ddsi_event::DdsiEvent;
+    use crate::ddsi_log_regex::QOS_POLICIES;
+    use crate::ddsi_parser::ParserOptions;
+    use arrow_array::builder::{
+        ListBuilder, StringBuilder, TimestampMicrosecondBuilder, UInt64Builder,
+    };
+    use arrow_array::{ArrayRef, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+    use parquet::arrow::ArrowWriter;
+    use std::error::Error;
+    use std::fs::File;
+    use std::sync::Arc;
+
+    /// Number of events per record batch.
+    const BATCH_SIZE: usize = 65536;
+
+    /// Schema of the event datasets. The QoS policies each have a column,
+    /// after the columns common to all the events. All the fields of the
+    /// entries are kept as a json object in the last column.
+    fn schema() -> SchemaRef {
+        let mut fields = vec![
+            Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                false,
+            ),
+            Field::new("source", DataType::Utf8, false),
+            Field::new("line_number", DataType::UInt64, false),
+            Field::new("kind", DataType::Utf8, false),
+            Field::new("participant", DataType::Utf8, false),
+            Field::new("guid", DataType::Utf8, true),
+            Field::new("dst_guid", DataType::Utf8, true),
+            Field::new("topic", DataType::Utf8, true),
+            Field::new("partition", DataType::Utf8, true),
+        ];
+        for (policy, _) in QOS_POLICIES {
+            fields.push(Field::new(policy, DataType::Utf8, true));
+        }
+        fields.push(Field::new(
+            "locators",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            true,
+        ));
+        fields.push(Field::new("fields", DataType::Utf8, false));
+        Arc::new(Schema::new(fields))
+    }
+
+    /// Columns of the events not yet written.
+    struct EventColumns {
+        schema: SchemaRef,
+        timestamp: TimestampMicrosecondBuilder,
+        source: StringBuilder,
+        line_number: UInt64Builder,
+        kind: StringBuilder,
+        participant: StringBuilder,
+        guid: StringBuilder,
+        dst_guid: StringBuilder,
+        topic: StringBuilder,
+        partition: StringBuilder,
+        policies: Vec<StringBuilder>,
+        locators: ListBuilder<StringBuilder>,
+        fields: StringBuilder,
+        len: usize,
+    }
+
+    impl EventColumns {
+        fn new(schema: SchemaRef) -> EventColumns {
+            EventColumns {
+                schema,
+                timestamp: TimestampMicrosecondBuilder::new().with_timezone("UTC"),
+                source: StringBuilder::new(),
+                line_number: UInt64Builder::new(),
+                kind: StringBuilder::new(),
+                participant: StringBuilder::new(),
+                guid: StringBuilder::new(),
+                dst_guid: StringBuilder::new(),
+                topic: StringBuilder::new(),
+                partition: StringBuilder::new(),
+                policies: QOS_POLICIES.iter().map(|_| StringBuilder::new()).collect(),
+                locators: ListBuilder::new(StringBuilder::new()),
+                fields: StringBuilder::new(),
+                len: 0,
+            }
+        }
+
+        fn append(&mut self, source: &str, event: &DdsiEvent) -> Result<(), Box<dyn Error>> {
+            self.timestamp
+                .append_value((event.timestamp * 1e6).round() as i64);
+            self.source.append_value(source);
+            self.line_number.append_value(event.line_number);
+            self.kind.append_value(event.kind);
+            self.participant.append_value(&event.system_id);
+            self.guid.append_option(event.get_guid());
+            self.dst_guid.append_option(event.get_dst_guid());
+            self.topic.append_option(event.get_field("topic"));
+            self.partition.append_option(event.get_field("partition"));
+            let policies: Vec<_> = event.get_policies().collect();
+            for ((policy, _), column) in QOS_POLICIES.iter().zip(&mut self.policies) {
+                column.append_option(
+                    policies
+                        .iter()
+                        .find(|(name, _)| name == policy)
+                        .map(|(_, value)| *value),
+                );
+            }
+            let locators = event.get_locators();
+            if locators.is_empty() {
+                self.locators.append_null();
+            } else {
+                for locator in locators {
+                    self.locators.values().append_value(locator);
+                }
+                self.locators.append(true);
+            }
+            self.fields
+                .append_value(serde_json::to_string(&event.fields)?);
+            self.len += 1;
+            Ok(())
+        }
+
+        /// Take the events appended so far as a record batch.
+        fn finish(&mut self) -> Result<RecordBatch, Box<dyn Error>> {
+            let mut columns: Vec<ArrayRef> = vec![
+                Arc::new(self.timestamp.finish()),
+                Arc::new(self.source.finish()),
+                Arc::new(self.line_number.finish()),
+                Arc::new(self.kind.finish()),
+                Arc::new(self.participant.finish()),
+                Arc::new(self.guid.finish()),
+                Arc::new(self.dst_guid.finish()),
+                Arc::new(self.topic.finish()),
+                Arc::new(self.partition.finish()),
+            ];
+            for column in &mut self.policies {
+                columns.push(Arc::new(column.finish()));
+            }
+            columns.push(Arc::new(self.locators.finish()));
+            columns.push(Arc::new(self.fields.finish()));
+            self.len = 0;
+            Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+        }
+    }
+
+    /// Writer of record batches in one of the columnar formats.
+    enum BatchWriter {
+        Parquet(ArrowWriter<File>),
+        Arrow(arrow_ipc::writer::FileWriter<File>),
+    }
+
+    impl BatchWriter {
+        fn write(&mut self, batch: &RecordBatch) -> Result<(), Box<dyn Error>> {
+            match self {
+                BatchWriter::Parquet(writer) => writer.write(batch)?,
+                BatchWriter::Arrow(writer) => writer.write(batch)?,
+            }
+            Ok(())
+        }
+
+        fn close(self) -> Result<(), Box<dyn Error>> {
+            match self {
+                BatchWriter::Parquet(writer) => {
+                    writer.close()?;
+                }
+                BatchWriter::Arrow(mut writer) => writer.finish()?,
+            }
+            Ok(())
+        }
+    }
+
+    pub fn write(
+        filenames: &[String],
+        options: &ParserOptions,
+        format: EventFormat,
+        output: &str,
+    ) -> Result<usize, Box<dyn Error>> {
+        let schema = schema();
+        let file = File::create(output)?;
+        let mut writer = match format {
+            EventFormat::Parquet => {
+                BatchWriter::Parquet(ArrowWriter::try_new(file, schema.clone(), None)?)
+            }
+            EventFormat::Arrow => {
+                BatchWriter::Arrow(arrow_ipc::writer::FileWriter::try_new(file, &schema)?)
+            }
+            EventFormat::Ndjson => unreachable!("ndjson events are not columnar"),
+        };
+
+        let mut columns = EventColumns::new(schema);
+        let mut n_events = 0;
+        for filename in filenames {
+            for event in read_events(filename, options)? {
+                columns.append(filename, &event?)?;
+                n_events += 1;
+                if columns.len == BATCH_SIZE {
+                    writer.write(&columns.finish()?)?;
+                }
+            }
+        }
+        if columns.len > 0 {
+            writer.write(&columns.finish()?)?;
+        }
+        writer.close()?;
+        Ok(n_events)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::ddsi_test_data::{TempFile, READER_SEDP_ST0};
+        use arrow_array::{Array, ListArray, StringArray, TimestampMicrosecondArray};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        #[test]
+        fn write_parquet() {
+            let log_file = TempFile::new(
+                "events.log",
+                &format!(
+                    "{}\n2022-01-23T14:11:52+0000 1642947112.000000/      recv: HEARTBEAT(#1:1..10 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107:)\n",
+                    READER_SEDP_ST0
+                ),
+            );
+            let output_file = TempFile::new("events.parquet", "");
+            let log = String::from(log_file.path());
+            let output = output_file.path();
+
+            assert_eq!(
+                write(
+                    std::slice::from_ref(&log),
+                    &ParserOptions::default(),
+                    EventFormat::Parquet,
+                    output
+                )
+                .unwrap(),
+                2
+            );
+
+            let batches = ParquetRecordBatchReaderBuilder::try_new(File::open(output).unwrap())
+                .unwrap()
+                .build()
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(batches.len(), 1);
+            let batch = &batches[0];
+            assert_eq!(batch.num_rows(), 2);
+            let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+            let strings = |name: &str| {
+                column(name)
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap()
+                    .clone()
+            };
+
+            let timestamps = column("timestamp");
+            let timestamps = timestamps
+                .as_any()
+                .downcast_ref::<TimestampMicrosecondArray>()
+                .unwrap();
+            assert_eq!(timestamps.value(0), 1642947089895283);
+            assert_eq!(strings("source").value(1), log);
+            assert_eq!(strings("kind").value(0), "reader_sedp_st0");
+            assert_eq!(strings("guid").value(0), "745ad3d:7b:1:3b07");
+            assert_eq!(strings("dst_guid").value(1), "5bbed783:7b:1:3107");
+            assert_eq!(strings("partition").value(0), "nile.Test.cmd");
+            assert_eq!(strings("reliability").value(0), "1:0.100000000");
+            assert!(strings("reliability").is_null(1));
+
+            let locators = column("locators");
+            let locators = locators.as_any().downcast_ref::<ListArray>().unwrap();
+            assert_eq!(locators.value(0).len(), 2);
+            assert!(locators.is_null(1));
+        }
+    }
+}
+
+#[cfg(not(feature = "parquet"))]
+mod columnar {
+    use super::EventFormat;
+    use crate::ddsi_parser::ParserOptions;
+    use std::error::Error;
+
+    pub fn write(
+        _filenames: &[String],
+        _options: &ParserOptions,
+        format: EventFormat,
+        _output: &str,
+    ) -> Result<usize, Box<dyn Error>> {
+        Err(format!(
+            "The {} event format requires building with the parquet feature.",
+            format
+        )
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use std::collections::BTreeSet;
+
+    /// Compare the ndjson of a log with an entry of each kind recognized by
+    /// `DdsiLogRegex` to its golden file. After an intended change of the
+    /// output, regenerate it with:
+    ///
+    /// ddsi_log_parser events -f tests/data/events.log --format ndjson > tests/data/events.ndjson
+    #[test]
+    fn write_ndjson_golden() {
+        let mut ndjson = vec![];
+        let n_events = write_ndjson(
+            &[String::from("tests/data/events.log")],
+            &ParserOptions::default(),
+            &mut ndjson,
+        )
+        .unwrap();
+        let ndjson = String::from_utf8(ndjson).unwrap();
+        let expected = include_str!("../tests/data/events.ndjson");
+
+        for (line, expected_line) in ndjson.lines().zip(expected.lines()) {
+            assert_eq!(line, expected_line);
+        }
+        assert_eq!(ndjson, expected);
+        assert_eq!(n_events, 16);
+
+        let kinds = ndjson
+            .lines()
+            .map(|line| {
+                let event: serde_json::Value = serde_json::from_str(line).unwrap();
+                String::from(event["kind"].as_str().unwrap())
+            })
+            .collect::<BTreeSet<_>>();
+        assert_eq!(kinds.len(), DdsiLogRegex::new().get_number_of_kinds());
+    }
+}
@@ -0,0 +1,55 @@
+use crate::ddsi_graph::DdsiGraph;
+use crate::ddsi_timeline;
+use crate::ddsi_topology::DdsiTopology;
+use serde::Deserialize;
+use std::error::Error;
+use std::{fmt, str::FromStr};
+
+/// Formats a topology can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    /// Graphviz DOT discovery graph.
+    Dot,
+    /// Mermaid flowchart discovery graph.
+    Mermaid,
+    /// Chrome trace-event json timeline of the discovery events.
+    ChromeTrace,
+}
+
+#[derive(Debug)]
+pub struct UnknownExportFormat(String);
+
+impl Error for UnknownExportFormat {}
+
+impl fmt::Display for UnknownExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unknown export format {}, expected one of dot, mermaid or chrome-trace.",
+            self.0
+        )
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = UnknownExportFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(ExportFormat::Dot),
+            "mermaid" => Ok(ExportFormat::Mermaid),
+            "chrome-trace" => Ok(ExportFormat::ChromeTrace),
+            _ => Err(UnknownExportFormat(String::from(s))),
+        }
+    }
+}
+
+/// Render a topology in the requested format.
+pub fn export(ddsi_topology: &DdsiTopology, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Dot => DdsiGraph::new(ddsi_topology).to_dot(),
+        ExportFormat::Mermaid => DdsiGraph::new(ddsi_topology).to_mermaid(),
+        ExportFormat::ChromeTrace => ddsi_timeline::to_chrome_trace(ddsi_topology),
+    }
+}
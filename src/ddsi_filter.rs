@@ -0,0 +1,483 @@
+use crate::ddsi_log_regex::QOS_POLICIES;
+use crate::ddsi_participant::{DdsiParticipant, Qos};
+use crate::ddsi_topology::DdsiTopology;
+use regex::Regex;
+use serde::Deserialize;
+use std::error::Error;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::{fmt, str::FromStr};
+
+/// Endpoint fields, other than the QoS policies, a filter can test.
+const FIELDS: [&str; 9] = [
+    "topic",
+    "partition",
+    "host",
+    "participant",
+    "id",
+    "guid",
+    "kind",
+    "alive",
+    "local",
+];
+
+/// Names of the kinds of the QoS policies a filter can compare by name, e.g.
+/// `reliability == best_effort`.
+const POLICY_KINDS: [(&str, &[&str]); 6] = [
+    ("reliability", &["best_effort", "reliable"]),
+    (
+        "durability",
+        &["volatile", "transient_local", "transient", "persistent"],
+    ),
+    ("ownership", &["shared", "exclusive"]),
+    (
+        "destination_order",
+        &["by_reception_timestamp", "by_source_timestamp"],
+    ),
+    ("history", &["keep_last", "keep_all"]),
+    (
+        "liveliness",
+        &["automatic", "manual_by_participant", "manual_by_topic"],
+    ),
+];
+
+#[derive(Debug)]
+pub struct InvalidFilter(String);
+
+impl Error for InvalidFilter {}
+
+impl fmt::Display for InvalidFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid filter expression: {}.", self.0)
+    }
+}
+
+/// Filter expression evaluated against the endpoints of a topology, e.g.
+/// `topic =~ "logevent_.*" && host == "172.17.0.4" && reliability == best_effort`.
+///
+/// Comparisons are `field == value`, `field != value`, `field =~ regex` and
+/// `field !~ regex`, combined with `&&`, `||`, `!` and parentheses. Values
+/// are either quoted strings or bare words. Fields are the topic, partition,
+/// host, participant, id, guid, kind (reader or writer), alive and local
+/// properties of the endpoint and the names of its QoS policies.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Filter {
+    text: String,
+    expression: Expression,
+}
+
+#[derive(Debug)]
+enum Expression {
+    Or(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Compare {
+        field: String,
+        operator: Operator,
+        value: String,
+    },
+    Match {
+        field: String,
+        negated: bool,
+        regex: Regex,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Equal,
+    NotEqual,
+    Match,
+    NotMatch,
+    And,
+    Or,
+    Not,
+    LeftParen,
+    RightParen,
+}
+
+impl FromStr for Filter {
+    type Err = InvalidFilter;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let expression = parser.parse_or()?;
+
+        match parser.peek() {
+            None => Ok(Filter {
+                text: String::from(s),
+                expression,
+            }),
+            Some(token) => Err(InvalidFilter(format!("unexpected {:?}", token))),
+        }
+    }
+}
+
+impl TryFrom<String> for Filter {
+    type Error = InvalidFilter;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl Filter {
+    /// Check if an endpoint matches the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `participant` - The participant owning the endpoint.
+    /// * `id` - The id of the endpoint.
+    /// * `is_writer` - Whether the endpoint is a writer.
+    /// * `qos` - The QoS of the endpoint.
+    ///
+    pub fn matches(
+        &self,
+        participant: &DdsiParticipant,
+        id: &str,
+        is_writer: bool,
+        qos: &Qos,
+    ) -> bool {
+        let endpoint = Endpoint {
+            participant,
+            id,
+            is_writer,
+            qos,
+        };
+        self.expression.evaluate(&endpoint)
+    }
+
+    /// Remove from a topology the endpoints not matching the filter, and the
+    /// participants left without endpoints.
+    pub fn apply(&self, ddsi_topology: &mut DdsiTopology) {
+        ddsi_topology.retain_endpoints(|participant, id, is_writer, qos| {
+            self.matches(participant, id, is_writer, qos)
+        });
+    }
+}
+
+struct Endpoint<'a> {
+    participant: &'a DdsiParticipant,
+    id: &'a str,
+    is_writer: bool,
+    qos: &'a Qos,
+}
+
+impl Endpoint<'_> {
+    fn get_field(&self, field: &str) -> Option<String> {
+        match field {
+            "topic" => Some(self.qos.get_topic().clone()),
+            "partition" => Some(self.qos.get_partition().clone()),
+            "host" => Some(self.participant.get_hostname().clone()),
+            "participant" => Some(self.participant.get_system_id().clone()),
+            "id" => Some(String::from(self.id)),
+            "guid" => Some(format!("{}:{}", self.participant.get_system_id(), self.id)),
+            "kind" => Some(String::from(if self.is_writer {
+                "writer"
+            } else {
+                "reader"
+            })),
+            "alive" => Some(self.qos.is_alive().to_string()),
+            "local" => Some(self.participant.is_local().to_string()),
+            policy => {
+                let value = self.qos.get_policies().get(policy)?;
+                let kind_name = POLICY_KINDS
+                    .iter()
+                    .find(|(name, _)| *name == policy)
+                    .and_then(|(_, kinds)| {
+                        let kind: usize = value.split(':').next()?.parse().ok()?;
+                        kinds.get(kind)
+                    });
+                Some(String::from(kind_name.copied().unwrap_or(value)))
+            }
+        }
+    }
+}
+
+impl Expression {
+    fn evaluate(&self, endpoint: &Endpoint) -> bool {
+        match self {
+            Expression::Or(left, right) => left.evaluate(endpoint) || right.evaluate(endpoint),
+            Expression::And(left, right) => left.evaluate(endpoint) && right.evaluate(endpoint),
+            Expression::Not(expression) => !expression.evaluate(endpoint),
+            Expression::Compare {
+                field,
+                operator,
+                value,
+            } => {
+                let equal = endpoint.get_field(field).as_ref() == Some(value);
+                (*operator == Operator::Equal) == equal
+            }
+            Expression::Match {
+                field,
+                negated,
+                regex,
+            } => {
+                let is_match = endpoint
+                    .get_field(field)
+                    .is_some_and(|value| regex.is_match(&value));
+                *negated != is_match
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, InvalidFilter> {
+        let mut expression = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expression = Expression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, InvalidFilter> {
+        let mut expression = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expression = Expression::And(Box::new(expression), Box::new(self.parse_unary()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, InvalidFilter> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expression::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LeftParen) => {
+                let expression = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RightParen) => Ok(expression),
+                    _ => Err(InvalidFilter(String::from("missing closing parenthesis"))),
+                }
+            }
+            Some(Token::Word(field)) => self.parse_comparison(field),
+            Some(token) => Err(InvalidFilter(format!("unexpected {:?}", token))),
+            None => Err(InvalidFilter(String::from("unexpected end of expression"))),
+        }
+    }
+
+    fn parse_comparison(&mut self, field: String) -> Result<Expression, InvalidFilter> {
+        if !FIELDS.contains(&field.as_str())
+            && !QOS_POLICIES.iter().any(|(policy, _)| *policy == field)
+        {
+            return Err(InvalidFilter(format!("unknown field {}", field)));
+        }
+
+        let operator = self.next();
+        let value = match self.next() {
+            Some(Token::Word(value)) | Some(Token::Text(value)) => value,
+            _ => return Err(InvalidFilter(format!("missing value after {}", field))),
+        };
+
+        match operator {
+            Some(Token::Equal) => Ok(Expression::Compare {
+                field,
+                operator: Operator::Equal,
+                value,
+            }),
+            Some(Token::NotEqual) => Ok(Expression::Compare {
+                field,
+                operator: Operator::NotEqual,
+                value,
+            }),
+            Some(Token::Match) | Some(Token::NotMatch) => {
+                let regex = Regex::new(&value).map_err(|error| {
+                    InvalidFilter(format!("invalid regex {}: {}", value, error))
+                })?;
+                Ok(Expression::Match {
+                    field,
+                    negated: operator == Some(Token::NotMatch),
+                    regex,
+                })
+            }
+            _ => Err(InvalidFilter(format!(
+                "expected ==, !=, =~ or !~ after {}",
+                field
+            ))),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, InvalidFilter> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '&' => expect(&mut chars, '&', Token::And)?,
+            '|' => expect(&mut chars, '|', Token::Or)?,
+            '=' => match chars.next() {
+                Some('=') => Token::Equal,
+                Some('~') => Token::Match,
+                _ => return Err(InvalidFilter(String::from("expected == or =~"))),
+            },
+            '!' => match chars.peek() {
+                Some('=') => {
+                    chars.next();
+                    Token::NotEqual
+                }
+                Some('~') => {
+                    chars.next();
+                    Token::NotMatch
+                }
+                _ => Token::Not,
+            },
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => value.push(escaped),
+                            None => return Err(InvalidFilter(String::from("unterminated string"))),
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err(InvalidFilter(String::from("unterminated string"))),
+                    }
+                }
+                Token::Text(value)
+            }
+            c if is_word_char(c) => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.peek().copied().filter(|c| is_word_char(*c)) {
+                    word.push(c);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+            c => return Err(InvalidFilter(format!("unexpected character {}", c))),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn expect(
+    chars: &mut Peekable<Chars>,
+    expected: char,
+    token: Token,
+) -> Result<Token, InvalidFilter> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(token),
+        _ => Err(InvalidFilter(format!("expected {0}{0}", expected))),
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || "_.:-/*@".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::{
+        BUILTIN_WRITER_SEDP_ST0, READER_SEDP_ST0, TRANSIENT_READER_SEDP_ST0,
+    };
+    use crate::ddsi_topology::SortOrder;
+
+    /// A volatile reader, a best effort transient reader and a writer.
+    fn log_sample() -> [String; 3] {
+        [
+            String::from(READER_SEDP_ST0),
+            TRANSIENT_READER_SEDP_ST0.replace("reliability=1:", "reliability=0:"),
+            String::from(BUILTIN_WRITER_SEDP_ST0),
+        ]
+    }
+
+    fn filtered_topology(filter: &str) -> DdsiTopology {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        for line in log_sample() {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(&line).unwrap())
+                .unwrap();
+        }
+        filter.parse::<Filter>().unwrap().apply(&mut ddsi_topology);
+        ddsi_topology
+    }
+
+    fn readers_ids(ddsi_topology: &DdsiTopology, participant_id: &str) -> Vec<String> {
+        ddsi_topology
+            .get_participant(participant_id)
+            .unwrap()
+            .get_readers_id(SortOrder::Guid)
+    }
+
+    #[test]
+    fn filter_endpoints() {
+        let ddsi_topology = filtered_topology(
+            r#"topic =~ "logevent_.*" && host == "172.17.0.4" && reliability == best_effort"#,
+        );
+        assert_eq!(ddsi_topology.len(), 1);
+        assert_eq!(readers_ids(&ddsi_topology, "745ad3d:7b:1"), ["5507"]);
+
+        let ddsi_topology = filtered_topology("kind == writer || durability == volatile");
+        assert_eq!(ddsi_topology.len(), 2);
+        assert_eq!(readers_ids(&ddsi_topology, "745ad3d:7b:1"), ["3b07"]);
+
+        let ddsi_topology = filtered_topology("!(host == 172.17.0.4) && alive == true");
+        assert_eq!(ddsi_topology.len(), 1);
+        assert!(ddsi_topology.get_participant("7efc2093:7b:1").is_some());
+
+        let ddsi_topology = filtered_topology(r#"topic !~ "^Test_" && ownership_strength != 1"#);
+        assert_eq!(ddsi_topology.len(), 1);
+    }
+
+    #[test]
+    fn invalid_filters() {
+        for filter in [
+            "",
+            "topic",
+            "topic ==",
+            "colour == red",
+            "topic = x",
+            "topic == x &&",
+            "(topic == x",
+            "topic == \"x",
+            "topic =~ \"(\"",
+            "topic == x y",
+        ] {
+            assert!(filter.parse::<Filter>().is_err(), "{}", filter);
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use crate::ddsi_log_regex::DdsiLogType;
+use crate::ddsi_topology::DdsiTopology;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Number of writers reported in the summary.
+const N_TOP: usize = 10;
+/// Width, in seconds, of the windows used to compute rates.
+const RATE_WINDOW: f64 = 1.0;
+/// Topic reported for writers not found in the topology.
+const UNKNOWN_TOPIC: &str = "<unknown>";
+
+/// Data sample flow statistics, from the `write_sample` lines logged by the
+/// sending nodes and the `DATA` lines logged by the receiving ones.
+#[derive(Debug, Default)]
+pub struct FlowAnalysis {
+    writers: BTreeMap<String, WriterFlow>,
+}
+
+/// Data sample flow of a writer.
+///
+/// Samples are lost when their sequence numbers are skipped by the samples
+/// received and they never arrive later on, reordered or retransmitted.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WriterFlow {
+    pub written: usize,
+    pub received: usize,
+    pub lost: usize,
+    pub bytes: usize,
+    last_received: Option<i64>,
+    /// Ranges, first to last included, of the sequence numbers skipped and
+    /// not received yet.
+    missing: BTreeMap<i64, i64>,
+    /// Number of samples written and received in each rate window, kept
+    /// apart as each sample is seen by its writer and by every receiver.
+    written_bins: BTreeMap<i64, usize>,
+    received_bins: BTreeMap<i64, usize>,
+}
+
+impl WriterFlow {
+    fn add(&mut self, other: &WriterFlow) {
+        self.written += other.written;
+        self.received += other.received;
+        self.lost += other.lost;
+        self.bytes += other.bytes;
+        for (bin, count) in &other.written_bins {
+            *self.written_bins.entry(*bin).or_default() += count;
+        }
+        for (bin, count) in &other.received_bins {
+            *self.received_bins.entry(*bin).or_default() += count;
+        }
+    }
+
+    /// Account for a received sample, returning false if it is a duplicate.
+    fn receive(&mut self, seq: i64) -> bool {
+        match self.last_received {
+            Some(last) if seq <= last => {
+                if !self.fill_missing(seq) {
+                    return false;
+                }
+                self.lost -= 1;
+            }
+            Some(last) => {
+                if seq > last + 1 {
+                    self.missing.insert(last + 1, seq - 1);
+                    self.lost += (seq - last - 1) as usize;
+                }
+                self.last_received = Some(seq);
+            }
+            None => self.last_received = Some(seq),
+        }
+        self.received += 1;
+        true
+    }
+
+    /// Remove a late sequence number from the missing ones, returning false
+    /// if it was not missing.
+    fn fill_missing(&mut self, seq: i64) -> bool {
+        let (first, last) = match self.missing.range(..=seq).next_back() {
+            Some((first, last)) if *last >= seq => (*first, *last),
+            _ => return false,
+        };
+        self.missing.remove(&first);
+        if first < seq {
+            self.missing.insert(first, seq - 1);
+        }
+        if seq < last {
+            self.missing.insert(seq + 1, last);
+        }
+        true
+    }
+
+    /// Return the mean and peak number of samples written per second, over
+    /// the windows in which samples were written.
+    pub fn get_write_rates(&self) -> (f64, f64) {
+        get_rates(&self.written_bins)
+    }
+
+    /// Return the mean and peak number of samples received per second, over
+    /// the windows in which samples were received.
+    pub fn get_receive_rates(&self) -> (f64, f64) {
+        get_rates(&self.received_bins)
+    }
+}
+
+/// Return the mean and peak number of samples per second of rate windows.
+fn get_rates(bins: &BTreeMap<i64, usize>) -> (f64, f64) {
+    if bins.is_empty() {
+        return (0.0, 0.0);
+    }
+    let total: usize = bins.values().sum();
+    let peak = bins.values().max().copied().unwrap_or_default();
+    (
+        total as f64 / (bins.len() as f64 * RATE_WINDOW),
+        peak as f64 / RATE_WINDOW,
+    )
+}
+
+impl FlowAnalysis {
+    /// Update the analysis with a data sample log entry. Other log entries
+    /// are ignored.
+    pub fn update(&mut self, ddsi_log_type: &DdsiLogType) -> Result<(), Box<dyn Error>> {
+        let (capture, is_write) = match ddsi_log_type {
+            DdsiLogType::WriteSample(capture) if capture.name("failed").is_none() => {
+                (capture, true)
+            }
+            DdsiLogType::Data(capture) => (capture, false),
+            _ => return Ok(()),
+        };
+
+        let timestamp = ddsi_log_type.get_timestamp()?;
+        let seq: i64 = capture["seq"].parse()?;
+        let size: usize = match capture.name("size") {
+            Some(size) => size.as_str().parse()?,
+            None => 0,
+        };
+
+        let writer = self
+            .writers
+            .entry(format!("{}:{}", &capture["system_id"], &capture["rw_id"]))
+            .or_default();
+
+        let bins = if is_write {
+            writer.written += 1;
+            &mut writer.written_bins
+        } else if writer.receive(seq) {
+            &mut writer.received_bins
+        } else {
+            return Ok(());
+        };
+        *bins
+            .entry((timestamp / RATE_WINDOW).floor() as i64)
+            .or_default() += 1;
+        writer.bytes += size;
+        Ok(())
+    }
+
+    /// Add the analysis of another log.
+    pub fn merge(&mut self, other: FlowAnalysis) {
+        for (guid, flow) in other.writers {
+            self.writers.entry(guid).or_default().add(&flow);
+        }
+    }
+
+    /// Check if any data sample was found.
+    pub fn is_empty(&self) -> bool {
+        self.writers.is_empty()
+    }
+
+    /// Return the flow of each writer along with its topic, looked up in the
+    /// topology.
+    pub fn get_writers(&self, ddsi_topology: &DdsiTopology) -> Vec<(&String, String, &WriterFlow)> {
+        self.writers
+            .iter()
+            .map(|(guid, flow)| (guid, get_topic(ddsi_topology, guid), flow))
+            .collect()
+    }
+
+    /// Return the flow aggregated per topic.
+    pub fn get_topics(&self, ddsi_topology: &DdsiTopology) -> BTreeMap<String, WriterFlow> {
+        let mut topics: BTreeMap<String, WriterFlow> = BTreeMap::new();
+
+        for (_, topic, flow) in self.get_writers(ddsi_topology) {
+            topics.entry(topic).or_default().add(flow);
+        }
+        topics
+    }
+
+    pub fn summarize(&self, ddsi_topology: &DdsiTopology) -> String {
+        let mut summary = format!("\t- Data flow, {} writers:\n", self.writers.len());
+
+        let topics = self.get_topics(ddsi_topology);
+        summary.push_str(&format!("\t\t- Topics {}:\n", topics.len()));
+        for (topic, flow) in &topics {
+            summary.push_str(&format!("\t\t\t- {}: {}\n", topic, summarize_flow(flow)));
+        }
+
+        let mut writers = self.get_writers(ddsi_topology);
+        writers.sort_by(|a, b| {
+            (b.2.written + b.2.received)
+                .cmp(&(a.2.written + a.2.received))
+                .then_with(|| a.0.cmp(b.0))
+        });
+
+        summary.push_str(&format!(
+            "\t\t- Top {} writers:\n",
+            writers.len().min(N_TOP)
+        ));
+        for (guid, topic, flow) in writers.into_iter().take(N_TOP) {
+            summary.push_str(&format!(
+                "\t\t\t- {} ({}): {}\n",
+                guid,
+                topic,
+                summarize_flow(flow)
+            ));
+        }
+        summary
+    }
+}
+
+fn summarize_flow(flow: &WriterFlow) -> String {
+    let (write_rate, peak_write_rate) = flow.get_write_rates();
+    let (receive_rate, peak_receive_rate) = flow.get_receive_rates();
+    format!(
+        "written={} received={} lost={} bytes={} write_rate={:.3}/s peak_write_rate={:.3}/s \
+        receive_rate={:.3}/s peak_receive_rate={:.3}/s",
+        flow.written,
+        flow.received,
+        flow.lost,
+        flow.bytes,
+        write_rate,
+        peak_write_rate,
+        receive_rate,
+        peak_receive_rate
+    )
+}
+
+/// Return the topic of a writer, given its guid.
+fn get_topic(ddsi_topology: &DdsiTopology, guid: &str) -> String {
+    guid.rsplit_once(':')
+        .and_then(|(participant_id, writer_id)| {
+            ddsi_topology
+                .get_participant(participant_id)?
+                .get_writer_qos(&String::from(writer_id))
+        })
+        .map_or(String::from(UNKNOWN_TOPIC), |qos| qos.get_topic().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::WRITER_QOS;
+
+    #[test]
+    fn data_flow() {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+        ddsi_topology
+            .update(ddsi_log_regex.parse(WRITER_QOS).unwrap())
+            .unwrap();
+
+        let mut analysis = FlowAnalysis::default();
+        for line in [
+            "2022-01-23T14:11:52+0000 1642947112.100000/    (anon): write_sample 5bbed783:7b:1:3902 #1: ST0 nile.Test.cmd.Test_command_start_cc827fa3/Test::command_start_cc827fa3:{} size=64",
+            "2022-01-23T14:11:52+0000 1642947112.200000/    (anon): write_sample 5bbed783:7b:1:3902 #2: ST0 nile.Test.cmd.Test_command_start_cc827fa3/Test::command_start_cc827fa3:{} size=64",
+            "2022-01-23T14:11:52+0000 1642947112.300000/      recv: DATA(5bbed783:7b:1:3902 -> 0:0:0:0 #1 L(:1c1 1642947112.300000)",
+            "2022-01-23T14:11:53+0000 1642947113.300000/      recv: DATA(5bbed783:7b:1:3902 -> 0:0:0:0 #4 L(:1c1 1642947113.300000)",
+            "2022-01-23T14:11:53+0000 1642947113.400000/      recv: DATA(5bbed783:7b:1:3902 -> 0:0:0:0 #6 L(:1c1 1642947113.400000)",
+            "2022-01-23T14:11:53+0000 1642947113.500000/      recv: DATA(745ad3d:7b:1:3102 -> 0:0:0:0 #1 L(:1c1 1642947113.500000)",
+        ] {
+            analysis
+                .update(&ddsi_log_regex.parse(line).unwrap())
+                .unwrap();
+        }
+
+        let topics = analysis.get_topics(&ddsi_topology);
+        assert_eq!(topics.len(), 2);
+
+        let flow = &topics["Test_command_start_cc827fa3"];
+        assert_eq!(flow.written, 2);
+        assert_eq!(flow.received, 3);
+        assert_eq!(flow.lost, 3);
+        assert_eq!(flow.bytes, 128);
+        assert_eq!(flow.get_write_rates(), (2.0, 2.0));
+        assert_eq!(flow.get_receive_rates(), (1.5, 2.0));
+
+        assert_eq!(topics[UNKNOWN_TOPIC].received, 1);
+    }
+
+    #[test]
+    fn late_samples_are_not_lost() {
+        let mut flow = WriterFlow::default();
+
+        // 5 skips 2 to 4, 3 and 2 arrive late, 3 twice, 4 never arrives.
+        let received: Vec<bool> = [1, 5, 3, 3, 2, 6]
+            .into_iter()
+            .map(|seq| flow.receive(seq))
+            .collect();
+
+        assert_eq!(received, [true, true, true, false, true, true]);
+        assert_eq!(flow.received, 5);
+        assert_eq!(flow.lost, 1);
+        assert_eq!(flow.missing, BTreeMap::from([(4, 4)]));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::ddsi_participant::{find_incompatible_pairs, Qos};
+use crate::ddsi_topology::{DdsiTopology, SortOrder};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Discovery graph of a topology: participants grouped by host, topics and
+/// the edges from writers to topics and from topics to readers.
+///
+/// Edges aggregate all the alive endpoints of a participant on the same
+/// topic and partition.
+#[derive(Debug, Default)]
+pub struct DdsiGraph {
+    hosts: BTreeMap<String, Vec<String>>,
+    topics: BTreeSet<String>,
+    edges: Vec<Edge>,
+}
+
+#[derive(Debug)]
+struct Edge {
+    participant_id: String,
+    topic: String,
+    partition: String,
+    is_writer: bool,
+    n_endpoints: usize,
+    incompatible: BTreeSet<&'static str>,
+}
+
+/// An alive endpoint of the topology.
+struct Endpoint<'a> {
+    participant_id: &'a str,
+    is_writer: bool,
+    qos: &'a Qos,
+}
+
+impl DdsiGraph {
+    pub fn new(ddsi_topology: &DdsiTopology) -> DdsiGraph {
+        let mut graph = DdsiGraph::default();
+        let mut endpoints = vec![];
+
+        let participants_ids = ddsi_topology.get_participants_ids(SortOrder::Host);
+
+        for participant_id in &participants_ids {
+            let participant = ddsi_topology.get_participant(participant_id).unwrap();
+
+            graph
+                .hosts
+                .entry(participant.get_hostname().clone())
+                .or_default()
+                .push(participant_id.clone());
+
+            for id in participant.get_writers_id(SortOrder::Topic) {
+                let qos = participant.get_writer_qos(&id).unwrap();
+                if qos.is_alive() {
+                    endpoints.push(Endpoint {
+                        participant_id,
+                        is_writer: true,
+                        qos,
+                    });
+                }
+            }
+            for id in participant.get_readers_id(SortOrder::Topic) {
+                let qos = participant.get_reader_qos(&id).unwrap();
+                if qos.is_alive() {
+                    endpoints.push(Endpoint {
+                        participant_id,
+                        is_writer: false,
+                        qos,
+                    });
+                }
+            }
+        }
+
+        let mut incompatible: Vec<BTreeSet<&'static str>> = vec![BTreeSet::new(); endpoints.len()];
+        let indexed_endpoints = || endpoints.iter().enumerate();
+
+        for (writer, reader, policies) in find_incompatible_pairs(
+            indexed_endpoints()
+                .filter(|(_, endpoint)| endpoint.is_writer)
+                .map(|(index, endpoint)| (index, endpoint.qos)),
+            indexed_endpoints()
+                .filter(|(_, endpoint)| !endpoint.is_writer)
+                .map(|(index, endpoint)| (index, endpoint.qos)),
+        ) {
+            incompatible[writer].extend(&policies);
+            incompatible[reader].extend(&policies);
+        }
+
+        // Position of the edge of each (participant, direction, topic,
+        // partition) in `graph.edges`.
+        let mut edges_index: BTreeMap<(&str, bool, &String, &String), usize> = BTreeMap::new();
+
+        for (endpoint, incompatible) in endpoints.iter().zip(incompatible) {
+            graph.topics.insert(endpoint.qos.get_topic().clone());
+
+            let key = (
+                endpoint.participant_id,
+                endpoint.is_writer,
+                endpoint.qos.get_topic(),
+                endpoint.qos.get_partition(),
+            );
+
+            match edges_index.get(&key) {
+                Some(position) => {
+                    let edge = &mut graph.edges[*position];
+                    edge.n_endpoints += 1;
+                    edge.incompatible.extend(incompatible);
+                }
+                None => {
+                    edges_index.insert(key, graph.edges.len());
+                    graph.edges.push(Edge {
+                        participant_id: String::from(endpoint.participant_id),
+                        topic: endpoint.qos.get_topic().clone(),
+                        partition: endpoint.qos.get_partition().clone(),
+                        is_writer: endpoint.is_writer,
+                        n_endpoints: 1,
+                        incompatible,
+                    });
+                }
+            }
+        }
+        graph
+    }
+
+    /// Render the graph in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph ddsi_topology {\n\trankdir=LR;\n");
+
+        for (index, (hostname, participants_ids)) in self.hosts.iter().enumerate() {
+            dot.push_str(&format!(
+                "\tsubgraph cluster_{} {{\n\t\tlabel=\"{}\";\n",
+                index,
+                escape_dot(hostname)
+            ));
+            for participant_id in participants_ids {
+                dot.push_str(&format!(
+                    "\t\t\"p:{0}\" [label=\"{0}\"];\n",
+                    escape_dot(participant_id)
+                ));
+            }
+            dot.push_str("\t}\n");
+        }
+
+        for topic in &self.topics {
+            dot.push_str(&format!(
+                "\t\"t:{0}\" [label=\"{0}\", shape=box];\n",
+                escape_dot(topic)
+            ));
+        }
+
+        for edge in &self.edges {
+            let (from, to) = if edge.is_writer {
+                (
+                    format!("p:{}", edge.participant_id),
+                    format!("t:{}", edge.topic),
+                )
+            } else {
+                (
+                    format!("t:{}", edge.topic),
+                    format!("p:{}", edge.participant_id),
+                )
+            };
+            let style = if edge.incompatible.is_empty() {
+                String::new()
+            } else {
+                String::from(", color=red, penwidth=2")
+            };
+            dot.push_str(&format!(
+                "\t\"{}\" -> \"{}\" [label=\"{}\"{}];\n",
+                escape_dot(&from),
+                escape_dot(&to),
+                escape_dot(&edge.label()),
+                style
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Render the graph as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+        let mut participant_nodes = BTreeMap::new();
+        let mut topic_nodes = BTreeMap::new();
+
+        for (index, (hostname, participants_ids)) in self.hosts.iter().enumerate() {
+            mermaid.push_str(&format!(
+                "\tsubgraph host_{}[\"{}\"]\n",
+                index,
+                escape_mermaid(hostname)
+            ));
+            for participant_id in participants_ids {
+                let node = format!("p{}", participant_nodes.len());
+                mermaid.push_str(&format!(
+                    "\t\t{}[\"{}\"]\n",
+                    node,
+                    escape_mermaid(participant_id)
+                ));
+                participant_nodes.insert(participant_id.as_str(), node);
+            }
+            mermaid.push_str("\tend\n");
+        }
+
+        for topic in &self.topics {
+            let node = format!("t{}", topic_nodes.len());
+            mermaid.push_str(&format!("\t{}[[\"{}\"]]\n", node, escape_mermaid(topic)));
+            topic_nodes.insert(topic.as_str(), node);
+        }
+
+        let mut incompatible_links = vec![];
+
+        for (index, edge) in self.edges.iter().enumerate() {
+            let participant_node = &participant_nodes[edge.participant_id.as_str()];
+            let topic_node = &topic_nodes[edge.topic.as_str()];
+            let (from, to) = if edge.is_writer {
+                (participant_node, topic_node)
+            } else {
+                (topic_node, participant_node)
+            };
+            mermaid.push_str(&format!(
+                "\t{} -->|\"{}\"| {}\n",
+                from,
+                escape_mermaid(&edge.label()),
+                to
+            ));
+            if !edge.incompatible.is_empty() {
+                incompatible_links.push(index.to_string());
+            }
+        }
+
+        if !incompatible_links.is_empty() {
+            mermaid.push_str(&format!(
+                "\tlinkStyle {} stroke:red,stroke-width:2px\n",
+                incompatible_links.join(",")
+            ));
+        }
+        mermaid
+    }
+}
+
+impl Edge {
+    fn label(&self) -> String {
+        let mut label = self.partition.clone();
+        if self.n_endpoints > 1 {
+            label.push_str(&format!(" x{}", self.n_endpoints));
+        }
+        if !self.incompatible.is_empty() {
+            label.push_str(&format!(
+                " incompatible: {}",
+                self.incompatible
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<&str>>()
+                    .join(",")
+            ));
+        }
+        label
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Replace the characters significant in Mermaid labels by their entity
+/// codes.
+fn escape_mermaid(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' | '[' | ']' | '(' | ')' | '{' | '}' | '|' | '<' | '>' | '#' | ';' => {
+                escaped.push_str(&format!("#{};", c as u32))
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::{READER_SEDP_ST0, WRITER_QOS};
+
+    fn graph(lines: &[&str]) -> DdsiGraph {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        for line in lines {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(line).unwrap())
+                .unwrap();
+        }
+        DdsiGraph::new(&ddsi_topology)
+    }
+
+    #[test]
+    fn compatible_graph() {
+        let graph = graph(&[WRITER_QOS, READER_SEDP_ST0]);
+
+        assert_eq!(graph.hosts.len(), 2);
+        assert_eq!(graph.topics.len(), 1);
+        assert_eq!(graph.edges.len(), 2);
+        assert!(graph.edges.iter().all(|edge| edge.incompatible.is_empty()));
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("\"p:5bbed783:7b:1\" -> \"t:Test_command_start_cc827fa3\""));
+        assert!(dot.contains("\"t:Test_command_start_cc827fa3\" -> \"p:745ad3d:7b:1\""));
+        assert!(!dot.contains("color=red"));
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(!mermaid.contains("linkStyle"));
+    }
+
+    #[test]
+    fn incompatible_graph() {
+        let best_effort_writer =
+            WRITER_QOS.replace("reliability=1:0.100000000", "reliability=0:0.100000000");
+        let graph = graph(&[&best_effort_writer, READER_SEDP_ST0]);
+
+        assert!(graph
+            .edges
+            .iter()
+            .all(|edge| edge.incompatible.contains("reliability")));
+        assert!(graph.to_dot().contains("color=red"));
+        assert!(graph
+            .to_mermaid()
+            .contains("linkStyle 0,1 stroke:red,stroke-width:2px"));
+    }
+
+    #[test]
+    fn aggregated_edges() {
+        let other_writer = WRITER_QOS.replace(":3902 ", ":4002 ");
+        let best_effort_writer = WRITER_QOS
+            .replace(":3902 ", ":4102 ")
+            .replace("reliability=1:0.100000000", "reliability=0:0.100000000");
+        let graph = graph(&[
+            WRITER_QOS,
+            &other_writer,
+            &best_effort_writer,
+            READER_SEDP_ST0,
+        ]);
+
+        assert_eq!(graph.edges.len(), 2);
+        assert!(graph
+            .edges
+            .iter()
+            .all(|edge| edge.incompatible.contains("reliability")));
+        let n_endpoints: Vec<(bool, usize)> = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.is_writer, edge.n_endpoints))
+            .collect();
+        assert_eq!(n_endpoints, [(false, 1), (true, 3)]);
+    }
+
+    #[test]
+    fn mermaid_escape() {
+        assert_eq!(escape_mermaid("nile.Test.cmd"), "nile.Test.cmd");
+        assert_eq!(
+            escape_mermaid("a\"[b]|<c>#d;{e}(f)"),
+            "a#34;#91;b#93;#124;#60;c#62;#35;d#59;#123;e#125;#40;f#41;"
+        );
+    }
+}
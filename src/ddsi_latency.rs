@@ -0,0 +1,290 @@
+use crate::ddsi_participant::{DdsiParticipant, Qos};
+use crate::ddsi_topology::{DdsiTopology, SortOrder};
+use std::collections::BTreeMap;
+
+/// Number of slowest discoveries reported in the summary.
+const N_SLOWEST: usize = 10;
+
+/// Delay between the creation of endpoints, as logged by the node owning
+/// them, and their discovery by every other node, computed over the
+/// topologies of logs from different nodes.
+#[derive(Debug, Default)]
+pub struct DiscoveryLatency {
+    samples: Vec<LatencySample>,
+}
+
+/// Discovery latency of one endpoint by one observing node.
+#[derive(Debug, PartialEq)]
+pub struct LatencySample {
+    pub guid: String,
+    pub topic: String,
+    pub owner_log: String,
+    pub owner_host: String,
+    pub observer_log: String,
+    pub observer_host: String,
+    pub created: f64,
+    pub discovered: f64,
+}
+
+impl LatencySample {
+    /// Return the discovery latency, in seconds. May be negative if the
+    /// clocks of the nodes are not synchronized.
+    pub fn get_latency(&self) -> f64 {
+        self.discovered - self.created
+    }
+}
+
+impl DiscoveryLatency {
+    /// Compute the discovery latencies from the topologies of several logs,
+    /// given as (log name, topology).
+    ///
+    /// Each discovery of an endpoint is matched with the latest creation of
+    /// the endpoint before it, or with the first creation if the discovery
+    /// predates all of them (clock skew).
+    pub fn new(logs: &[(&str, &DdsiTopology)]) -> DiscoveryLatency {
+        let mut samples = vec![];
+
+        for (owner_log, owner) in logs {
+            for participant_id in owner.get_participants_ids(SortOrder::Guid) {
+                let participant = owner.get_participant(&participant_id).unwrap();
+                if !participant.is_local() {
+                    continue;
+                }
+
+                for (observer_log, observer) in logs {
+                    let observed = match observer.get_participant(&participant_id) {
+                        Some(observed) if !observed.is_local() => observed,
+                        _ => continue,
+                    };
+
+                    for (id, qos, observed_qos) in matching_endpoints(participant, observed) {
+                        for discovered in observed_qos.get_created() {
+                            let created = qos
+                                .get_created()
+                                .iter()
+                                .rev()
+                                .find(|created| *created <= discovered)
+                                .or_else(|| qos.get_created().first());
+
+                            if let Some(created) = created {
+                                samples.push(LatencySample {
+                                    guid: format!("{}:{}", participant_id, id),
+                                    topic: qos.get_topic().clone(),
+                                    owner_log: String::from(*owner_log),
+                                    owner_host: owner.get_own_ip().clone(),
+                                    observer_log: String::from(*observer_log),
+                                    observer_host: observer.get_own_ip().clone(),
+                                    created: *created,
+                                    discovered: *discovered,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        DiscoveryLatency { samples }
+    }
+
+    /// Return all the latency samples.
+    pub fn get_samples(&self) -> &Vec<LatencySample> {
+        &self.samples
+    }
+
+    /// Return the latencies grouped by (creating host, observing host),
+    /// sorted.
+    ///
+    /// Samples are computed per pair of logs, so that the discoveries of
+    /// several logs of the same host, or of hosts without a known address,
+    /// all count, each once.
+    pub fn get_latencies_per_host_pair(&self) -> BTreeMap<(&str, &str), Vec<f64>> {
+        let mut latencies: BTreeMap<(&str, &str), Vec<f64>> = BTreeMap::new();
+
+        for sample in &self.samples {
+            latencies
+                .entry((&sample.owner_host, &sample.observer_host))
+                .or_default()
+                .push(sample.get_latency());
+        }
+        for values in latencies.values_mut() {
+            values.sort_by(f64::total_cmp);
+        }
+        latencies
+    }
+
+    pub fn summarize(&self) -> String {
+        let mut summary = format!("\t- Discovery latency, {} samples:\n", self.samples.len());
+
+        for ((owner_host, observer_host), latencies) in self.get_latencies_per_host_pair() {
+            summary.push_str(&format!(
+                "\t\t- {} -> {}: n={} p50={:.6}s p90={:.6}s p99={:.6}s max={:.6}s\n",
+                owner_host,
+                observer_host,
+                latencies.len(),
+                percentile(&latencies, 50.0),
+                percentile(&latencies, 90.0),
+                percentile(&latencies, 99.0),
+                latencies[latencies.len() - 1],
+            ));
+        }
+
+        let mut slowest: Vec<&LatencySample> = self.get_samples().iter().collect();
+        slowest.sort_by(|a, b| {
+            b.get_latency()
+                .total_cmp(&a.get_latency())
+                .then_with(|| a.guid.cmp(&b.guid))
+        });
+
+        summary.push_str(&format!(
+            "\t\t- Slowest {} discoveries:\n",
+            slowest.len().min(N_SLOWEST)
+        ));
+        for sample in slowest.into_iter().take(N_SLOWEST) {
+            summary.push_str(&format!(
+                "\t\t\t- {} ({}) {} -> {}: {:.6}s\n",
+                sample.guid,
+                sample.topic,
+                sample.owner_host,
+                sample.observer_host,
+                sample.get_latency()
+            ));
+        }
+        summary
+    }
+}
+
+/// Return the endpoints of the owning participant that are also known to
+/// the observer, as (id, owner qos, observer qos).
+fn matching_endpoints<'a>(
+    owner: &'a DdsiParticipant,
+    observer: &'a DdsiParticipant,
+) -> Vec<(String, &'a Qos, &'a Qos)> {
+    let readers = owner
+        .get_readers_id(SortOrder::Guid)
+        .into_iter()
+        .filter_map(|id| {
+            let qos = owner.get_reader_qos(&id)?;
+            let observed_qos = observer.get_reader_qos(&id)?;
+            Some((id, qos, observed_qos))
+        });
+    let writers = owner
+        .get_writers_id(SortOrder::Guid)
+        .into_iter()
+        .filter_map(|id| {
+            let qos = owner.get_writer_qos(&id)?;
+            let observed_qos = observer.get_writer_qos(&id)?;
+            Some((id, qos, observed_qos))
+        });
+    readers.chain(writers).collect()
+}
+
+/// Return the nearest-rank percentile of sorted values.
+pub fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::READER_SEDP_ST0;
+
+    const OWNER_LOG: [&str; 3] = [
+        "2022-01-23T14:08:13+0000 1642947080.000000/      main: ownip: 172.17.0.3",
+        "2022-01-23T14:11:29+0000 1642947089.000000/      main: handleParticipantsSelf: found 745ad3d:7b:1 (self)",
+        "2022-01-23T14:11:29+0000 1642947089.500000/    (anon): READER 745ad3d:7b:1:3b07 QOS={topic=Test_command_start_cc827fa3,type=Test::command_start_cc827fa3,presentation=0:0:0,partition={nile.Test.cmd},durability=2,durability_service=0.000000000:{0:100}:{-1:-1:-1},deadline=2147483647.999999999,latency_budget=0.000000000,liveliness=0:2147483647.999999999,reliability=1:0.100000000,destination_order=0,history=0:100,resource_limits=-1:-1:-1,transport_priority=0,lifespan=2147483647.999999999,ownership=0,time_based_filter=0.000000000,reader_data_lifecycle=2147483647.999999999:2147483647.999999999:0:1:1,relaxed_qos_matching=0,reader_lifespan={0,2147483647.999999999},subscription_keys={0,{}},share={0,},synchronous_endpoint=0}",
+    ];
+
+    /// Log of a node discovering the reader of the owner's node.
+    fn observer_log() -> [String; 2] {
+        [
+            String::from(
+                "2022-01-23T14:08:13+0000 1642947080.000000/      main: ownip: 172.17.0.4",
+            ),
+            READER_SEDP_ST0.replace("172.17.0.4", "172.17.0.3"),
+        ]
+    }
+
+    fn topology<S: AsRef<str>>(lines: &[S]) -> DdsiTopology {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        for line in lines {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(line.as_ref()).unwrap())
+                .unwrap();
+        }
+        ddsi_topology
+    }
+
+    #[test]
+    fn discovery_latency() {
+        let (owner, observer) = (topology(&OWNER_LOG), topology(&observer_log()));
+
+        let latency = DiscoveryLatency::new(&[("owner.log", &owner), ("observer.log", &observer)]);
+
+        assert_eq!(latency.get_samples().len(), 1);
+        let sample = &latency.get_samples()[0];
+        assert_eq!(sample.guid, "745ad3d:7b:1:3b07");
+        assert_eq!(sample.owner_host, "172.17.0.3");
+        assert_eq!(sample.observer_host, "172.17.0.4");
+        assert!((sample.get_latency() - 0.395283).abs() < 1e-6);
+
+        let per_host_pair = latency.get_latencies_per_host_pair();
+        assert_eq!(per_host_pair.len(), 1);
+        assert!(per_host_pair.contains_key(&("172.17.0.3", "172.17.0.4")));
+        assert!(latency
+            .summarize()
+            .contains("\t\t- 172.17.0.3 -> 172.17.0.4: n=1 "));
+    }
+
+    #[test]
+    fn no_latency_from_a_single_log() {
+        let owner = topology(&OWNER_LOG);
+
+        assert!(DiscoveryLatency::new(&[("owner.log", &owner)])
+            .get_samples()
+            .is_empty());
+    }
+
+    #[test]
+    fn percentiles() {
+        let values: Vec<f64> = (1..=100).map(f64::from).collect();
+
+        assert_eq!(percentile(&values, 50.0), 50.0);
+        assert_eq!(percentile(&values, 90.0), 90.0);
+        assert_eq!(percentile(&values, 99.0), 99.0);
+        assert_eq!(percentile(&values, 100.0), 100.0);
+        assert_eq!(percentile(&[3.0], 50.0), 3.0);
+    }
+
+    #[test]
+    fn latency_of_logs_of_same_host() {
+        // Two logs of the same observing host.
+        let (owner, first, second) = (
+            topology(&OWNER_LOG),
+            topology(&observer_log()),
+            topology(&observer_log()),
+        );
+
+        let latency = DiscoveryLatency::new(&[
+            ("owner.log", &owner),
+            ("first.log", &first),
+            ("second.log", &second),
+        ]);
+
+        let observer_logs: Vec<&str> = latency
+            .get_samples()
+            .iter()
+            .map(|sample| sample.observer_log.as_str())
+            .collect();
+        assert_eq!(observer_logs, ["first.log", "second.log"]);
+        let per_host_pair = latency.get_latencies_per_host_pair();
+        assert_eq!(per_host_pair.len(), 1);
+        assert_eq!(per_host_pair[&("172.17.0.3", "172.17.0.4")].len(), 2);
+    }
+}
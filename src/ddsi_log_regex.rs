@@ -1,10 +1,12 @@
 use regex::{Captures, Regex, RegexSet};
+use std::num::ParseFloatError;
 
 /// Provide a set of regular expressions and methods for parsing ddsi log
 /// entries.
 pub struct DdsiLogRegex {
     regex_set: RegexSet,
     regex: Vec<Regex>,
+    submessage_regex: Regex,
 }
 
 pub enum DdsiLogType<'a> {
@@ -16,6 +18,14 @@ pub enum DdsiLogType<'a> {
     OwnIp(Captures<'a>),
     WriterSedpSt3(Captures<'a>),
     ReaderSedpSt3(Captures<'a>),
+    Heartbeat(Captures<'a>),
+    AckNack(Captures<'a>),
+    NackFrag(Captures<'a>),
+    WriteSample(Captures<'a>),
+    Data(Captures<'a>),
+    ThreadCputime(Captures<'a>),
+    Config(Captures<'a>),
+    Gap(Captures<'a>),
 }
 
 impl<'a> DdsiLogType<'a> {
@@ -29,8 +39,20 @@ impl<'a> DdsiLogType<'a> {
             DdsiLogType::OwnIp(capture) => capture,
             DdsiLogType::WriterSedpSt3(capture) => capture,
             DdsiLogType::ReaderSedpSt3(capture) => capture,
+            DdsiLogType::Heartbeat(capture) => capture,
+            DdsiLogType::AckNack(capture) => capture,
+            DdsiLogType::NackFrag(capture) => capture,
+            DdsiLogType::WriteSample(capture) => capture,
+            DdsiLogType::Data(capture) => capture,
+            DdsiLogType::ThreadCputime(capture) => capture,
+            DdsiLogType::Config(capture) => capture,
+            DdsiLogType::Gap(capture) => capture,
         }
     }
+    /// Return the timestamp of the log entry, in seconds.
+    pub fn get_timestamp(&self) -> Result<f64, ParseFloatError> {
+        self.get_capture()["timestamp"].parse()
+    }
     pub fn get_system_id(&self) -> String {
         match self {
             DdsiLogType::HandleParticipantsSelf(capture) => String::from(&capture["system_id"]),
@@ -41,13 +63,102 @@ impl<'a> DdsiLogType<'a> {
             DdsiLogType::OwnIp(_) => String::from("ownip"),
             DdsiLogType::WriterSedpSt3(capture) => String::from(&capture["system_id"]),
             DdsiLogType::ReaderSedpSt3(capture) => String::from(&capture["system_id"]),
+            DdsiLogType::Heartbeat(capture) => String::from(&capture["system_id"]),
+            DdsiLogType::AckNack(capture) => String::from(&capture["system_id"]),
+            DdsiLogType::NackFrag(capture) => String::from(&capture["system_id"]),
+            DdsiLogType::WriteSample(capture) => String::from(&capture["system_id"]),
+            DdsiLogType::Data(capture) => String::from(&capture["system_id"]),
+            DdsiLogType::ThreadCputime(_) => String::from("thread_cputime"),
+            DdsiLogType::Config(_) => String::from("config"),
+            DdsiLogType::Gap(capture) => String::from(&capture["system_id"]),
+        }
+    }
+    /// Return the name of the kind of log entry.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            DdsiLogType::HandleParticipantsSelf(_) => "handle_participants_self",
+            DdsiLogType::WriterQos(_) => "writer_qos",
+            DdsiLogType::ReaderQos(_) => "reader_qos",
+            DdsiLogType::WriterSedpSt0(_) => "writer_sedp_st0",
+            DdsiLogType::ReaderSedpSt0(_) => "reader_sedp_st0",
+            DdsiLogType::OwnIp(_) => "own_ip",
+            DdsiLogType::WriterSedpSt3(_) => "writer_sedp_st3",
+            DdsiLogType::ReaderSedpSt3(_) => "reader_sedp_st3",
+            DdsiLogType::Heartbeat(_) => "heartbeat",
+            DdsiLogType::AckNack(_) => "acknack",
+            DdsiLogType::NackFrag(_) => "nackfrag",
+            DdsiLogType::WriteSample(_) => "write_sample",
+            DdsiLogType::Data(_) => "data",
+            DdsiLogType::ThreadCputime(_) => "thread_cputime",
+            DdsiLogType::Config(_) => "config",
+            DdsiLogType::Gap(_) => "gap",
+        }
+    }
+    /// Return the index of the regex expression matching the log entry.
+    fn get_index(&self) -> usize {
+        match self {
+            DdsiLogType::HandleParticipantsSelf(_) => 0,
+            DdsiLogType::WriterQos(_) => 1,
+            DdsiLogType::ReaderQos(_) => 2,
+            DdsiLogType::WriterSedpSt0(_) => 3,
+            DdsiLogType::ReaderSedpSt0(_) => 4,
+            DdsiLogType::OwnIp(_) => 5,
+            DdsiLogType::WriterSedpSt3(_) => 6,
+            DdsiLogType::ReaderSedpSt3(_) => 7,
+            DdsiLogType::Heartbeat(_) => 8,
+            DdsiLogType::AckNack(_) => 9,
+            DdsiLogType::NackFrag(_) => 10,
+            DdsiLogType::WriteSample(_) => 11,
+            DdsiLogType::Data(_) => 12,
+            DdsiLogType::ThreadCputime(_) => 13,
+            DdsiLogType::Config(_) => 14,
+            DdsiLogType::Gap(_) => 15,
         }
     }
+    /// Check if the log entry updates the topology.
+    pub fn updates_topology(&self) -> bool {
+        !matches!(
+            self,
+            DdsiLogType::Heartbeat(_)
+                | DdsiLogType::AckNack(_)
+                | DdsiLogType::NackFrag(_)
+                | DdsiLogType::Gap(_)
+                | DdsiLogType::WriteSample(_)
+                | DdsiLogType::Data(_)
+        )
+    }
 }
 
 const HEADER_REGEX: &str = r"(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})T(?P<hour>\d{2}):(?P<min>\d{2}):(?P<sec>\d{2})\+(?P<timezone>\d{4}) (?P<timestamp>[0-9]*\.[0-9]*)/";
 const WRITER_QOS_REGEX: &str = r"QOS=\{topic=(?P<topic>[a-zA-Z0-9_]*),type=(?P<type>[a-zA-Z0-9_:]*),presentation=(?P<presentation>[a-zA-Z0-9_:]*),partition=\{(?P<partition>.*)\},durability=(?P<qos_durability>[a-zA-Z0-9_:]*),durability_service=(?P<durability_service>[a-zA-Z0-9_:\{\}\.\-]*),deadline=(?P<deadline>[a-zA-Z0-9\.]*),latency_budget=(?P<latency_budget>[a-zA-Z0-9\.]*),liveliness=(?P<liveliness>[a-zA-Z0-9_:\.]*),reliability=(?P<qos_reliability>[a-zA-Z0-9_:\.]*),destination_order=(?P<destination_order>[a-zA-Z0-9_:]*),history=(?P<history>[a-zA-Z0-9_:\-]*),resource_limits=(?P<resource_limits>[a-zA-Z0-9_:\-]*),transport_priority=(?P<transport_priority>[a-zA-Z0-9_:]*),lifespan=(?P<lifespan>[a-zA-Z0-9_:\.]*),ownership=(?P<ownership>[a-zA-Z0-9_:]*),ownership_strength=(?P<ownership_strength>[a-zA-Z0-9_:]*),writer_data_lifecycle=\{(?P<writer_data_lifecycle>[a-zA-Z0-9_:\.,]*)\},relaxed_qos_matching=(?P<relaxed_qos_matching>[a-zA-Z0-9_:]*),synchronous_endpoint=(?P<synchronous_endpoint>[a-zA-Z0-9_:]*)\}";
-const READER_QOS_REGEX: &str = r"QOS=\{topic=(?P<topic>[a-zA-Z0-9_]*),type=(?P<type>[a-zA-Z0-9_:]*),presentation=(?P<presentation>[a-zA-Z0-9_:]*),partition=\{(?P<partition>.*)\},durability=(?P<qos_durability>[a-zA-Z0-9_:]*)(,durability_service=)?(?P<durability_service>[a-zA-Z0-9_:\{\}\.\-]*)?,deadline=(?P<deadline>[a-zA-Z0-9\.]*),latency_budget=(?P<latency_budget>[a-zA-Z0-9\.]*),liveliness=(?P<liveliness>[a-zA-Z0-9_:\.]*),reliability=(?P<qos_reliability>[a-zA-Z0-9_:\.]*),destination_order=(?P<destination_order>[a-zA-Z0-9_:]*),history=(?P<history>[a-zA-Z0-9_:\-]*),resource_limits=(?P<resource_limits>[a-zA-Z0-9_:\-]*),transport_priority=(?P<transport_priority>[a-zA-Z0-9_:]*)(,lifespan=)?(?P<lifespan>[a-zA-Z0-9_:\.]*)?,ownership=(?P<ownership>[a-zA-Z0-9_:]*),time_based_filter=(?P<time_based_filter>[0-9\.]*),reader_data_lifecycle=(?P<reader_data_lifecycle>[0-9_:\.]*),relaxed_qos_matching=(?P<relaxed_qos_matching>[0-9]*),reader_lifespan=\{(?P<reader_lifespan>[0-9\.,])*\},subscription_keys=\{(?P<subscription_keys>[0-9\{\},]*)\},share=\{(?P<share>[0-9\{\},]*)\},synchronous_endpoint=(?P<synchronous_endpoint>[a-zA-Z0-9_:]*)\}";
+const READER_QOS_REGEX: &str = r"QOS=\{topic=(?P<topic>[a-zA-Z0-9_]*),type=(?P<type>[a-zA-Z0-9_:]*),presentation=(?P<presentation>[a-zA-Z0-9_:]*),partition=\{(?P<partition>.*)\},durability=(?P<qos_durability>[a-zA-Z0-9_:]*)(,durability_service=)?(?P<durability_service>[a-zA-Z0-9_:\{\}\.\-]*)?,deadline=(?P<deadline>[a-zA-Z0-9\.]*),latency_budget=(?P<latency_budget>[a-zA-Z0-9\.]*),liveliness=(?P<liveliness>[a-zA-Z0-9_:\.]*),reliability=(?P<qos_reliability>[a-zA-Z0-9_:\.]*),destination_order=(?P<destination_order>[a-zA-Z0-9_:]*),history=(?P<history>[a-zA-Z0-9_:\-]*),resource_limits=(?P<resource_limits>[a-zA-Z0-9_:\-]*),transport_priority=(?P<transport_priority>[a-zA-Z0-9_:]*)(,lifespan=)?(?P<lifespan>[a-zA-Z0-9_:\.]*)?,ownership=(?P<ownership>[a-zA-Z0-9_:]*),time_based_filter=(?P<time_based_filter>[0-9\.]*),reader_data_lifecycle=(?P<reader_data_lifecycle>[0-9_:\.]*),relaxed_qos_matching=(?P<relaxed_qos_matching>[0-9]*),reader_lifespan=\{(?P<reader_lifespan>[0-9\.,]*)\},subscription_keys=\{(?P<subscription_keys>[0-9\{\},]*)\},share=\{(?P<share>[0-9\{\},]*)\},synchronous_endpoint=(?P<synchronous_endpoint>[a-zA-Z0-9_:]*)\}";
+/// QoS policies reported in the QOS={...} section of a log entry, as pairs of
+/// policy name and name of the capture group holding its value.
+pub const QOS_POLICIES: [(&str, &str); 23] = [
+    ("type", "type"),
+    ("presentation", "presentation"),
+    ("durability", "qos_durability"),
+    ("durability_service", "durability_service"),
+    ("deadline", "deadline"),
+    ("latency_budget", "latency_budget"),
+    ("liveliness", "liveliness"),
+    ("reliability", "qos_reliability"),
+    ("destination_order", "destination_order"),
+    ("history", "history"),
+    ("resource_limits", "resource_limits"),
+    ("transport_priority", "transport_priority"),
+    ("lifespan", "lifespan"),
+    ("ownership", "ownership"),
+    ("ownership_strength", "ownership_strength"),
+    ("writer_data_lifecycle", "writer_data_lifecycle"),
+    ("time_based_filter", "time_based_filter"),
+    ("reader_data_lifecycle", "reader_data_lifecycle"),
+    ("relaxed_qos_matching", "relaxed_qos_matching"),
+    ("reader_lifespan", "reader_lifespan"),
+    ("subscription_keys", "subscription_keys"),
+    ("share", "share"),
+    ("synchronous_endpoint", "synchronous_endpoint"),
+];
 const SYSTEM_ID_REGEX: &str = r"(?P<system_id>[a-zA-Z0-9]*:[a-zA-Z0-9]*:[a-zA-Z0-9]*)";
 const RW_ID_REGEX: &str = r"(?P<rw_id>[a-zA-Z0-9]*)";
 const RELIABILITY_REGEX: &str = r"(?P<reliability>reliable|best-effort)";
@@ -57,6 +168,29 @@ const SUBNET_REGEX: &str = r"(?P<subnet>[0-9\.]*)";
 const SUBNET_PORT_REGEX: &str = r"(?P<subnet_port>[0-9]*)";
 const HOSTNAME_REGEX: &str = r"(?P<hostname>[0-9\.]*)";
 const HOSTNAME_PORT_REGEX: &str = r"(?P<hostname_port>[0-9]*)";
+const DST_SYSTEM_ID_REGEX: &str = r"(?P<dst_system_id>[a-zA-Z0-9]*:[a-zA-Z0-9]*:[a-zA-Z0-9]*)";
+const DST_RW_ID_REGEX: &str = r"(?P<dst_rw_id>[a-zA-Z0-9]*)";
+const BITMAP_REGEX: &str = r"(?P<bitmap>[01]*)";
+const SEQ_REGEX: &str = r"#(?P<seq>-?[0-9]*)";
+const SIZE_REGEX: &str = r"(.*\bsize[=: ](?P<size>[0-9]*))?";
+/// Start of an RTPS submessage in a `recv` or `xmit` line.
+const SUBMESSAGE_REGEX: &str = r"\b(?P<kind>INFOTS|INFODST|INFOSRC|INFOREPLY|DATA|DATAFRAG|HEARTBEAT|HEARTBEATFRAG|ACKNACK|NACKFRAG|GAP|PAD)\(";
+
+/// Indices of the regex expressions parsing submessages.
+const SUBMESSAGE_INDICES: [usize; 5] = [8, 9, 10, 12, 15];
+
+/// Return the index of the regex expression parsing a submessage kind, if
+/// it is parsed.
+fn submessage_index(kind: &str) -> Option<usize> {
+    match kind {
+        "HEARTBEAT" => Some(8),
+        "ACKNACK" => Some(9),
+        "NACKFRAG" => Some(10),
+        "DATA" => Some(12),
+        "GAP" => Some(15),
+        _ => None,
+    }
+}
 
 impl DdsiLogRegex {
     /// Create a new instance of DdsiLogRegex with the regular expressions
@@ -159,14 +293,110 @@ impl DdsiLogRegex {
                 "delete_proxy_reader",
             ]
             .join(r""),
+            [
+                HEADER_REGEX,
+                r".*HEARTBEAT\((?P<flags>[FL]*)#(?P<count>[0-9]*):(?P<first_seq>-?[0-9]*)\.\.(?P<last_seq>-?[0-9]*) ",
+                SYSTEM_ID_REGEX,
+                r":",
+                RW_ID_REGEX,
+                r" -> ",
+                DST_SYSTEM_ID_REGEX,
+                r":",
+                DST_RW_ID_REGEX,
+            ]
+            .join(r""),
+            [
+                HEADER_REGEX,
+                r".*ACKNACK\((?P<flags>F?)#(?P<count>[0-9]*):(?P<base>-?[0-9]*)/(?P<numbits>[0-9]*):",
+                BITMAP_REGEX,
+                r"( L\([^\)]*\))? ",
+                SYSTEM_ID_REGEX,
+                r":",
+                RW_ID_REGEX,
+                r" -> ",
+                DST_SYSTEM_ID_REGEX,
+                r":",
+                DST_RW_ID_REGEX,
+                r"(.* rexmit#(?P<rexmit>[0-9]*))?",
+            ]
+            .join(r""),
+            [
+                HEADER_REGEX,
+                r".*NACKFRAG\(#(?P<count>[0-9]*):(?P<seq>-?[0-9]*)/(?P<base>[0-9]*)/(?P<numbits>[0-9]*):",
+                BITMAP_REGEX,
+                r" ",
+                SYSTEM_ID_REGEX,
+                r":",
+                RW_ID_REGEX,
+                r" -> ",
+                DST_SYSTEM_ID_REGEX,
+                r":",
+                DST_RW_ID_REGEX,
+            ]
+            .join(r""),
+            [
+                HEADER_REGEX,
+                r".*write_sample ",
+                SYSTEM_ID_REGEX,
+                r":",
+                RW_ID_REGEX,
+                r" ",
+                SEQ_REGEX,
+                SIZE_REGEX,
+                r"(?P<failed>.*FAILED)?",
+            ]
+            .join(r""),
+            [
+                HEADER_REGEX,
+                r".*DATA\(",
+                SYSTEM_ID_REGEX,
+                r":",
+                RW_ID_REGEX,
+                r" -> ",
+                DST_SYSTEM_ID_REGEX,
+                r":",
+                DST_RW_ID_REGEX,
+                r" ",
+                SEQ_REGEX,
+                SIZE_REGEX,
+            ]
+            .join(r""),
+            [
+                HEADER_REGEX,
+                r"\s*(?P<thread>[^:]*): thread_cputime (?P<cputime>[0-9]*\.[0-9]*)",
+            ]
+            .join(r""),
+            [
+                HEADER_REGEX,
+                r"\s*(?P<thread>[^:]*): config: (?P<key>\S+?): (?P<value>.*?)( \{[0-9,]*\})?$",
+            ]
+            .join(r""),
+            [
+                HEADER_REGEX,
+                r".*GAP\((?P<first_seq>-?[0-9]*)\.\.(?P<last_seq>-?[0-9]*)/(?P<numbits>[0-9]*):",
+                BITMAP_REGEX,
+                r" ",
+                SYSTEM_ID_REGEX,
+                r":",
+                RW_ID_REGEX,
+                r" -> ",
+                DST_SYSTEM_ID_REGEX,
+                r":",
+                DST_RW_ID_REGEX,
+            ]
+            .join(r""),
         ])
         .unwrap();
         let regex = regex_set
             .patterns()
-            .into_iter()
+            .iter()
             .map(|pattern| Regex::new(pattern).unwrap())
             .collect();
-        DdsiLogRegex { regex_set, regex }
+        DdsiLogRegex {
+            regex_set,
+            regex,
+            submessage_regex: Regex::new(SUBMESSAGE_REGEX).unwrap(),
+        }
     }
 
     /// Parse an input string using the collection of regular expressions for
@@ -177,42 +407,131 @@ impl DdsiLogRegex {
     /// * `text` - A text to parse.
     ///
     pub fn parse<'a>(&self, text: &'a str) -> Option<DdsiLogType<'a>> {
-        if let Some(match_index) = self.get_match_index(text) {
-            let capture = self.regex[match_index].captures(text).unwrap();
-
-            match match_index {
-                0 => Some(DdsiLogType::HandleParticipantsSelf(capture)),
-                1 => Some(DdsiLogType::WriterQos(capture)),
-                2 => Some(DdsiLogType::ReaderQos(capture)),
-                3 => Some(DdsiLogType::WriterSedpSt0(capture)),
-                4 => Some(DdsiLogType::ReaderSedpSt0(capture)),
-                5 => Some(DdsiLogType::OwnIp(capture)),
-                6 => Some(DdsiLogType::WriterSedpSt3(capture)),
-                7 => Some(DdsiLogType::ReaderSedpSt3(capture)),
-                _ => None,
-            }
-        } else {
-            None
+        self.get_match_index(text)
+            .and_then(|match_index| self.parse_index(text, match_index))
+    }
+
+    /// Parse all the ddsi log entries of an input string, e.g. the several
+    /// submessages (INFOTS, DATA, HEARTBEAT...) of a `recv` line, in the
+    /// order they appear in the line.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - A text to parse.
+    ///
+    pub fn parse_all<'a>(&self, text: &'a str) -> Vec<DdsiLogType<'a>> {
+        let matches = self.regex_set.matches(text);
+        let mut ddsi_log_types: Vec<DdsiLogType<'a>> = matches
+            .iter()
+            .filter(|match_index| !SUBMESSAGE_INDICES.contains(match_index))
+            .filter_map(|match_index| self.parse_index(text, match_index))
+            .collect();
+
+        if SUBMESSAGE_INDICES
+            .iter()
+            .any(|index| matches.matched(*index))
+        {
+            ddsi_log_types.extend(self.parse_submessages(text));
         }
+        ddsi_log_types
     }
+
+    /// Parse each submessage of a line, in line order.
+    ///
+    /// The submessage regexes match the last occurrence of their kind, so
+    /// each one is matched against the line up to the end of its submessage
+    /// and kept only if the match starts in that submessage.
+    fn parse_submessages<'a>(&self, text: &'a str) -> Vec<DdsiLogType<'a>> {
+        let starts: Vec<(usize, &str)> = self
+            .submessage_regex
+            .captures_iter(text)
+            .map(|capture| {
+                (
+                    capture.get(0).unwrap().start(),
+                    capture.name("kind").unwrap().as_str(),
+                )
+            })
+            .collect();
+
+        starts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (start, kind))| {
+                let match_index = submessage_index(kind)?;
+                let end = starts.get(i + 1).map_or(text.len(), |(next, _)| *next);
+                let capture = self.regex[match_index].captures(&text[..end])?;
+                if capture.name("system_id")?.start() < *start {
+                    return None;
+                }
+                Self::from_capture(match_index, capture)
+            })
+            .collect()
+    }
+
+    /// Parse an input string with the regular expression of the given index.
+    fn parse_index<'a>(&self, text: &'a str, match_index: usize) -> Option<DdsiLogType<'a>> {
+        let capture = self.regex[match_index].captures(text).unwrap();
+        Self::from_capture(match_index, capture)
+    }
+
+    /// Wrap the capture of the regular expression of the given index.
+    fn from_capture(match_index: usize, capture: Captures) -> Option<DdsiLogType> {
+        match match_index {
+            0 => Some(DdsiLogType::HandleParticipantsSelf(capture)),
+            1 => Some(DdsiLogType::WriterQos(capture)),
+            2 => Some(DdsiLogType::ReaderQos(capture)),
+            3 => Some(DdsiLogType::WriterSedpSt0(capture)),
+            4 => Some(DdsiLogType::ReaderSedpSt0(capture)),
+            5 => Some(DdsiLogType::OwnIp(capture)),
+            6 => Some(DdsiLogType::WriterSedpSt3(capture)),
+            7 => Some(DdsiLogType::ReaderSedpSt3(capture)),
+            8 => Some(DdsiLogType::Heartbeat(capture)),
+            9 => Some(DdsiLogType::AckNack(capture)),
+            10 => Some(DdsiLogType::NackFrag(capture)),
+            11 => Some(DdsiLogType::WriteSample(capture)),
+            12 => Some(DdsiLogType::Data(capture)),
+            13 => Some(DdsiLogType::ThreadCputime(capture)),
+            14 => Some(DdsiLogType::Config(capture)),
+            15 => Some(DdsiLogType::Gap(capture)),
+            _ => None,
+        }
+    }
+    /// Return the number of kinds of log entries recognized.
+    pub fn get_number_of_kinds(&self) -> usize {
+        self.regex_set.len()
+    }
+
     /// Check if input text is a valid ddsi log entry.
     pub fn is_match(&self, text: &str) -> bool {
         self.regex_set.is_match(text)
     }
 
+    /// Return the names of the fields captured for a log entry.
+    pub fn get_field_names<'r>(
+        &'r self,
+        ddsi_log_type: &DdsiLogType,
+    ) -> impl Iterator<Item = &'r str> {
+        self.regex[ddsi_log_type.get_index()]
+            .capture_names()
+            .flatten()
+    }
+
     /// Return the index of the regex expression that matches the input text.
     fn get_match_index(&self, text: &str) -> Option<usize> {
-        if text.contains("thread_cputime") {
-            None
-        } else {
-            self.regex_set.matches(text).into_iter().next()
-        }
+        self.regex_set.matches(text).into_iter().next()
+    }
+}
+
+impl Default for DdsiLogRegex {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ddsi_test_data::{BUILTIN_WRITER_SEDP_ST0, DURABILITY_WRITER_QOS, READER_SEDP_ST0};
 
     #[test]
     fn dds_log_regex_handle_participants_self() {
@@ -318,6 +637,32 @@ mod tests {
         assert_eq!(&capture["synchronous_endpoint"], "0");
     }
 
+    #[test]
+    fn reader_lifespan_capture() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let qos = "QOS={topic=Test_logevent_logLevel_418de7a5,type=Test::logevent_logLevel_418de7a5,presentation=0:0:0,partition={nile.Test.data},durability=2,deadline=2147483647.999999999,latency_budget=0.000000000,liveliness=0:2147483647.999999999,reliability=1:0.100000000,destination_order=0,history=0:100,resource_limits=-1:-1:-1,transport_priority=0,ownership=0,time_based_filter=0.000000000,reader_data_lifecycle=2147483647.999999999:2147483647.999999999:0:1:1,relaxed_qos_matching=0,reader_lifespan={1,10.500000000},subscription_keys={0,{}},share={0,},synchronous_endpoint=0}";
+
+        // The whole value is captured, not only its last character, both for
+        // local readers and for discovered ones.
+        for line in [
+            format!("2022-01-23T14:11:29+0000 1642947089.987560/    (anon): READER 5bbed783:7b:1:3907 {}", qos),
+            format!("2022-01-23T14:11:29+0000 1642947089.904222/dq.builtin: SEDP ST0 745ad3d:7b:1:5507 reliable transient reader: nile.Test.data.Test_logevent_logLevel_418de7a5/Test::logevent_logLevel_418de7a5 p(open) NEW (as 239.255.0.1:7401 172.17.0.4:39948) {}", qos),
+        ] {
+            let ddsi_log_type = dds_log_regex.parse(&line).unwrap();
+            assert_eq!(&ddsi_log_type.get_capture()["reader_lifespan"], "1,10.500000000");
+        }
+
+        let empty = qos.replace("reader_lifespan={1,10.500000000}", "reader_lifespan={}");
+        let line = format!(
+            "2022-01-23T14:11:29+0000 1642947089.987560/    (anon): READER 5bbed783:7b:1:3907 {}",
+            empty
+        );
+        assert_eq!(
+            &dds_log_regex.parse(&line).unwrap().get_capture()["reader_lifespan"],
+            ""
+        );
+    }
+
     #[test]
     fn dds_log_regex_writer_sedp_st0() {
         let dds_log_regex = DdsiLogRegex::new();
@@ -429,6 +774,7 @@ mod tests {
         assert_eq!(&capture["transport_priority"], "0");
         assert_eq!(&capture["lifespan"], "2147483647.999999999");
         assert_eq!(&capture["ownership"], "0");
+        assert_eq!(&capture["reader_lifespan"], "0,2147483647.999999999");
         assert_eq!(&capture["synchronous_endpoint"], "0");
     }
 
@@ -527,15 +873,227 @@ mod tests {
         assert_eq!(&capture["rw_id"], "3007");
     }
 
+    #[test]
+    fn heartbeat() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let heartbeat =
+        "2022-01-23T14:11:52+0000 1642947112.100000/      recv: INFOTS(1642947112.099000) HEARTBEAT(F#12:3..17 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107: 5bbed783:7b:1:3107@16(sync))";
+
+        let matches = dds_log_regex.regex_set.matches(heartbeat);
+
+        assert!(matches.matched(8));
+
+        let capture = dds_log_regex.regex[8].captures(heartbeat).unwrap();
+
+        assert_eq!(&capture["flags"], "F");
+        assert_eq!(&capture["count"], "12");
+        assert_eq!(&capture["first_seq"], "3");
+        assert_eq!(&capture["last_seq"], "17");
+        assert_eq!(&capture["system_id"], "745ad3d:7b:1");
+        assert_eq!(&capture["rw_id"], "3102");
+        assert_eq!(&capture["dst_system_id"], "5bbed783:7b:1");
+        assert_eq!(&capture["dst_rw_id"], "3107");
+    }
+
+    #[test]
+    fn acknack() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let acknack =
+        "2022-01-23T14:11:52+0000 1642947112.200000/      recv: ACKNACK(#8:15/3:101 L(:1c1 1642947112.199000) 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102 RX15 RX17 rexmit#2 maxseq:17<17<=17)";
+
+        let matches = dds_log_regex.regex_set.matches(acknack);
+
+        assert!(matches.matched(9));
+
+        let capture = dds_log_regex.regex[9].captures(acknack).unwrap();
+
+        assert_eq!(&capture["count"], "8");
+        assert_eq!(&capture["base"], "15");
+        assert_eq!(&capture["numbits"], "3");
+        assert_eq!(&capture["bitmap"], "101");
+        assert_eq!(&capture["system_id"], "5bbed783:7b:1");
+        assert_eq!(&capture["rw_id"], "3107");
+        assert_eq!(&capture["dst_system_id"], "745ad3d:7b:1");
+        assert_eq!(&capture["dst_rw_id"], "3102");
+        assert_eq!(&capture["rexmit"], "2");
+    }
+
+    #[test]
+    fn nackfrag() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let nackfrag =
+        "2022-01-23T14:11:52+0000 1642947112.300000/      recv: NACKFRAG(#3:16/2/4:0110 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102)";
+
+        let matches = dds_log_regex.regex_set.matches(nackfrag);
+
+        assert!(matches.matched(10));
+
+        let capture = dds_log_regex.regex[10].captures(nackfrag).unwrap();
+
+        assert_eq!(&capture["seq"], "16");
+        assert_eq!(&capture["bitmap"], "0110");
+        assert_eq!(&capture["system_id"], "5bbed783:7b:1");
+        assert_eq!(&capture["dst_rw_id"], "3102");
+    }
+
+    #[test]
+    fn write_sample() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let write_sample =
+        "2022-01-23T14:11:52+0000 1642947112.400000/    (anon): write_sample 745ad3d:7b:1:3102 #18: ST0 nile.Test.cmd.Test_command_start_cc827fa3/Test::command_start_cc827fa3:{0,\"\"} size=64";
+
+        let matches = dds_log_regex.regex_set.matches(write_sample);
+
+        assert!(matches.matched(11));
+
+        let capture = dds_log_regex.regex[11].captures(write_sample).unwrap();
+
+        assert_eq!(&capture["system_id"], "745ad3d:7b:1");
+        assert_eq!(&capture["rw_id"], "3102");
+        assert_eq!(&capture["seq"], "18");
+        assert_eq!(&capture["size"], "64");
+        assert!(capture.name("failed").is_none());
+    }
+
+    #[test]
+    fn data() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let data =
+        "2022-01-23T14:11:52+0000 1642947112.500000/      recv: INFOTS(1642947112.400000) DATA(745ad3d:7b:1:3102 -> 0:0:0:0 #18 L(:1c1 1642947112.500000) => EVERYONE";
+
+        let matches = dds_log_regex.regex_set.matches(data);
+
+        assert!(matches.matched(12));
+
+        let capture = dds_log_regex.regex[12].captures(data).unwrap();
+
+        assert_eq!(&capture["system_id"], "745ad3d:7b:1");
+        assert_eq!(&capture["rw_id"], "3102");
+        assert_eq!(&capture["dst_system_id"], "0:0:0");
+        assert_eq!(&capture["seq"], "18");
+        assert!(capture.name("size").is_none());
+    }
+
+    #[test]
+    fn thread_cputime() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let thread_cputime =
+            "2022-01-20T13:24:36+0000 1642685076.168332/dq.builtin: thread_cputime 1260.618874505";
+
+        let matches = dds_log_regex.regex_set.matches(thread_cputime);
+
+        assert!(matches.matched(13));
+
+        let capture = dds_log_regex.regex[13].captures(thread_cputime).unwrap();
+
+        assert_eq!(&capture["thread"], "dq.builtin");
+        assert_eq!(&capture["cputime"], "1260.618874505");
+        assert!(matches!(
+            dds_log_regex.parse(thread_cputime),
+            Some(DdsiLogType::ThreadCputime(_))
+        ));
+    }
+
+    #[test]
+    fn config() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let config = "2022-01-23T14:08:13+0000 1642946893.209000/      main: config: Domain/General/NetworkInterfaceAddress/#text: auto {0}";
+
+        let matches = dds_log_regex.regex_set.matches(config);
+
+        assert!(matches.matched(14));
+
+        let capture = dds_log_regex.regex[14].captures(config).unwrap();
+
+        assert_eq!(
+            &capture["key"],
+            "Domain/General/NetworkInterfaceAddress/#text"
+        );
+        assert_eq!(&capture["value"], "auto");
+
+        let config_default =
+            "2022-01-23T14:08:13+0000 1642946893.209000/      main: config: Domain/Discovery/Ports/Base: 7400 {}";
+        let capture = dds_log_regex.regex[14].captures(config_default).unwrap();
+
+        assert_eq!(&capture["key"], "Domain/Discovery/Ports/Base");
+        assert_eq!(&capture["value"], "7400");
+    }
+
+    #[test]
+    fn dds_log_regex_gap() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let gap =
+        "2022-01-23T14:11:52+0000 1642947112.600000/      recv: GAP(3..5/4:0101 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107)";
+
+        let capture = dds_log_regex.parse(gap).unwrap();
+
+        assert_eq!(capture.get_name(), "gap");
+        let capture = capture.get_capture();
+        assert_eq!(&capture["first_seq"], "3");
+        assert_eq!(&capture["last_seq"], "5");
+        assert_eq!(&capture["numbits"], "4");
+        assert_eq!(&capture["bitmap"], "0101");
+        assert_eq!(&capture["system_id"], "745ad3d:7b:1");
+        assert_eq!(&capture["rw_id"], "3102");
+        assert_eq!(&capture["dst_system_id"], "5bbed783:7b:1");
+        assert_eq!(&capture["dst_rw_id"], "3107");
+    }
+
+    #[test]
+    fn parse_all_submessages() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let recv = "2022-01-23T14:11:52+0000 1642947112.500000/      recv: INFOTS(1642947112.400000) DATA(745ad3d:7b:1:3102 -> 0:0:0:0 #18 L(:1c1 1642947112.500000) => EVERYONE HEARTBEAT(#13:1..18 745ad3d:7b:1:3102 -> 0:0:0:0:)";
+
+        let names: Vec<&str> = dds_log_regex
+            .parse_all(recv)
+            .iter()
+            .map(|ddsi_log_type| ddsi_log_type.get_name())
+            .collect();
+
+        assert_eq!(names, ["data", "heartbeat"]);
+        assert_eq!(dds_log_regex.parse(recv).unwrap().get_name(), "heartbeat");
+    }
+
+    #[test]
+    fn parse_all_submessages_of_same_kind() {
+        let dds_log_regex = DdsiLogRegex::new();
+        let recv = "2022-01-23T14:11:52+0000 1642947112.500000/      recv: INFOTS(1642947112.400000) DATA(745ad3d:7b:1:3102 -> 0:0:0:0 #18 L(:1c1 1642947112.500000) => EVERYONE INFOTS(1642947112.450000) DATA(745ad3d:7b:1:3102 -> 0:0:0:0 #19 L(:1c1 1642947112.500000) => EVERYONE HEARTBEAT(#13:1..19 745ad3d:7b:1:3102 -> 0:0:0:0:) ACKNACK(#8:15/0: 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102) ACKNACK(#9:16/0: 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3202)";
+
+        let entries: Vec<(&str, String)> = dds_log_regex
+            .parse_all(recv)
+            .iter()
+            .map(|ddsi_log_type| {
+                let capture = ddsi_log_type.get_capture();
+                let value = capture
+                    .name("seq")
+                    .or_else(|| capture.name("last_seq"))
+                    .or_else(|| capture.name("dst_rw_id"))
+                    .unwrap();
+                (ddsi_log_type.get_name(), String::from(value.as_str()))
+            })
+            .collect();
+
+        assert_eq!(
+            entries,
+            [
+                ("data", String::from("18")),
+                ("data", String::from("19")),
+                ("heartbeat", String::from("19")),
+                ("acknack", String::from("3102")),
+                ("acknack", String::from("3202")),
+            ]
+        );
+    }
+
     #[test]
     fn parse_match() {
         let dds_log_regex = DdsiLogRegex::new();
         let text_samples_match = [
             "2022-01-23T14:08:13+0000 1642946893.209853/      main: ownip: 172.17.0.3",
             "2021-12-07T22:19:48+0000 1638915588.796443/      main: handleParticipantsSelf: found 428f812:7b:1 (self)",
-            "2021-12-07T22:19:48+0000 1638915588.898675/      main: WRITER 428f812:7b:1:2302 QOS={topic=d_sampleChain,type=durabilityModule2::d_sampleChain_s,presentation=1:0:0,partition={durabilityPartition},durability=0,durability_service=0.000000000:{0:1}:{-1:-1:-1},deadline=2147483647.999999999,latency_budget=0.000000000,liveliness=0:0.000000000,reliability=1:1.000000000,destination_order=0,history=1:1,resource_limits=1:-1:-1,transport_priority=0,lifespan=2147483647.999999999,ownership=0,ownership_strength=0,writer_data_lifecycle={1,2147483647.999999999,2147483647.999999999},relaxed_qos_matching=0,synchronous_endpoint=0}",
-            "2021-12-07T22:22:48+0000 1638915768.903511/dq.builtin: SEDP ST0 7efc2093:7b:1:302 reliable transient writer: __BUILT-IN PARTITION__.DCPSParticipant/kernelModule::v_participantInfo p(open) NEW (as 239.255.0.1:7401 139.229.170.24:37673) QOS={topic=DCPSParticipant,type=kernelModule::v_participantInfo,presentation=1:0:0,partition={__BUILT-IN PARTITION__},durability=2,durability_service=0.000000000:{0:1}:{-1:-1:-1},deadline=2147483647.999999999,latency_budget=0.000000000,liveliness=0:0.000000000,reliability=1:0.000000000,destination_order=0,history=1:-1,resource_limits=-1:-1:-1,transport_priority=0,lifespan=2147483647.999999999,ownership=0,ownership_strength=0,writer_data_lifecycle={1,2147483647.999999999,2147483647.999999999},relaxed_qos_matching=0,synchronous_endpoint=0}",
-            "2022-01-23T14:11:29+0000 1642947089.895283/dq.builtin: SEDP ST0 745ad3d:7b:1:3b07 reliable volatile reader: nile.Test.cmd.Test_command_start_cc827fa3/Test::command_start_cc827fa3 p(open) NEW (as 239.255.0.1:7401 172.17.0.4:39948) QOS={topic=Test_command_start_cc827fa3,type=Test::command_start_cc827fa3,presentation=0:0:0,partition={nile.Test.cmd},durability=0,deadline=2147483647.999999999,latency_budget=0.000000000,liveliness=0:2147483647.999999999,reliability=1:0.100000000,destination_order=0,history=0:100,resource_limits=-1:-1:-1,transport_priority=0,ownership=0,time_based_filter=0.000000000,reader_data_lifecycle=2147483647.999999999:2147483647.999999999:0:1:1,relaxed_qos_matching=0,reader_lifespan={0,2147483647.999999999},subscription_keys={0,{}},share={0,},synchronous_endpoint=0}",
+            DURABILITY_WRITER_QOS,
+            BUILTIN_WRITER_SEDP_ST0,
+            READER_SEDP_ST0,
             "2022-01-23T14:11:29+0000 1642947089.987961/    (anon): READER 5bbed783:7b:1:3b07 QOS={topic=Test_logevent_summaryState_782ec3fd,type=Test::logevent_summaryState_782ec3fd,presentation=0:0:0,partition={nile.Test.data},durability=2,durability_service=0.000000000:{0:100}:{-1:-1:-1},deadline=2147483647.999999999,latency_budget=0.000000000,liveliness=0:2147483647.999999999,reliability=1:0.100000000,destination_order=0,history=0:100,resource_limits=-1:-1:-1,transport_priority=0,lifespan=2147483647.999999999,ownership=0,time_based_filter=0.000000000,reader_data_lifecycle=2147483647.999999999:2147483647.999999999:0:1:1,relaxed_qos_matching=0,reader_lifespan={0,2147483647.999999999},subscription_keys={0,{}},share={0,},synchronous_endpoint=0}",
             "2022-01-23T14:11:51+0000 1642947111.749832/dq.builtin: SEDP ST3 745ad3d:7b:1:4802delete_proxy_writer (745ad3d:7b:1:4802) - deleting",
             "2022-01-23T14:11:51+0000 1642947111.745674/dq.builtin: SEDP ST3 745ad3d:7b:1:3007delete_proxy_reader (745ad3d:7b:1:3007) - deleting",
@@ -561,7 +1119,7 @@ mod tests {
     fn parse_no_match() {
         let dds_log_regex = DdsiLogRegex::new();
         let text_sample_no_match =
-            "2022-01-20T13:24:36+0000 1642685076.168332/dq.builtin: thread_cputime 1260.618874505";
+            "2022-01-20T13:24:36+0000 1642685076.168332/      main: started as domain 0";
 
         dds_log_regex.parse(text_sample_no_match).unwrap();
     }
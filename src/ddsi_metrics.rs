@@ -0,0 +1,403 @@
+use crate::ddsi_analysis::LogAnalysis;
+use crate::ddsi_participant::Qos;
+use crate::ddsi_problems::{ProblemKind, PROBLEM_PATTERNS};
+use crate::ddsi_topology::{DdsiTopology, SortOrder};
+use log::warn;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Response, Server};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Metrics of a followed ddsi log, as exposed to Prometheus.
+#[derive(Debug, Default)]
+pub struct LogMetrics {
+    log: String,
+    n_lines: usize,
+    participants: usize,
+    /// Alive readers and writers, by topic.
+    readers: BTreeMap<String, usize>,
+    writers: BTreeMap<String, usize>,
+    /// Number of creations and deletions of readers and writers.
+    created: BTreeMap<&'static str, usize>,
+    deleted: BTreeMap<&'static str, usize>,
+    /// Alive writer/reader pairs with incompatible QoS, by topic.
+    incompatible: BTreeMap<String, usize>,
+    problems: BTreeMap<ProblemKind, usize>,
+}
+
+impl LogMetrics {
+    pub fn new(
+        log: &str,
+        n_lines: usize,
+        ddsi_topology: &DdsiTopology,
+        analysis: &LogAnalysis,
+    ) -> LogMetrics {
+        let mut metrics = LogMetrics {
+            log: String::from(log),
+            n_lines,
+            participants: ddsi_topology.len(),
+            created: BTreeMap::from([("reader", 0), ("writer", 0)]),
+            deleted: BTreeMap::from([("reader", 0), ("writer", 0)]),
+            problems: PROBLEM_PATTERNS
+                .iter()
+                .map(|(kind, _)| (*kind, 0))
+                .collect(),
+            ..Default::default()
+        };
+        let mut alive_writers: Vec<&Qos> = vec![];
+        let mut alive_readers: Vec<&Qos> = vec![];
+
+        for participant_id in ddsi_topology.get_participants_ids(SortOrder::Guid) {
+            let participant = ddsi_topology.get_participant(&participant_id).unwrap();
+            for id in participant.get_readers_id(SortOrder::Guid) {
+                let qos = participant.get_reader_qos(&id).unwrap();
+                metrics.add_endpoint("reader", qos);
+                if qos.is_alive() {
+                    alive_readers.push(qos);
+                }
+            }
+            for id in participant.get_writers_id(SortOrder::Guid) {
+                let qos = participant.get_writer_qos(&id).unwrap();
+                metrics.add_endpoint("writer", qos);
+                if qos.is_alive() {
+                    alive_writers.push(qos);
+                }
+            }
+        }
+
+        for reader in &alive_readers {
+            let n_incompatible = alive_writers
+                .iter()
+                .filter(|writer| {
+                    writer.get_topic() == reader.get_topic()
+                        && writer.partition_matches(reader)
+                        && !writer.incompatible_policies(reader).is_empty()
+                })
+                .count();
+            *metrics
+                .incompatible
+                .entry(reader.get_topic().clone())
+                .or_default() += n_incompatible;
+        }
+
+        for (kind, count) in analysis.problems.get_counts() {
+            metrics.problems.insert(kind, count);
+        }
+        metrics
+    }
+
+    fn add_endpoint(&mut self, kind: &'static str, qos: &Qos) {
+        let alive = match kind {
+            "reader" => &mut self.readers,
+            _ => &mut self.writers,
+        };
+        *alive.entry(qos.get_topic().clone()).or_default() += qos.is_alive() as usize;
+        *self.created.get_mut(kind).unwrap() += qos.get_created().len();
+        *self.deleted.get_mut(kind).unwrap() += qos.get_deleted().len();
+    }
+}
+
+/// Render the metrics of the followed logs in the OpenMetrics text format.
+pub fn render(metrics: &[LogMetrics]) -> String {
+    let mut text = String::new();
+
+    family(
+        &mut text,
+        "ddsi_lines",
+        "counter",
+        "Lines parsed from the log.",
+    );
+    for log in metrics {
+        sample(&mut text, "ddsi_lines_total", &log.log, &[], log.n_lines);
+    }
+
+    family(
+        &mut text,
+        "ddsi_participants",
+        "gauge",
+        "Participants known from the log.",
+    );
+    for log in metrics {
+        sample(
+            &mut text,
+            "ddsi_participants",
+            &log.log,
+            &[],
+            log.participants,
+        );
+    }
+
+    family(
+        &mut text,
+        "ddsi_readers",
+        "gauge",
+        "Alive readers, by topic.",
+    );
+    for log in metrics {
+        for (topic, count) in &log.readers {
+            sample(
+                &mut text,
+                "ddsi_readers",
+                &log.log,
+                &[("topic", topic)],
+                *count,
+            );
+        }
+    }
+
+    family(
+        &mut text,
+        "ddsi_writers",
+        "gauge",
+        "Alive writers, by topic.",
+    );
+    for log in metrics {
+        for (topic, count) in &log.writers {
+            sample(
+                &mut text,
+                "ddsi_writers",
+                &log.log,
+                &[("topic", topic)],
+                *count,
+            );
+        }
+    }
+
+    family(
+        &mut text,
+        "ddsi_endpoints_discovered",
+        "counter",
+        "Creations or discoveries of readers and writers.",
+    );
+    for log in metrics {
+        for (kind, count) in &log.created {
+            sample(
+                &mut text,
+                "ddsi_endpoints_discovered_total",
+                &log.log,
+                &[("kind", kind)],
+                *count,
+            );
+        }
+    }
+
+    family(
+        &mut text,
+        "ddsi_endpoints_deleted",
+        "counter",
+        "Deletions of readers and writers.",
+    );
+    for log in metrics {
+        for (kind, count) in &log.deleted {
+            sample(
+                &mut text,
+                "ddsi_endpoints_deleted_total",
+                &log.log,
+                &[("kind", kind)],
+                *count,
+            );
+        }
+    }
+
+    family(
+        &mut text,
+        "ddsi_incompatible_qos",
+        "gauge",
+        "Alive writer/reader pairs with incompatible QoS, by topic.",
+    );
+    for log in metrics {
+        for (topic, count) in &log.incompatible {
+            sample(
+                &mut text,
+                "ddsi_incompatible_qos",
+                &log.log,
+                &[("topic", topic)],
+                *count,
+            );
+        }
+    }
+
+    family(
+        &mut text,
+        "ddsi_problems",
+        "counter",
+        "Warnings and errors found in the log, by kind.",
+    );
+    for log in metrics {
+        for (kind, count) in &log.problems {
+            sample(
+                &mut text,
+                "ddsi_problems_total",
+                &log.log,
+                &[("kind", &kind.to_string())],
+                *count,
+            );
+        }
+    }
+
+    text.push_str("# EOF\n");
+    text
+}
+
+fn family(text: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(text, "# TYPE {} {}", name, kind).unwrap();
+    writeln!(text, "# HELP {} {}", name, help).unwrap();
+}
+
+fn sample(text: &mut String, name: &str, log: &str, labels: &[(&str, &str)], value: usize) {
+    let labels = [("log", log)]
+        .iter()
+        .chain(labels)
+        .map(|(label, value)| format!("{}=\"{}\"", label, escape_label(value)))
+        .collect::<Vec<_>>();
+    writeln!(text, "{}{{{}}} {}", name, labels.join(","), value).unwrap();
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// HTTP server exposing the metrics on `/metrics`.
+pub struct MetricsServer {
+    server: Server,
+}
+
+impl MetricsServer {
+    /// Listen on `address`, e.g. `0.0.0.0:9100`.
+    pub fn new(address: &str) -> Result<MetricsServer, Box<dyn Error>> {
+        Ok(MetricsServer {
+            server: Server::http(address).map_err(|error| format!("{}: {}", address, error))?,
+        })
+    }
+
+    pub fn get_address(&self) -> String {
+        self.server.server_addr().to_string()
+    }
+
+    /// Answer the requests received during `duration`, rendering the
+    /// metrics for each scrape.
+    pub fn serve<F>(&self, duration: Duration, metrics: F) -> Result<(), Box<dyn Error>>
+    where
+        F: Fn() -> String,
+    {
+        let deadline = Instant::now() + duration;
+
+        while let Some(request) = self
+            .server
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))?
+        {
+            let path = request.url().split('?').next().unwrap_or_default();
+            let response = if path == "/metrics" {
+                Response::from_string(metrics())
+                    .with_header(Header::from_bytes("Content-Type", CONTENT_TYPE).unwrap())
+            } else {
+                Response::from_string("Not found, metrics are served on /metrics.\n")
+                    .with_status_code(404)
+            };
+            if let Err(error) = request.respond(response) {
+                warn!("Failed to answer metrics request: {}", error);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_parser::{Parser, ParserOptions};
+    use crate::ddsi_test_data::{READER_SEDP_ST0, WRITER_QOS};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    fn log_metrics() -> LogMetrics {
+        // The reader is transient and the writer best effort, incompatible.
+        let log = [
+            READER_SEDP_ST0.replace("durability=0,", "durability=1,"),
+            WRITER_QOS.replace("reliability=1:", "reliability=0:"),
+            String::from(
+                "2022-01-23T14:11:31+0000 1642947091.000000/     lease: lease expired for participant 5bbed783:7b:1",
+            ),
+        ]
+        .join("\n");
+        let (ddsi_topology, analysis) = Parser::new(ParserOptions::default())
+            .parse(log.as_bytes(), None)
+            .unwrap();
+        LogMetrics::new("node\"1\".log", 3, &ddsi_topology, &analysis)
+    }
+
+    #[test]
+    fn render_metrics() {
+        let text = render(&[log_metrics()]);
+        let log = r#"log="node\"1\".log""#;
+
+        assert!(text.starts_with("# TYPE ddsi_lines counter\n"));
+        assert!(text.ends_with("# EOF\n"));
+        for line in [
+            format!("ddsi_lines_total{{{}}} 3", log),
+            format!("ddsi_participants{{{}}} 2", log),
+            format!(
+                "ddsi_readers{{{},topic=\"Test_command_start_cc827fa3\"}} 1",
+                log
+            ),
+            format!(
+                "ddsi_writers{{{},topic=\"Test_command_start_cc827fa3\"}} 1",
+                log
+            ),
+            format!(
+                "ddsi_endpoints_discovered_total{{{},kind=\"reader\"}} 1",
+                log
+            ),
+            format!("ddsi_endpoints_deleted_total{{{},kind=\"writer\"}} 0", log),
+            format!(
+                "ddsi_incompatible_qos{{{},topic=\"Test_command_start_cc827fa3\"}} 1",
+                log
+            ),
+            format!("ddsi_problems_total{{{},kind=\"lease expired\"}} 1", log),
+            format!("ddsi_problems_total{{{},kind=\"socket error\"}} 0", log),
+        ] {
+            assert!(text.lines().any(|sample| sample == line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn serve_metrics() {
+        let server = MetricsServer::new("127.0.0.1:0").unwrap();
+        let address = server.get_address();
+
+        let client = thread::spawn(move || {
+            let get = |path: &str| {
+                let mut stream = TcpStream::connect(&address).unwrap();
+                write!(
+                    stream,
+                    "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                    path
+                )
+                .unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response
+            };
+            (get("/metrics"), get("/"))
+        });
+        let metrics = render(&[log_metrics()]);
+        while !client.is_finished() {
+            server
+                .serve(Duration::from_millis(100), || metrics.clone())
+                .unwrap();
+        }
+
+        let (metrics_response, other_response) = client.join().unwrap();
+        assert!(metrics_response.starts_with("HTTP/1.1 200"));
+        assert!(metrics_response.contains(CONTENT_TYPE));
+        assert!(metrics_response.ends_with(&metrics));
+        assert!(other_response.starts_with("HTTP/1.1 404"));
+    }
+}
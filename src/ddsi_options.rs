@@ -0,0 +1,225 @@
+use crate::ddsi_export::ExportFormat;
+use crate::ddsi_topology::SortOrder;
+use crate::{DdsiInputConfig, DdsiLogConfig, DEFAULT_FLAP_CYCLES, DEFAULT_FLAP_WINDOW};
+use serde::Deserialize;
+use std::error::Error;
+use std::io::Read;
+use std::{fmt, str::FromStr};
+
+/// Export of the merged topology written along with a summary.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SummaryExport {
+    pub format: ExportFormat,
+    /// Name of the file to write the export to.
+    pub output: String,
+}
+
+#[derive(Debug)]
+pub struct InvalidSummaryExport(String);
+
+impl Error for InvalidSummaryExport {}
+
+impl fmt::Display for InvalidSummaryExport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid export {}, expected <format>=<file>.", self.0)
+    }
+}
+
+impl FromStr for SummaryExport {
+    type Err = InvalidSummaryExport;
+
+    /// Parse an export given as `<format>=<file>`, e.g. `dot=graph.dot`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((format, output)) if !output.is_empty() => Ok(SummaryExport {
+                format: format
+                    .parse()
+                    .map_err(|_| InvalidSummaryExport(String::from(s)))?,
+                output: String::from(output),
+            }),
+            _ => Err(InvalidSummaryExport(String::from(s))),
+        }
+    }
+}
+
+/// Input options, to process ddsi logs from code or a configuration file
+/// rather than from the command line.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputOptions {
+    pub filenames: Vec<String>,
+    pub sort: SortOrder,
+    pub filter: Option<String>,
+    pub since: Option<f64>,
+    pub until: Option<f64>,
+    pub strict: bool,
+    pub jobs: usize,
+    pub progress: bool,
+    pub quiet: bool,
+}
+
+impl Default for InputOptions {
+    fn default() -> Self {
+        InputOptions {
+            filenames: vec![],
+            sort: SortOrder::default(),
+            filter: None,
+            since: None,
+            until: None,
+            strict: false,
+            jobs: 1,
+            progress: true,
+            quiet: false,
+        }
+    }
+}
+
+impl DdsiInputConfig for InputOptions {
+    fn get_filenames(&self) -> &[String] {
+        &self.filenames
+    }
+
+    fn get_sort(&self) -> SortOrder {
+        self.sort
+    }
+
+    fn get_filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    fn get_since(&self) -> Option<f64> {
+        self.since
+    }
+
+    fn get_until(&self) -> Option<f64> {
+        self.until
+    }
+
+    fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    fn get_jobs(&self) -> usize {
+        self.jobs
+    }
+
+    fn get_progress(&self) -> bool {
+        self.progress
+    }
+
+    fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+}
+
+/// Options of the summary, to drive `run` from code or from a json
+/// configuration file. Missing fields take their default value:
+///
+/// ```json
+/// {
+///     "input": {"filenames": ["node1.log", "node2.log"], "jobs": 2,
+///               "filter": "topic =~ \"logevent_.*\"", "until": 1642947100.0},
+///     "output": "summary.txt",
+///     "json": null,
+///     "exports": [{"format": "dot", "output": "graph.dot"}]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SummaryOptions {
+    pub input: InputOptions,
+    pub output: String,
+    pub json: Option<String>,
+    pub exports: Vec<SummaryExport>,
+    /// Sqlite database to save the topology, problems and events to.
+    pub sqlite: Option<String>,
+    pub flap_window: f64,
+    pub flap_cycles: usize,
+}
+
+impl SummaryOptions {
+    pub fn from_json<R: Read>(reader: R) -> Result<SummaryOptions, Box<dyn Error>> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        SummaryOptions {
+            input: InputOptions::default(),
+            output: String::from("summary.txt"),
+            json: Some(String::from("ddsi_topology.json")),
+            exports: vec![],
+            sqlite: None,
+            flap_window: DEFAULT_FLAP_WINDOW,
+            flap_cycles: DEFAULT_FLAP_CYCLES,
+        }
+    }
+}
+
+impl DdsiLogConfig for SummaryOptions {
+    fn get_input(&self) -> &dyn DdsiInputConfig {
+        &self.input
+    }
+
+    fn get_output(&self) -> &str {
+        &self.output
+    }
+
+    fn get_json(&self) -> Option<&str> {
+        self.json.as_deref()
+    }
+
+    fn get_exports(&self) -> &[SummaryExport] {
+        &self.exports
+    }
+
+    fn get_sqlite(&self) -> Option<&str> {
+        self.sqlite.as_deref()
+    }
+
+    fn get_flap_window(&self) -> f64 {
+        self.flap_window
+    }
+
+    fn get_flap_cycles(&self) -> usize {
+        self.flap_cycles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_options_from_json() {
+        let options = SummaryOptions::from_json(
+            r#"{
+                "input": {"filenames": ["a.log", "b.log"], "sort": "topic", "jobs": 2},
+                "json": null,
+                "exports": [{"format": "chrome-trace", "output": "trace.json"}],
+                "flap_cycles": 5
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(options.get_input().get_filenames(), ["a.log", "b.log"]);
+        assert_eq!(options.get_input().get_sort(), SortOrder::Topic);
+        assert_eq!(options.get_input().get_jobs(), 2);
+        assert!(options.get_input().get_progress());
+        assert_eq!(options.get_output(), "summary.txt");
+        assert_eq!(options.get_json(), None);
+        assert_eq!(
+            options.get_exports(),
+            ["chrome-trace=trace.json".parse::<SummaryExport>().unwrap()]
+        );
+        assert_eq!(options.get_flap_window(), 60.0);
+        assert_eq!(options.get_flap_cycles(), 5);
+
+        assert!(SummaryOptions::from_json(r#"{"outptu": "summary.txt"}"#.as_bytes()).is_err());
+        assert!("dot".parse::<SummaryExport>().is_err());
+        assert!("svg=graph.svg".parse::<SummaryExport>().is_err());
+    }
+}
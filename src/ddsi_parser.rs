@@ -0,0 +1,293 @@
+use crate::ddsi_analysis::LogAnalysis;
+use crate::ddsi_log_regex::DdsiLogRegex;
+use crate::ddsi_topology::DdsiTopology;
+use log::warn;
+use regex::Regex;
+use std::error::Error;
+use std::io::{prelude::*, BufReader};
+
+/// Receives the progress of a parse.
+///
+/// All the methods do nothing by default, so implementations only need to
+/// override the ones they are interested in.
+pub trait Progress {
+    /// Called before the first line is parsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `total_bytes` - Size of the input, if known.
+    ///
+    fn start(&mut self, _total_bytes: Option<u64>) {}
+
+    /// Called after each parsed line.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Number of bytes parsed so far.
+    /// * `lines` - Number of lines parsed so far.
+    ///
+    fn update(&mut self, _bytes: u64, _lines: u64) {}
+
+    /// Called once all the lines were parsed.
+    fn finish(&mut self) {}
+}
+
+/// Timestamp at the start of a log line.
+const TIMESTAMP_REGEX: &str = r"^\S+ (?P<timestamp>[0-9]*\.[0-9]*)/";
+
+/// Progress receiver ignoring the progress.
+pub struct NoProgress;
+
+impl Progress for NoProgress {}
+
+/// Options of a parse.
+#[derive(Debug, Clone, Default)]
+pub struct ParserOptions {
+    /// Ignore lines logged before this time, if given, both for the
+    /// topology and the analyses. Endpoints created before it are unknown to
+    /// the topology.
+    pub since: Option<f64>,
+    /// Ignore lines logged after this time, if given.
+    pub until: Option<f64>,
+    /// Fail on entries inconsistent with the topology instead of warning
+    /// about them.
+    pub strict: bool,
+}
+
+/// Parser of ddsi logs into topologies, free of any I/O other than reading
+/// its input.
+///
+/// ```no_run
+/// use ddsi_log_parser::{Parser, ParserOptions};
+/// use std::fs::File;
+///
+/// let mut parser = Parser::new(ParserOptions::default());
+/// let ddsi_topology = parser.parse_reader(File::open("ddsi.log")?)?;
+/// println!("{} participants", ddsi_topology.len());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Parser {
+    options: ParserOptions,
+    ddsi_log_regex: DdsiLogRegex,
+    timestamp_regex: Regex,
+    progress: Box<dyn Progress>,
+}
+
+impl Parser {
+    pub fn new(options: ParserOptions) -> Parser {
+        Parser {
+            options,
+            ddsi_log_regex: DdsiLogRegex::new(),
+            timestamp_regex: Regex::new(TIMESTAMP_REGEX).unwrap(),
+            progress: Box::new(NoProgress),
+        }
+    }
+
+    /// Report the progress of the parses to `progress`.
+    pub fn with_progress(mut self, progress: Box<dyn Progress>) -> Parser {
+        self.progress = progress;
+        self
+    }
+
+    /// Parse a ddsi log into a topology.
+    ///
+    /// Unless the parse is strict, entries that cannot be applied to the
+    /// topology are logged as warnings and skipped.
+    pub fn parse_reader<R: Read>(&mut self, reader: R) -> Result<DdsiTopology, Box<dyn Error>> {
+        let (ddsi_topology, _) = self.parse(reader, None)?;
+        Ok(ddsi_topology)
+    }
+
+    /// Parse a ddsi log into a topology, along with the analysis of all its
+    /// lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The ddsi log.
+    /// * `total_bytes` - Size of the log, if known, reported to the progress.
+    ///
+    pub(crate) fn parse<R: Read>(
+        &mut self,
+        reader: R,
+        total_bytes: Option<u64>,
+    ) -> Result<(DdsiTopology, LogAnalysis), Box<dyn Error>> {
+        let mut ddsi_topology = DdsiTopology::new();
+        let mut analysis = LogAnalysis::new();
+        let (mut bytes, mut lines) = (0, 0);
+
+        self.progress.start(total_bytes);
+        for line in BufReader::new(reader).lines() {
+            let line_data = &line?;
+            self.parse_line(line_data, &mut ddsi_topology, &mut analysis)
+                .map_err(|error| format!("Line {}: {}", lines + 1, error))?;
+            bytes += line_data.len() as u64 + 1;
+            lines += 1;
+            self.progress.update(bytes, lines);
+        }
+        self.progress.finish();
+
+        Ok((ddsi_topology, analysis))
+    }
+
+    /// Update a topology and the analysis of its log with all the entries
+    /// of a log line.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The log line.
+    /// * `ddsi_topology` - The topology to update.
+    /// * `analysis` - The analysis to update.
+    ///
+    pub(crate) fn parse_line(
+        &self,
+        line: &str,
+        ddsi_topology: &mut DdsiTopology,
+        analysis: &mut LogAnalysis,
+    ) -> Result<(), Box<dyn Error>> {
+        // Lines outside of the time window are ignored altogether, lines
+        // without a timestamp can only count as unmatched.
+        if !self.is_in_window(line) {
+            return Ok(());
+        }
+
+        let mut matched = false;
+        for dds_log_type in self.ddsi_log_regex.parse_all(line) {
+            let name = dds_log_type.get_name();
+            let result = if dds_log_type.updates_topology() {
+                ddsi_topology.update(dds_log_type)
+            } else {
+                analysis.update_entry(&dds_log_type)
+            };
+            match result {
+                Err(error) if self.options.strict => return Err(error),
+                Err(error) => warn!("Skipped {} entry: {}", name, error),
+                Ok(()) => {}
+            }
+            matched = true;
+        }
+        analysis.update(line, matched);
+        Ok(())
+    }
+
+    /// Check if a log line was logged in the time window of the parse.
+    fn is_in_window(&self, line: &str) -> bool {
+        if self.options.since.is_none() && self.options.until.is_none() {
+            return true;
+        }
+        let timestamp = self
+            .timestamp_regex
+            .captures(line)
+            .and_then(|capture| capture["timestamp"].parse::<f64>().ok());
+        match timestamp {
+            Some(timestamp) => {
+                self.options.since.is_none_or(|since| timestamp >= since)
+                    && self.options.until.is_none_or(|until| timestamp <= until)
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_test_data::{BUILTIN_WRITER_SEDP_ST0, READER_SEDP_ST0};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Log with two entries, followed by a line that is not one.
+    fn log_sample() -> String {
+        [
+            READER_SEDP_ST0,
+            BUILTIN_WRITER_SEDP_ST0,
+            "2022-01-23T14:11:31+0000 1642947091.000000/      main: started as domain 0",
+        ]
+        .join("\n")
+    }
+
+    /// Progress receiver recording the calls it receives.
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl Progress for Recorder {
+        fn start(&mut self, total_bytes: Option<u64>) {
+            self.0.borrow_mut().push(format!("start {:?}", total_bytes));
+        }
+
+        fn update(&mut self, _bytes: u64, lines: u64) {
+            self.0.borrow_mut().push(format!("update {}", lines));
+        }
+
+        fn finish(&mut self) {
+            self.0.borrow_mut().push(String::from("finish"));
+        }
+    }
+
+    #[test]
+    fn parse_reader() {
+        let calls = Rc::new(RefCell::new(vec![]));
+        let mut parser = Parser::new(ParserOptions::default())
+            .with_progress(Box::new(Recorder(Rc::clone(&calls))));
+
+        let ddsi_topology = parser.parse_reader(log_sample().as_bytes()).unwrap();
+
+        assert_eq!(ddsi_topology.len(), 2);
+        assert_eq!(
+            *calls.borrow(),
+            ["start None", "update 1", "update 2", "update 3", "finish"]
+        );
+    }
+
+    #[test]
+    fn parse_until() {
+        let mut parser = Parser::new(ParserOptions {
+            until: Some(1640000000.0),
+            ..Default::default()
+        });
+        let (ddsi_topology, analysis) = parser.parse(log_sample().as_bytes(), Some(1)).unwrap();
+
+        assert_eq!(ddsi_topology.len(), 1);
+        assert!(ddsi_topology.get_participant("7efc2093:7b:1").is_some());
+        assert_eq!(analysis.statistics.get_n_matched(), 1);
+
+        let mut parser = Parser::new(ParserOptions {
+            since: Some(1640000000.0),
+            ..Default::default()
+        });
+        let ddsi_topology = parser.parse_reader(log_sample().as_bytes()).unwrap();
+
+        assert_eq!(ddsi_topology.len(), 1);
+        assert!(ddsi_topology.get_participant("745ad3d:7b:1").is_some());
+    }
+
+    #[test]
+    fn parse_until_ignores_later_problems() {
+        let log = "2022-01-23T14:11:29+0000 1642947089.000000/      main: started as domain 0\n\
+            2022-01-23T14:11:31+0000 1642947091.000000/     lease: lease expired for 745ad3d:7b:1\n\
+            no timestamp\n";
+
+        let mut parser = Parser::new(ParserOptions {
+            until: Some(1642947090.0),
+            ..Default::default()
+        });
+        let (_, analysis) = parser.parse(log.as_bytes(), None).unwrap();
+
+        assert!(analysis.problems.get_problems().is_empty());
+        assert_eq!(analysis.statistics.get_n_lines(), 2);
+        assert_eq!(analysis.statistics.get_n_matched(), 0);
+    }
+
+    #[test]
+    fn parse_strict() {
+        let log = "2022-01-20T13:24:36+0000 1642685076.168332/dq.builtin: thread_cputime .\n";
+
+        let mut parser = Parser::new(ParserOptions::default());
+        assert!(parser.parse_reader(log.as_bytes()).is_ok());
+
+        let mut parser = Parser::new(ParserOptions {
+            strict: true,
+            ..Default::default()
+        });
+        let error = parser.parse_reader(log.as_bytes()).unwrap_err();
+        assert!(error.to_string().starts_with("Line 1: "));
+    }
+}
@@ -1,8 +1,10 @@
-use crate::ddsi_log_regex::DdsiLogType;
+use crate::ddsi_log_regex::{DdsiLogType, QOS_POLICIES};
+use crate::ddsi_topology::SortOrder;
 use log::debug;
 use regex::Captures;
 use serde::ser::{Serialize, SerializeStruct, Serializer};
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::{error::Error, fmt};
 
 #[derive(Debug)]
@@ -23,20 +25,34 @@ impl fmt::Display for WrongSystemId {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct DdsiParticipant {
     system_id: String,
     hostname: String,
-    readers: HashMap<String, Qos>,
-    writers: HashMap<String, Qos>,
+    readers: BTreeMap<String, Qos>,
+    writers: BTreeMap<String, Qos>,
     is_master: bool,
     master_id: String,
+    #[serde(default)]
+    first_seen: Option<f64>,
+    #[serde(default)]
+    last_seen: Option<f64>,
+    #[serde(default)]
+    self_detected: Option<f64>,
+    #[serde(default)]
+    is_local: bool,
+    #[serde(default)]
+    config: BTreeMap<String, String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Qos {
     topic: String,
     partition: String,
+    #[serde(default)]
+    policies: BTreeMap<String, String>,
+    #[serde(default)]
+    locators: Vec<String>,
     created: Vec<f64>,
     deleted: Vec<f64>,
 }
@@ -46,10 +62,15 @@ impl DdsiParticipant {
         DdsiParticipant {
             system_id: String::from(system_id),
             hostname: String::from(own_ip),
-            readers: HashMap::new(),
-            writers: HashMap::new(),
+            readers: BTreeMap::new(),
+            writers: BTreeMap::new(),
             is_master: false,
             master_id: String::new(),
+            first_seen: None,
+            last_seen: None,
+            self_detected: None,
+            is_local: false,
+            config: BTreeMap::new(),
         }
     }
 
@@ -63,14 +84,50 @@ impl DdsiParticipant {
         &self.hostname
     }
 
-    /// Return a vector with all the readers id.
-    pub fn get_readers_id(&self) -> Vec<String> {
-        self.readers.keys().cloned().collect()
+    /// Return the timestamp of the first log entry seen for this participant,
+    /// if any.
+    pub fn get_first_seen(&self) -> Option<f64> {
+        self.first_seen
+    }
+
+    /// Return the timestamp of the last log entry seen for this participant,
+    /// if any.
+    pub fn get_last_seen(&self) -> Option<f64> {
+        self.last_seen
+    }
+
+    /// Return the timestamp at which the participant was detected as one of
+    /// the log's own participants, if it was.
+    pub fn get_self_detected(&self) -> Option<f64> {
+        self.self_detected
+    }
+
+    /// Check if the participant is local to the node that produced the log,
+    /// in which case its endpoints timestamps are creation times instead of
+    /// discovery times.
+    pub fn is_local(&self) -> bool {
+        self.is_local
     }
 
-    /// Return a vector with all the writers id.
-    pub fn get_writers_id(&self) -> Vec<String> {
-        self.writers.keys().cloned().collect()
+    /// Return the configuration logged by the node, for the log's self
+    /// participant.
+    pub fn get_config(&self) -> &BTreeMap<String, String> {
+        &self.config
+    }
+
+    /// Set a configuration entry logged by the node.
+    pub fn add_config(&mut self, key: String, value: String) {
+        self.config.insert(key, value);
+    }
+
+    /// Return a vector with all the readers id, in the requested order.
+    pub fn get_readers_id(&self, sort_order: SortOrder) -> Vec<String> {
+        sorted_ids(&self.readers, sort_order)
+    }
+
+    /// Return a vector with all the writers id, in the requested order.
+    pub fn get_writers_id(&self, sort_order: SortOrder) -> Vec<String> {
+        sorted_ids(&self.writers, sort_order)
     }
 
     /// Return reader QoS.
@@ -83,15 +140,83 @@ impl DdsiParticipant {
         self.writers.get(writer_id)
     }
 
+    /// Keep only the endpoints for which the predicate, called with the
+    /// participant, the endpoint id, whether it is a writer and its QoS,
+    /// returns true.
+    pub fn retain_endpoints<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&DdsiParticipant, &str, bool, &Qos) -> bool,
+    {
+        let removed_readers: Vec<String> = self
+            .readers
+            .iter()
+            .filter(|(id, qos)| !predicate(self, id, false, qos))
+            .map(|(id, _)| id.clone())
+            .collect();
+        let removed_writers: Vec<String> = self
+            .writers
+            .iter()
+            .filter(|(id, qos)| !predicate(self, id, true, qos))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in removed_readers {
+            self.readers.remove(&id);
+        }
+        for id in removed_writers {
+            self.writers.remove(&id);
+        }
+    }
+
+    /// Check if the participant has any reader or writer.
+    pub fn has_endpoints(&self) -> bool {
+        !self.readers.is_empty() || !self.writers.is_empty()
+    }
+
+    /// Merge the view another log has of the same participant.
+    ///
+    /// The view from the log local to the participant takes precedence: its
+    /// endpoints and fields are kept, and only the endpoints unknown to it
+    /// are added from the other view, so creation and discovery times of
+    /// different logs are not mixed.
+    pub fn merge(&mut self, other: DdsiParticipant) {
+        let first_seen = merge_timestamps(self.first_seen, other.first_seen, f64::min);
+        let last_seen = merge_timestamps(self.last_seen, other.last_seen, f64::max);
+
+        let other = if other.is_local && !self.is_local {
+            std::mem::replace(self, other)
+        } else {
+            other
+        };
+        for (id, qos) in other.readers {
+            self.readers.entry(id).or_insert(qos);
+        }
+        for (id, qos) in other.writers {
+            self.writers.entry(id).or_insert(qos);
+        }
+        if self.config.is_empty() {
+            self.config = other.config;
+        }
+        self.self_detected = self.self_detected.or(other.self_detected);
+        self.first_seen = first_seen;
+        self.last_seen = last_seen;
+    }
+
     /// Update information based on input ddsi log type.
     pub fn update(&mut self, ddsi_log_type: DdsiLogType) -> Result<(), Box<dyn Error>> {
+        let timestamp = ddsi_log_type.get_timestamp()?;
+        self.first_seen = Some(self.first_seen.map_or(timestamp, |t| t.min(timestamp)));
+        self.last_seen = Some(self.last_seen.map_or(timestamp, |t| t.max(timestamp)));
+
         match ddsi_log_type {
             DdsiLogType::WriterQos(capture) => {
                 debug!("WriterQos: {}", &capture["system_id"]);
+                self.is_local = true;
                 self.update_writer_qos(capture)
             }
             DdsiLogType::ReaderQos(capture) => {
                 debug!("ReaderQos: {}", &capture["system_id"]);
+                self.is_local = true;
                 self.update_reader_qos(capture)
             }
             DdsiLogType::WriterSedpSt0(capture) => {
@@ -110,15 +235,22 @@ impl DdsiParticipant {
                 debug!("ReaderSedpSt3: {}", &capture["system_id"]);
                 self.update_reader_sedp_st3(capture)
             }
-            DdsiLogType::OwnIp(_) => Ok(()),
+            DdsiLogType::OwnIp(_)
+            | DdsiLogType::Heartbeat(_)
+            | DdsiLogType::AckNack(_)
+            | DdsiLogType::NackFrag(_)
+            | DdsiLogType::Gap(_)
+            | DdsiLogType::WriteSample(_)
+            | DdsiLogType::Data(_)
+            | DdsiLogType::ThreadCputime(_)
+            | DdsiLogType::Config(_) => Ok(()),
             DdsiLogType::HandleParticipantsSelf(capture) => {
                 debug!("HandleParticipantsSelf: {}", &capture["system_id"]);
-                if let Err(check) = self.check_system_id(&capture["system_id"]) {
-                    return Err(check);
-                } else {
-                    self.system_id = String::from(&capture["system_id"]);
-                    Ok(())
-                }
+                self.check_system_id(&capture["system_id"])?;
+                self.system_id = String::from(&capture["system_id"]);
+                self.self_detected.get_or_insert(timestamp);
+                self.is_local = true;
+                Ok(())
             }
         }
     }
@@ -134,90 +266,351 @@ impl DdsiParticipant {
     }
 
     fn update_writer_qos(&mut self, capture: Captures) -> Result<(), Box<dyn Error>> {
-        if let Err(check) = self.check_system_id(&capture["system_id"]) {
-            return Err(check);
-        }
-
-        let rw_id = String::from(&capture["rw_id"]);
+        self.check_system_id(&capture["system_id"])?;
 
-        let qos = self.writers.entry(rw_id).or_insert(Qos {
-            topic: String::from(&capture["topic"]),
-            partition: String::from(&capture["partition"]),
-            created: vec![],
-            deleted: vec![],
-        });
-        qos.created.push(capture["timestamp"].parse().unwrap());
+        add_endpoint(&mut self.writers, &capture);
         Ok(())
     }
 
     fn update_reader_qos(&mut self, capture: Captures) -> Result<(), Box<dyn Error>> {
-        if let Err(check) = self.check_system_id(&capture["system_id"]) {
-            return Err(check);
-        }
-        let rw_id = String::from(&capture["rw_id"]);
-
-        let qos = self.readers.entry(rw_id).or_insert(Qos {
-            topic: String::from(&capture["topic"]),
-            partition: String::from(&capture["partition"]),
-            created: vec![],
-            deleted: vec![],
-        });
-        qos.created.push(capture["timestamp"].parse().unwrap());
+        self.check_system_id(&capture["system_id"])?;
+        add_endpoint(&mut self.readers, &capture);
         Ok(())
     }
 
     fn update_writer_sedp_st0(&mut self, capture: Captures) -> Result<(), Box<dyn Error>> {
-        if let Err(check) = self.check_system_id(&capture["system_id"]) {
-            return Err(check);
-        }
+        self.check_system_id(&capture["system_id"])?;
         self.hostname = String::from(&capture["hostname"]);
         self.update_writer_qos(capture)
     }
 
     fn update_reader_sedp_st0(&mut self, capture: Captures) -> Result<(), Box<dyn Error>> {
-        if let Err(check) = self.check_system_id(&capture["system_id"]) {
-            return Err(check);
-        }
+        self.check_system_id(&capture["system_id"])?;
         self.hostname = String::from(&capture["hostname"]);
         self.update_reader_qos(capture)
     }
 
     fn update_writer_sedp_st3(&mut self, capture: Captures) -> Result<(), Box<dyn Error>> {
-        if let Err(check) = self.check_system_id(&capture["system_id"]) {
-            return Err(check);
-        } else {
-            self.writers
-                .entry(String::from(&capture["rw_id"]))
-                .and_modify(|writer| writer.deleted.push(capture["timestamp"].parse().unwrap()));
-        }
+        self.check_system_id(&capture["system_id"])?;
+        self.writers
+            .entry(String::from(&capture["rw_id"]))
+            .and_modify(|writer| writer.deleted.push(capture["timestamp"].parse().unwrap()));
         Ok(())
     }
 
     fn update_reader_sedp_st3(&mut self, capture: Captures) -> Result<(), Box<dyn Error>> {
-        if let Err(check) = self.check_system_id(&capture["system_id"]) {
-            return Err(check);
-        } else {
-            self.readers
-                .entry(String::from(&capture["rw_id"]))
-                .and_modify(|writer| writer.deleted.push(capture["timestamp"].parse().unwrap()));
-        }
+        self.check_system_id(&capture["system_id"])?;
+        self.readers
+            .entry(String::from(&capture["rw_id"]))
+            .and_modify(|writer| writer.deleted.push(capture["timestamp"].parse().unwrap()));
         Ok(())
     }
 }
 
+impl Qos {
+    /// Create a new Qos from the capture of a QOS log entry.
+    ///
+    /// Locators are only available in SEDP entries, for local endpoints they
+    /// are left empty.
+    fn from_capture(capture: &Captures) -> Qos {
+        let policies = QOS_POLICIES
+            .iter()
+            .filter_map(|(policy, group)| {
+                capture
+                    .name(group)
+                    .filter(|value| !value.as_str().is_empty())
+                    .map(|value| (String::from(*policy), String::from(value.as_str())))
+            })
+            .collect();
+
+        let locators = match (capture.name("subnet"), capture.name("hostname")) {
+            (Some(_), Some(_)) => vec![
+                format!("{}:{}", &capture["subnet"], &capture["subnet_port"]),
+                format!("{}:{}", &capture["hostname"], &capture["hostname_port"]),
+            ],
+            _ => vec![],
+        };
+
+        Qos {
+            topic: String::from(&capture["topic"]),
+            partition: String::from(&capture["partition"]),
+            policies,
+            locators,
+            created: vec![],
+            deleted: vec![],
+        }
+    }
+
+    /// Return topic name.
+    pub fn get_topic(&self) -> &String {
+        &self.topic
+    }
+
+    /// Return partition.
+    pub fn get_partition(&self) -> &String {
+        &self.partition
+    }
+
+    /// Return the QoS policies, indexed by policy name.
+    pub fn get_policies(&self) -> &BTreeMap<String, String> {
+        &self.policies
+    }
+
+    /// Return the locators the endpoint was announced with.
+    pub fn get_locators(&self) -> &Vec<String> {
+        &self.locators
+    }
+
+    /// Return the timestamps at which the endpoint was created (or
+    /// discovered).
+    pub fn get_created(&self) -> &Vec<f64> {
+        &self.created
+    }
+
+    /// Return the timestamps at which the endpoint was deleted.
+    pub fn get_deleted(&self) -> &Vec<f64> {
+        &self.deleted
+    }
+
+    /// Return the lifetimes of the endpoint as pairs of creation and
+    /// deletion time. The deletion time is `None` for an endpoint that is
+    /// still alive at the end of the log.
+    pub fn get_lifetimes(&self) -> Vec<(f64, Option<f64>)> {
+        self.created
+            .iter()
+            .enumerate()
+            .map(|(index, created)| {
+                let next_created = self
+                    .created
+                    .get(index + 1)
+                    .copied()
+                    .unwrap_or(f64::INFINITY);
+                let deleted = self
+                    .deleted
+                    .iter()
+                    .find(|deleted| *deleted >= created && **deleted < next_created)
+                    .copied();
+                (*created, deleted)
+            })
+            .collect()
+    }
+
+    /// Check if the endpoint was not deleted after it was last created.
+    pub fn is_alive(&self) -> bool {
+        match (self.created.last(), self.deleted.last()) {
+            (_, None) => true,
+            (Some(created), Some(deleted)) => created > deleted,
+            (None, Some(_)) => false,
+        }
+    }
+
+    /// Return the timestamp of the first creation (or discovery) of the
+    /// endpoint.
+    pub fn get_first_seen(&self) -> f64 {
+        self.created
+            .first()
+            .or_else(|| self.deleted.first())
+            .copied()
+            .unwrap_or(f64::INFINITY)
+    }
+
+    /// Check if two endpoints have at least one partition in common.
+    /// Partition names may contain `*` and `?` wildcards.
+    pub fn partition_matches(&self, other: &Qos) -> bool {
+        self.partition.split(',').any(|partition| {
+            other.partition.split(',').any(|other_partition| {
+                glob_match(partition, other_partition) || glob_match(other_partition, partition)
+            })
+        })
+    }
+
+    /// Return the names of the policies for which the QoS offered by this
+    /// writer does not satisfy the QoS requested by `reader`.
+    ///
+    /// Policies missing from either QoS are not checked.
+    pub fn incompatible_policies(&self, reader: &Qos) -> Vec<&'static str> {
+        // Policy, field index in the policy value and whether the offered
+        // value must be greater or equal (true) or less or equal (false) than
+        // the requested one.
+        const RULES: [(&str, usize, bool); 10] = [
+            ("reliability", 0, true),
+            ("durability", 0, true),
+            ("presentation", 0, true),
+            ("presentation", 1, true),
+            ("presentation", 2, true),
+            ("deadline", 0, false),
+            ("latency_budget", 0, false),
+            ("liveliness", 0, true),
+            ("liveliness", 1, false),
+            ("destination_order", 0, true),
+        ];
+
+        let mut incompatible = vec![];
+
+        for (policy, index, offered_greater) in RULES {
+            if let (Some(offered), Some(requested)) = (
+                self.get_policy_field(policy, index),
+                reader.get_policy_field(policy, index),
+            ) {
+                let compatible = if offered_greater {
+                    offered >= requested
+                } else {
+                    offered <= requested
+                };
+                if !compatible && !incompatible.contains(&policy) {
+                    incompatible.push(policy);
+                }
+            }
+        }
+
+        if let (Some(offered), Some(requested)) = (
+            self.get_policy_field("ownership", 0),
+            reader.get_policy_field("ownership", 0),
+        ) {
+            if offered != requested {
+                incompatible.push("ownership");
+            }
+        }
+
+        incompatible
+    }
+
+    /// Return a numeric field of a policy value, e.g. the kind of the
+    /// reliability policy "1:0.100000000" is field 0.
+    fn get_policy_field(&self, policy: &str, index: usize) -> Option<f64> {
+        self.policies
+            .get(policy)?
+            .split(':')
+            .nth(index)?
+            .parse()
+            .ok()
+    }
+}
+
+/// Return the alive writer/reader pairs on the same topic, with at least one
+/// partition in common, whose QoS are incompatible, along with the
+/// incompatible policies.
+///
+/// Endpoints are given as (key, qos) and writers are grouped by topic, so
+/// that each reader is only compared to the writers of its topic.
+pub fn find_incompatible_pairs<'a, W: Copy, R: Copy>(
+    writers: impl IntoIterator<Item = (W, &'a Qos)>,
+    readers: impl IntoIterator<Item = (R, &'a Qos)>,
+) -> Vec<(W, R, Vec<&'static str>)> {
+    let mut topics_writers: BTreeMap<&String, Vec<(W, &Qos)>> = BTreeMap::new();
+    let mut pairs = vec![];
+
+    for (writer, qos) in writers.into_iter().filter(|(_, qos)| qos.is_alive()) {
+        topics_writers
+            .entry(qos.get_topic())
+            .or_default()
+            .push((writer, qos));
+    }
+
+    for (reader, reader_qos) in readers.into_iter().filter(|(_, qos)| qos.is_alive()) {
+        let topic_writers = match topics_writers.get(reader_qos.get_topic()) {
+            Some(topic_writers) => topic_writers,
+            None => continue,
+        };
+        for (writer, writer_qos) in topic_writers {
+            if !writer_qos.partition_matches(reader_qos) {
+                continue;
+            }
+            let incompatible = writer_qos.incompatible_policies(reader_qos);
+            if !incompatible.is_empty() {
+                pairs.push((*writer, reader, incompatible));
+            }
+        }
+    }
+    pairs
+}
+
+/// Record the creation (or discovery) of an endpoint from the capture of a
+/// QOS log entry.
+///
+/// An endpoint created again takes the QoS of its latest creation, keeping
+/// its history and, for entries without any, its locators.
+fn add_endpoint(endpoints: &mut BTreeMap<String, Qos>, capture: &Captures) {
+    let rw_id = String::from(&capture["rw_id"]);
+    let mut qos = Qos::from_capture(capture);
+
+    if let Some(previous) = endpoints.remove(&rw_id) {
+        qos.created = previous.created;
+        qos.deleted = previous.deleted;
+        if qos.locators.is_empty() {
+            qos.locators = previous.locators;
+        }
+    }
+    qos.created.push(capture["timestamp"].parse().unwrap());
+    endpoints.insert(rw_id, qos);
+}
+
+/// Combine the optional timestamps of two views of a participant.
+fn merge_timestamps(a: Option<f64>, b: Option<f64>, combine: fn(f64, f64) -> f64) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(combine(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Match a text against a pattern with `*` and `?` wildcards.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.chars().next() {
+        None => text.is_empty(),
+        Some('*') => {
+            glob_match(&pattern[1..], text)
+                || text
+                    .chars()
+                    .next()
+                    .is_some_and(|c| glob_match(pattern, &text[c.len_utf8()..]))
+        }
+        Some(p) => text.chars().next().is_some_and(|c| {
+            (p == '?' || p == c) && glob_match(&pattern[p.len_utf8()..], &text[c.len_utf8()..])
+        }),
+    }
+}
+
+/// Return the ids of a collection of endpoints sorted by the requested order.
+///
+/// Ties are always broken by the endpoint id, so the output is deterministic.
+fn sorted_ids(endpoints: &BTreeMap<String, Qos>, sort_order: SortOrder) -> Vec<String> {
+    let mut ids: Vec<&String> = endpoints.keys().collect();
+
+    match sort_order {
+        SortOrder::FirstSeen => ids.sort_by(|a, b| {
+            endpoints[*a]
+                .get_first_seen()
+                .total_cmp(&endpoints[*b].get_first_seen())
+                .then_with(|| a.cmp(b))
+        }),
+        SortOrder::Topic => ids.sort_by(|a, b| {
+            let (qos_a, qos_b) = (&endpoints[*a], &endpoints[*b]);
+            (&qos_a.topic, &qos_a.partition, a).cmp(&(&qos_b.topic, &qos_b.partition, b))
+        }),
+        SortOrder::Host | SortOrder::Guid => {}
+    }
+
+    ids.into_iter().cloned().collect()
+}
+
 impl Serialize for DdsiParticipant {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        // 3 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("DdsiParticipant", 6)?;
-        state.serialize_field("system_id", &self.system_id);
-        state.serialize_field("hostname", &self.hostname);
-        state.serialize_field("readers", &self.readers);
-        state.serialize_field("writers", &self.writers);
-        state.serialize_field("is_master", &self.is_master);
-        state.serialize_field("master_id", &self.master_id);
+        // 11 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("DdsiParticipant", 11)?;
+        state.serialize_field("system_id", &self.system_id)?;
+        state.serialize_field("hostname", &self.hostname)?;
+        state.serialize_field("readers", &self.readers)?;
+        state.serialize_field("writers", &self.writers)?;
+        state.serialize_field("is_master", &self.is_master)?;
+        state.serialize_field("master_id", &self.master_id)?;
+        state.serialize_field("first_seen", &self.first_seen)?;
+        state.serialize_field("last_seen", &self.last_seen)?;
+        state.serialize_field("self_detected", &self.self_detected)?;
+        state.serialize_field("is_local", &self.is_local)?;
+        state.serialize_field("config", &self.config)?;
         state.end()
     }
 }
@@ -227,12 +620,14 @@ impl Serialize for Qos {
     where
         S: Serializer,
     {
-        // 3 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("DdsiParticipant", 6)?;
-        state.serialize_field("topic", &self.topic);
-        state.serialize_field("partition", &self.partition);
-        state.serialize_field("created", &self.created);
-        state.serialize_field("deleted", &self.deleted);
+        // 6 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("Qos", 6)?;
+        state.serialize_field("topic", &self.topic)?;
+        state.serialize_field("partition", &self.partition)?;
+        state.serialize_field("policies", &self.policies)?;
+        state.serialize_field("locators", &self.locators)?;
+        state.serialize_field("created", &self.created)?;
+        state.serialize_field("deleted", &self.deleted)?;
         state.end()
     }
 }
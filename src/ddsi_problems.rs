@@ -0,0 +1,303 @@
+use crate::ddsi_topology::DdsiTopology;
+use regex::{Regex, RegexSet};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Number of problems of each kind listed in the summary.
+const N_LISTED: usize = 20;
+
+pub(crate) const PROBLEM_PATTERNS: [(ProblemKind, &str); 6] = [
+    (ProblemKind::MalformedPacket, r"(?i)\bmalformed\b"),
+    (
+        ProblemKind::SendFailure,
+        r"(?i)\bnn_xpack_send\b.*\b(fail|failed|error)\b",
+    ),
+    (ProblemKind::WriteFailure, r"\bwrite_sample\b.*\bFAILED\b"),
+    (ProblemKind::LeaseExpired, r"(?i)\blease expired\b"),
+    (
+        ProblemKind::SocketError,
+        r"\b(make_socket|bind_socket|set_rcvbuf|set_sndbuf|(join|leave)_mcgroups?|ddsi_(udp|tcp)_\w+|os_sock\w+)\b.*\bfailed\b|\bfailed to (create|bind|increase) socket\b",
+    ),
+    (
+        ProblemKind::ResourceLimit,
+        r"(?i)\b(resource limits?|out of memory|max_samples|max_instances|(buffer|queue) full)\b",
+    ),
+];
+
+const HEADER_REGEX: &str = r"^\S+ (?P<timestamp>[0-9]*\.[0-9]*)/\s*(?P<thread>[^:]*): ";
+const GUID_REGEX: &str =
+    r"\b(?P<participant>[0-9a-f]+:[0-9a-f]+:[0-9a-f]+)(:(?P<endpoint>[0-9a-f]+))?\b";
+
+/// Kind of problem reported in ddsi logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProblemKind {
+    MalformedPacket,
+    SendFailure,
+    WriteFailure,
+    LeaseExpired,
+    SocketError,
+    ResourceLimit,
+}
+
+impl fmt::Display for ProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ProblemKind::MalformedPacket => "malformed packet",
+            ProblemKind::SendFailure => "send failure",
+            ProblemKind::WriteFailure => "write failure",
+            ProblemKind::LeaseExpired => "lease expired",
+            ProblemKind::SocketError => "socket error",
+            ProblemKind::ResourceLimit => "resource limit",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A warning or error found in a ddsi log.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    pub timestamp: Option<f64>,
+    pub thread: Option<String>,
+    pub participant_id: Option<String>,
+    pub endpoint_id: Option<String>,
+    pub message: String,
+    /// Log the problem was found in, once named with `Problems::set_log`.
+    pub log: Option<String>,
+}
+
+/// Warnings and errors found in ddsi logs.
+#[derive(Debug)]
+pub struct Problems {
+    patterns: RegexSet,
+    header_regex: Regex,
+    guid_regex: Regex,
+    problems: Vec<Problem>,
+}
+
+impl Problems {
+    pub fn new() -> Problems {
+        Problems {
+            patterns: RegexSet::new(PROBLEM_PATTERNS.iter().map(|(_, pattern)| pattern)).unwrap(),
+            header_regex: Regex::new(HEADER_REGEX).unwrap(),
+            guid_regex: Regex::new(GUID_REGEX).unwrap(),
+            problems: vec![],
+        }
+    }
+
+    /// Check a log line for problems, returning the kind of the problem
+    /// found, if any.
+    ///
+    /// Only the first matching kind is recorded, so a line is never counted
+    /// twice.
+    pub fn update(&mut self, line: &str) -> Option<ProblemKind> {
+        let (kind, _) = PROBLEM_PATTERNS[self.patterns.matches(line).iter().next()?];
+
+        let (timestamp, thread, message) = match self.header_regex.captures(line) {
+            Some(capture) => {
+                let header = capture.get(0).unwrap();
+                (
+                    capture["timestamp"].parse::<f64>().ok(),
+                    Some(String::from(&capture["thread"])),
+                    &line[header.end()..],
+                )
+            }
+            None => (None, None, line),
+        };
+
+        let guid = self.guid_regex.captures(message);
+
+        self.problems.push(Problem {
+            kind,
+            timestamp,
+            thread,
+            participant_id: guid.as_ref().map(|guid| String::from(&guid["participant"])),
+            endpoint_id: guid
+                .as_ref()
+                .and_then(|guid| guid.name("endpoint"))
+                .map(|endpoint| String::from(endpoint.as_str())),
+            message: String::from(message),
+            log: None,
+        });
+        Some(kind)
+    }
+
+    /// Name the log the problems found so far were found in.
+    pub fn set_log(&mut self, log: &str) {
+        for problem in self
+            .problems
+            .iter_mut()
+            .filter(|problem| problem.log.is_none())
+        {
+            problem.log = Some(String::from(log));
+        }
+    }
+
+    /// Add the problems found in another log.
+    pub fn merge(&mut self, other: Problems) {
+        self.problems.extend(other.problems);
+    }
+
+    /// Return all the problems found.
+    pub fn get_problems(&self) -> &Vec<Problem> {
+        &self.problems
+    }
+
+    /// Return the number of problems of each kind.
+    pub fn get_counts(&self) -> BTreeMap<ProblemKind, usize> {
+        let mut counts = BTreeMap::new();
+        for problem in &self.problems {
+            *counts.entry(problem.kind).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Summarize the problems, attributing them to the participants of the
+    /// topology when known.
+    pub fn summarize(&self, ddsi_topology: &DdsiTopology) -> String {
+        let mut summary = format!("\t- Problems {}:\n", self.problems.len());
+
+        for (kind, count) in self.get_counts() {
+            summary.push_str(&format!("\t\t- {}: {}\n", kind, count));
+
+            let mut problems: Vec<&Problem> = self
+                .get_problems()
+                .iter()
+                .filter(|problem| problem.kind == kind)
+                .collect();
+            problems.sort_by(|a, b| {
+                a.timestamp
+                    .unwrap_or(f64::INFINITY)
+                    .total_cmp(&b.timestamp.unwrap_or(f64::INFINITY))
+            });
+
+            for problem in problems.iter().take(N_LISTED) {
+                let timestamp = match problem.timestamp {
+                    Some(timestamp) => format!("{:.6}", timestamp),
+                    None => String::from("-"),
+                };
+                let origin = match &problem.participant_id {
+                    Some(participant_id) => {
+                        let guid = match &problem.endpoint_id {
+                            Some(endpoint_id) => format!("{}:{}", participant_id, endpoint_id),
+                            None => participant_id.clone(),
+                        };
+                        match ddsi_topology.get_participant(participant_id) {
+                            Some(participant) => {
+                                format!(" [{}@{}]", guid, participant.get_hostname())
+                            }
+                            None => format!(" [{}]", guid),
+                        }
+                    }
+                    None => String::new(),
+                };
+                summary.push_str(&format!(
+                    "\t\t\t- {}{}: {}\n",
+                    timestamp, origin, problem.message
+                ));
+            }
+            if problems.len() > N_LISTED {
+                summary.push_str(&format!(
+                    "\t\t\t- ... and {} more\n",
+                    problems.len() - N_LISTED
+                ));
+            }
+        }
+        summary
+    }
+}
+
+impl Default for Problems {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_problems() {
+        let mut problems = Problems::new();
+
+        assert_eq!(
+            problems.update("2022-01-23T14:11:29+0000 1642947089.895283/      xmit: write_sample 745ad3d:7b:1:3b02 #12: ST0 FAILED"),
+            Some(ProblemKind::WriteFailure)
+        );
+        assert_eq!(
+            problems.update("2022-01-23T14:11:30+0000 1642947090.000000/      recv: malformed packet received from vendor 1.2 state parse:shortmsg"),
+            Some(ProblemKind::MalformedPacket)
+        );
+        assert_eq!(
+            problems.update("2022-01-23T14:11:31+0000 1642947091.000000/     lease: lease expired for participant 5bbed783:7b:1"),
+            Some(ProblemKind::LeaseExpired)
+        );
+        assert_eq!(
+            problems.update("2022-01-23T14:11:32+0000 1642947092.000000/      xmit: nn_xpack_send failed: sendmsg errno 101"),
+            Some(ProblemKind::SendFailure)
+        );
+        assert_eq!(
+            problems
+                .update("2022-01-23T14:11:29+0000 1642947089.895283/      main: ownip: 172.17.0.3"),
+            None
+        );
+
+        let write_failure = &problems.get_problems()[0];
+        assert_eq!(write_failure.timestamp, Some(1642947089.895283));
+        assert_eq!(write_failure.thread.as_deref(), Some("xmit"));
+        assert_eq!(
+            write_failure.participant_id.as_deref(),
+            Some("745ad3d:7b:1")
+        );
+        assert_eq!(write_failure.endpoint_id.as_deref(), Some("3b02"));
+
+        let lease_expired = &problems.get_problems()[2];
+        assert_eq!(
+            lease_expired.participant_id.as_deref(),
+            Some("5bbed783:7b:1")
+        );
+        assert_eq!(lease_expired.endpoint_id, None);
+
+        let counts = problems.get_counts();
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts[&ProblemKind::WriteFailure], 1);
+    }
+
+    #[test]
+    fn socket_errors() {
+        let mut problems = Problems::new();
+
+        for line in [
+            "2022-01-23T14:11:28+0000 1642947088.000000/      main: make_socket: failed to create socket: Too many open files",
+            "2022-01-23T14:11:28+0000 1642947088.000000/      main: failed to increase socket receive buffer size to 1048576 bytes, continuing with 212992 bytes",
+            "2022-01-23T14:11:29+0000 1642947089.000000/      xmit: ddsi_udp_conn_write failed with retcode -1",
+            "2022-01-23T14:11:30+0000 1642947090.000000/      main: join_mcgroups: failed to join 239.255.0.1",
+        ] {
+            assert_eq!(problems.update(line), Some(ProblemKind::SocketError), "{}", line);
+        }
+
+        // Socket related words in other messages are not socket errors.
+        for line in [
+            "2022-01-23T14:11:31+0000 1642947091.000000/dq.builtin: connect of reader 745ad3d:7b:1:3b07 failed: no matching writer",
+            "2022-01-23T14:11:31+0000 1642947091.000000/      main: bind error on topic socket_status",
+        ] {
+            assert_eq!(problems.update(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn summarize_problems() {
+        let mut problems = Problems::new();
+        problems.update("2022-01-23T14:11:31+0000 1642947091.000000/     lease: lease expired for participant 5bbed783:7b:1");
+
+        let summary = problems.summarize(&DdsiTopology::new());
+
+        assert_eq!(
+            summary,
+            "\t- Problems 1:\n\
+            \t\t- lease expired: 1\n\
+            \t\t\t- 1642947091.000000 [5bbed783:7b:1]: lease expired for participant 5bbed783:7b:1\n"
+        );
+    }
+}
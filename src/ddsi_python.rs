@@ -0,0 +1,352 @@
+//! Python extension module, built with the `python` feature:
+//!
+//! ```python
+//! import pandas as pd
+//! import ddsi_log_parser
+//!
+//! topology = ddsi_log_parser.parse("ddsi.log", until=1642947100.0)
+//! endpoints = pd.DataFrame(topology.endpoints())
+//! events = pd.DataFrame(e.to_record() for e in ddsi_log_parser.events("ddsi.log"))
+//! ```
+use crate::ddsi_event::{DdsiEvent, EventReader};
+use crate::ddsi_parser::{Parser, ParserOptions};
+use crate::ddsi_topology::{DdsiTopology, SortOrder};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader};
+
+/// Convert an error of the parser to a python exception.
+fn to_py_err(error: Box<dyn Error>) -> PyErr {
+    match error.downcast::<io::Error>() {
+        Ok(error) => PyIOError::new_err(error.to_string()),
+        Err(error) => PyValueError::new_err(error.to_string()),
+    }
+}
+
+/// Topology of the participants and endpoints found in ddsi logs.
+#[pyclass(name = "Topology", module = "ddsi_log_parser", frozen)]
+struct PyTopology {
+    ddsi_topology: DdsiTopology,
+}
+
+#[pymethods]
+impl PyTopology {
+    /// Load a topology saved as json.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<PyTopology> {
+        let ddsi_topology = DdsiTopology::from_json(json.as_bytes()).map_err(to_py_err)?;
+        Ok(PyTopology { ddsi_topology })
+    }
+
+    /// Save the topology as json.
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.ddsi_topology)
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    /// Text summary of the topology, with participants and endpoints in the
+    /// given order: first-seen, host, guid or topic.
+    #[pyo3(signature = (sort = "first-seen"))]
+    fn summary(&self, sort: &str) -> PyResult<String> {
+        let sort_order: SortOrder =
+            sort.parse()
+                .map_err(|error: crate::ddsi_topology::UnknownSortOrder| {
+                    PyValueError::new_err(error.to_string())
+                })?;
+        Ok(self.ddsi_topology.summarize(sort_order))
+    }
+
+    /// One record per participant, e.g. for `pandas.DataFrame`.
+    fn participants<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let mut records = vec![];
+        for participant_id in self.ddsi_topology.get_participants_ids(SortOrder::Guid) {
+            let participant = self.ddsi_topology.get_participant(&participant_id).unwrap();
+            let record = PyDict::new(py);
+            record.set_item("participant", &participant_id)?;
+            record.set_item("host", participant.get_hostname())?;
+            record.set_item("local", participant.is_local())?;
+            record.set_item("first_seen", participant.get_first_seen())?;
+            record.set_item("last_seen", participant.get_last_seen())?;
+            record.set_item("self_detected", participant.get_self_detected())?;
+            record.set_item("writers", participant.get_writers_id(SortOrder::Guid).len())?;
+            record.set_item("readers", participant.get_readers_id(SortOrder::Guid).len())?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    /// One record per reader or writer, with a column per QoS policy, e.g.
+    /// for `pandas.DataFrame`.
+    fn endpoints<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let mut records = vec![];
+        for participant_id in self.ddsi_topology.get_participants_ids(SortOrder::Guid) {
+            let participant = self.ddsi_topology.get_participant(&participant_id).unwrap();
+
+            for (kind, ids) in [
+                ("writer", participant.get_writers_id(SortOrder::Guid)),
+                ("reader", participant.get_readers_id(SortOrder::Guid)),
+            ] {
+                for id in ids {
+                    let qos = match kind {
+                        "writer" => participant.get_writer_qos(&id),
+                        _ => participant.get_reader_qos(&id),
+                    }
+                    .unwrap();
+                    let record = PyDict::new(py);
+                    record.set_item("guid", format!("{}:{}", participant_id, id))?;
+                    record.set_item("participant", &participant_id)?;
+                    record.set_item("id", &id)?;
+                    record.set_item("kind", kind)?;
+                    record.set_item("topic", qos.get_topic())?;
+                    record.set_item("partition", qos.get_partition())?;
+                    record.set_item("host", participant.get_hostname())?;
+                    record.set_item("alive", qos.is_alive())?;
+                    record.set_item("first_seen", qos.get_first_seen())?;
+                    record.set_item("locators", qos.get_locators().join(" "))?;
+                    for (policy, value) in qos.get_policies() {
+                        record.set_item(policy, value)?;
+                    }
+                    records.push(record);
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    fn __len__(&self) -> usize {
+        self.ddsi_topology.len()
+    }
+
+    fn __repr__(&self) -> String {
+        let counts = self.ddsi_topology.get_counts();
+        format!(
+            "Topology({} participants, {} writers, {} readers)",
+            counts.participants, counts.writers, counts.readers
+        )
+    }
+}
+
+/// A recognized ddsi log entry.
+#[pyclass(name = "Event", module = "ddsi_log_parser", frozen, get_all)]
+struct PyEvent {
+    /// Number of the line of the entry in its log, starting at 1.
+    line_number: u64,
+    /// Kind of the entry, e.g. `reader_sedp_st0` or `heartbeat`.
+    kind: String,
+    /// Time of the entry, in seconds since epoch.
+    timestamp: f64,
+    /// Id of the participant the entry is about.
+    system_id: String,
+    /// Values of the other fields of the entry, by name.
+    fields: BTreeMap<String, String>,
+}
+
+impl From<DdsiEvent> for PyEvent {
+    fn from(event: DdsiEvent) -> Self {
+        PyEvent {
+            line_number: event.line_number,
+            kind: String::from(event.kind),
+            timestamp: event.timestamp,
+            system_id: event.system_id,
+            fields: event.fields,
+        }
+    }
+}
+
+#[pymethods]
+impl PyEvent {
+    /// Flat record of the event, with a column per field, e.g. for
+    /// `pandas.DataFrame`.
+    fn to_record<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let record = PyDict::new(py);
+        record.set_item("line_number", self.line_number)?;
+        record.set_item("kind", &self.kind)?;
+        record.set_item("timestamp", self.timestamp)?;
+        record.set_item("system_id", &self.system_id)?;
+        for (name, value) in &self.fields {
+            record.set_item(name, value)?;
+        }
+        Ok(record)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Event({}, line {}, {} at {:.6})",
+            self.kind, self.line_number, self.system_id, self.timestamp
+        )
+    }
+}
+
+/// Iterator over the events of a ddsi log.
+#[pyclass(name = "EventIterator", module = "ddsi_log_parser")]
+struct PyEventIterator {
+    reader: EventReader<BufReader<File>>,
+}
+
+#[pymethods]
+impl PyEventIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> PyResult<Option<PyEvent>> {
+        match self.reader.next() {
+            Some(Ok(event)) => Ok(Some(event.into())),
+            Some(Err(error)) => Err(to_py_err(error)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Parse a ddsi log file into a topology.
+///
+/// Entries logged outside of `since`..`until`, in seconds since epoch, are
+/// ignored. A strict parse fails on entries inconsistent with the topology.
+#[pyfunction]
+#[pyo3(signature = (path, since = None, until = None, strict = false))]
+fn parse(
+    py: Python<'_>,
+    path: &str,
+    since: Option<f64>,
+    until: Option<f64>,
+    strict: bool,
+) -> PyResult<PyTopology> {
+    let options = ParserOptions {
+        since,
+        until,
+        strict,
+    };
+    let ddsi_topology = py.detach(|| -> PyResult<DdsiTopology> {
+        let file =
+            File::open(path).map_err(|error| PyIOError::new_err(format!("{}: {}", path, error)))?;
+        Parser::new(options).parse_reader(file).map_err(to_py_err)
+    })?;
+    Ok(PyTopology { ddsi_topology })
+}
+
+/// Parse the text of a ddsi log into a topology.
+#[pyfunction]
+#[pyo3(signature = (text, since = None, until = None, strict = false))]
+fn parse_text(
+    text: &str,
+    since: Option<f64>,
+    until: Option<f64>,
+    strict: bool,
+) -> PyResult<PyTopology> {
+    let options = ParserOptions {
+        since,
+        until,
+        strict,
+    };
+    let ddsi_topology = Parser::new(options)
+        .parse_reader(text.as_bytes())
+        .map_err(to_py_err)?;
+    Ok(PyTopology { ddsi_topology })
+}
+
+/// Iterate over the recognized entries of a ddsi log file.
+#[pyfunction]
+fn events(path: &str) -> PyResult<PyEventIterator> {
+    let file = File::open(path)?;
+    Ok(PyEventIterator {
+        reader: EventReader::new(BufReader::new(file)),
+    })
+}
+
+/// Parse ddsi2 logs into topologies and events.
+#[pymodule]
+#[pyo3(name = "ddsi_log_parser")]
+fn ddsi_log_parser_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyTopology>()?;
+    module.add_class::<PyEvent>()?;
+    module.add_class::<PyEventIterator>()?;
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_text, module)?)?;
+    module.add_function(wrap_pyfunction!(events, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_test_data::{TempFile, READER_SEDP_ST0};
+
+    #[test]
+    fn topology_records() {
+        Python::initialize();
+        Python::attach(|py| {
+            let topology = parse_text(READER_SEDP_ST0, None, None, false).unwrap();
+            assert_eq!(topology.__len__(), 1);
+
+            let endpoints = topology.endpoints(py).unwrap();
+            assert_eq!(endpoints.len(), 1);
+            let endpoint = &endpoints[0];
+            let item = |key: &str| endpoint.get_item(key).unwrap().unwrap();
+            assert_eq!(
+                item("guid").extract::<String>().unwrap(),
+                "745ad3d:7b:1:3b07"
+            );
+            assert_eq!(item("kind").extract::<String>().unwrap(), "reader");
+            assert!(item("alive").extract::<bool>().unwrap());
+            assert_eq!(
+                item("reliability").extract::<String>().unwrap(),
+                "1:0.100000000"
+            );
+
+            let participants = topology.participants(py).unwrap();
+            assert_eq!(participants.len(), 1);
+
+            let copy = PyTopology::from_json(&topology.to_json().unwrap()).unwrap();
+            assert_eq!(
+                copy.__repr__(),
+                "Topology(1 participants, 0 writers, 1 readers)"
+            );
+        });
+    }
+
+    #[test]
+    fn parse_errors() {
+        let log = TempFile::new(
+            "python.log",
+            "2022-01-20T13:24:36+0000 1642685076.168332/dq.builtin: thread_cputime .\n",
+        );
+        let log = log.path();
+
+        Python::initialize();
+        Python::attach(|py| {
+            assert!(parse(py, log, None, None, false).is_ok());
+            assert!(parse(py, log, None, None, true)
+                .err()
+                .unwrap()
+                .is_instance_of::<PyValueError>(py));
+            assert!(parse(py, "missing.log", None, None, false)
+                .err()
+                .unwrap()
+                .is_instance_of::<PyIOError>(py));
+        });
+    }
+
+    #[test]
+    fn event_records() {
+        Python::initialize();
+        Python::attach(|py| {
+            let event: PyEvent = EventReader::new(READER_SEDP_ST0.as_bytes())
+                .next()
+                .unwrap()
+                .unwrap()
+                .into();
+            let record = event.to_record(py).unwrap();
+            let item = |key: &str| record.get_item(key).unwrap().unwrap();
+            assert_eq!(item("line_number").extract::<u64>().unwrap(), 1);
+            assert_eq!(item("kind").extract::<String>().unwrap(), "reader_sedp_st0");
+            assert_eq!(
+                item("topic").extract::<String>().unwrap(),
+                "Test_command_start_cc827fa3"
+            );
+        });
+    }
+}
@@ -0,0 +1,342 @@
+use crate::ddsi_log_regex::DdsiLogType;
+use regex::Captures;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Number of writer/reader pairs reported in the summary.
+const N_TOP: usize = 10;
+/// Minimum number of ACKNACKs for a reader to be considered persistently
+/// behind.
+const MIN_ACKNACKS: usize = 3;
+/// Minimum fraction of ACKNACKs acknowledging less than the last heartbeat
+/// announced for a reader to be considered persistently behind.
+const MIN_BEHIND_RATIO: f64 = 0.5;
+
+/// Analysis of the reliable delivery protocol (HEARTBEAT, ACKNACK, NACKFRAG
+/// and GAP messages and retransmits) per writer/reader pair.
+#[derive(Debug, Default)]
+pub struct ReliabilityAnalysis {
+    pairs: BTreeMap<(String, String), PairStatistics>,
+    last_seq: BTreeMap<String, i64>,
+}
+
+/// Reliability statistics of a writer/reader pair.
+#[derive(Debug, Default, PartialEq)]
+pub struct PairStatistics {
+    pub heartbeats: usize,
+    pub acknacks: usize,
+    pub nacks: usize,
+    pub nacked_samples: usize,
+    pub nackfrags: usize,
+    pub nacked_fragments: usize,
+    pub gaps: usize,
+    pub gapped_samples: usize,
+    pub retransmits: usize,
+    pub behind: usize,
+    pub max_lag: i64,
+    pub first_seen: Option<f64>,
+    pub last_seen: Option<f64>,
+}
+
+impl PairStatistics {
+    /// Return the number of NACKs per second, over the time the pair was
+    /// active.
+    pub fn get_nack_rate(&self) -> f64 {
+        match (self.first_seen, self.last_seen) {
+            (Some(first_seen), Some(last_seen)) if last_seen > first_seen => {
+                self.nacks as f64 / (last_seen - first_seen)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Check if the reader persistently acknowledges less than the writer
+    /// announced.
+    pub fn is_behind(&self) -> bool {
+        self.acknacks >= MIN_ACKNACKS
+            && self.behind as f64 >= MIN_BEHIND_RATIO * self.acknacks as f64
+    }
+
+    fn seen(&mut self, timestamp: f64) {
+        self.first_seen = Some(self.first_seen.map_or(timestamp, |t| t.min(timestamp)));
+        self.last_seen = Some(self.last_seen.map_or(timestamp, |t| t.max(timestamp)));
+    }
+
+    fn merge(&mut self, other: PairStatistics) {
+        self.heartbeats += other.heartbeats;
+        self.acknacks += other.acknacks;
+        self.nacks += other.nacks;
+        self.nacked_samples += other.nacked_samples;
+        self.nackfrags += other.nackfrags;
+        self.nacked_fragments += other.nacked_fragments;
+        self.gaps += other.gaps;
+        self.gapped_samples += other.gapped_samples;
+        self.retransmits += other.retransmits;
+        self.behind += other.behind;
+        self.max_lag = self.max_lag.max(other.max_lag);
+        for timestamp in [other.first_seen, other.last_seen].into_iter().flatten() {
+            self.seen(timestamp);
+        }
+    }
+}
+
+impl ReliabilityAnalysis {
+    /// Update the analysis with a reliability protocol log entry. Other log
+    /// entries are ignored.
+    pub fn update(&mut self, ddsi_log_type: &DdsiLogType) -> Result<(), Box<dyn Error>> {
+        let timestamp = ddsi_log_type.get_timestamp()?;
+
+        match ddsi_log_type {
+            DdsiLogType::Heartbeat(capture) => {
+                let writer = source_guid(capture);
+                let last_seq: i64 = capture["last_seq"].parse()?;
+                self.last_seq.insert(writer.clone(), last_seq);
+
+                // A heartbeat sent to all readers (multicast) only announces
+                // the last sequence number of the writer.
+                let reader = destination_guid(capture);
+                if !is_unknown_guid(&reader) {
+                    let pair = self.get_pair(writer, reader);
+                    pair.heartbeats += 1;
+                    pair.seen(timestamp);
+                }
+            }
+            DdsiLogType::AckNack(capture) => {
+                let reader = source_guid(capture);
+                let writer = destination_guid(capture);
+                let base: i64 = capture["base"].parse()?;
+                let rexmit = match capture.name("rexmit") {
+                    Some(rexmit) => rexmit.as_str().parse()?,
+                    None => 0,
+                };
+                let lag = self
+                    .last_seq
+                    .get(&writer)
+                    .map_or(0, |last_seq| last_seq - (base - 1));
+                let nacked_samples = count_ones(&capture["bitmap"]);
+
+                let pair = self.get_pair(writer, reader);
+                pair.acknacks += 1;
+                if nacked_samples > 0 {
+                    pair.nacks += 1;
+                    pair.nacked_samples += nacked_samples;
+                }
+                pair.retransmits += rexmit;
+                if lag > 0 {
+                    pair.behind += 1;
+                    pair.max_lag = pair.max_lag.max(lag);
+                }
+                pair.seen(timestamp);
+            }
+            DdsiLogType::NackFrag(capture) => {
+                let pair = self.get_pair(destination_guid(capture), source_guid(capture));
+                pair.nackfrags += 1;
+                pair.nacked_fragments += count_ones(&capture["bitmap"]);
+                pair.seen(timestamp);
+            }
+            DdsiLogType::Gap(capture) => {
+                // Samples from the start of the gap up to the base of the
+                // bitmap, and those set in the bitmap, will not be sent.
+                let first_seq: i64 = capture["first_seq"].parse()?;
+                let last_seq: i64 = capture["last_seq"].parse()?;
+                let gapped_samples =
+                    (last_seq - first_seq).max(0) as usize + count_ones(&capture["bitmap"]);
+
+                let pair = self.get_pair(source_guid(capture), destination_guid(capture));
+                pair.gaps += 1;
+                pair.gapped_samples += gapped_samples;
+                pair.seen(timestamp);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Add the analysis of another log.
+    pub fn merge(&mut self, other: ReliabilityAnalysis) {
+        for (key, pair) in other.pairs {
+            self.pairs.entry(key).or_default().merge(pair);
+        }
+        self.last_seq.extend(other.last_seq);
+    }
+
+    /// Return the statistics of each (writer, reader) pair.
+    pub fn get_pairs(&self) -> &BTreeMap<(String, String), PairStatistics> {
+        &self.pairs
+    }
+
+    /// Check if any reliability protocol message was found.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn summarize(&self) -> String {
+        let mut summary = format!(
+            "\t- Reliability, {} writer/reader pairs:\n",
+            self.pairs.len()
+        );
+
+        let mut pairs: Vec<(&(String, String), &PairStatistics)> = self.pairs.iter().collect();
+        pairs.sort_by(|a, b| {
+            (b.1.retransmits + b.1.nacked_samples)
+                .cmp(&(a.1.retransmits + a.1.nacked_samples))
+                .then_with(|| a.0.cmp(b.0))
+        });
+
+        summary.push_str(&format!(
+            "\t\t- Top {} pairs by retransmits and NACKed samples:\n",
+            pairs.len().min(N_TOP)
+        ));
+        for ((writer, reader), pair) in pairs.iter().take(N_TOP) {
+            summary.push_str(&format!(
+                "\t\t\t- {} -> {}: heartbeats={} acknacks={} nacks={} ({:.3}/s) nacked_samples={} nackfrags={} nacked_fragments={} gaps={} gapped_samples={} retransmits={} max_lag={}\n",
+                writer,
+                reader,
+                pair.heartbeats,
+                pair.acknacks,
+                pair.nacks,
+                pair.get_nack_rate(),
+                pair.nacked_samples,
+                pair.nackfrags,
+                pair.nacked_fragments,
+                pair.gaps,
+                pair.gapped_samples,
+                pair.retransmits,
+                pair.max_lag
+            ));
+        }
+
+        let behind: Vec<&(&(String, String), &PairStatistics)> =
+            pairs.iter().filter(|(_, pair)| pair.is_behind()).collect();
+
+        summary.push_str(&format!(
+            "\t\t- Readers persistently behind {}:\n",
+            behind.len()
+        ));
+        for ((writer, reader), pair) in behind {
+            summary.push_str(&format!(
+                "\t\t\t- {} behind {}: in {}/{} acknacks, up to {} samples\n",
+                reader, writer, pair.behind, pair.acknacks, pair.max_lag
+            ));
+        }
+        summary
+    }
+
+    fn get_pair(&mut self, writer: String, reader: String) -> &mut PairStatistics {
+        self.pairs.entry((writer, reader)).or_default()
+    }
+}
+
+fn source_guid(capture: &Captures) -> String {
+    format!("{}:{}", &capture["system_id"], &capture["rw_id"])
+}
+
+fn destination_guid(capture: &Captures) -> String {
+    format!("{}:{}", &capture["dst_system_id"], &capture["dst_rw_id"])
+}
+
+/// Check if a guid is the unknown guid (0:0:0:0), used as destination of
+/// messages to all readers.
+fn is_unknown_guid(guid: &str) -> bool {
+    guid.split(':')
+        .all(|part| part.chars().all(|digit| digit == '0'))
+}
+
+fn count_ones(bitmap: &str) -> usize {
+    bitmap.chars().filter(|bit| *bit == '1').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+
+    const WRITER: &str = "745ad3d:7b:1:3102";
+    const READER: &str = "5bbed783:7b:1:3107";
+
+    fn analysis(lines: &[&str]) -> ReliabilityAnalysis {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut analysis = ReliabilityAnalysis::default();
+
+        for line in lines {
+            for ddsi_log_type in ddsi_log_regex.parse_all(line) {
+                analysis.update(&ddsi_log_type).unwrap();
+            }
+        }
+        analysis
+    }
+
+    #[test]
+    fn reliability_per_pair() {
+        let analysis = analysis(&[
+            "2022-01-23T14:11:52+0000 1642947112.000000/      recv: HEARTBEAT(#1:1..10 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107:)",
+            "2022-01-23T14:11:52+0000 1642947112.500000/      recv: ACKNACK(#1:8/3:101 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102 RX8 RX10 rexmit#2 maxseq:10<10<=10)",
+            "2022-01-23T14:11:53+0000 1642947113.000000/      recv: NACKFRAG(#1:9/1/4:0110 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102)",
+            "2022-01-23T14:11:53+0000 1642947113.500000/      recv: ACKNACK(#2:11/0: 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102)",
+        ]);
+
+        let pair = &analysis.pairs[&(String::from(WRITER), String::from(READER))];
+
+        assert_eq!(pair.heartbeats, 1);
+        assert_eq!(pair.acknacks, 2);
+        assert_eq!(pair.nacks, 1);
+        assert_eq!(pair.nacked_samples, 2);
+        assert_eq!(pair.nackfrags, 1);
+        assert_eq!(pair.nacked_fragments, 2);
+        assert_eq!(pair.retransmits, 2);
+        assert_eq!(pair.behind, 1);
+        assert_eq!(pair.max_lag, 3);
+        assert_eq!(pair.get_nack_rate(), 1.0 / 1.5);
+        assert!(!pair.is_behind());
+    }
+
+    #[test]
+    fn reader_persistently_behind() {
+        let analysis = analysis(&[
+            "2022-01-23T14:11:52+0000 1642947112.000000/      recv: HEARTBEAT(#1:1..10 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107:)",
+            "2022-01-23T14:11:52+0000 1642947112.100000/      recv: ACKNACK(#1:2/0: 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102)",
+            "2022-01-23T14:11:52+0000 1642947112.200000/      recv: ACKNACK(#2:3/0: 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102)",
+            "2022-01-23T14:11:52+0000 1642947112.300000/      recv: ACKNACK(#3:4/0: 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102)",
+        ]);
+
+        let pair = &analysis.pairs[&(String::from(WRITER), String::from(READER))];
+
+        assert_eq!(pair.behind, 3);
+        assert_eq!(pair.max_lag, 9);
+        assert!(pair.is_behind());
+        assert!(analysis
+            .summarize()
+            .contains("\t\t- Readers persistently behind 1:\n"));
+    }
+
+    #[test]
+    fn gaps_per_pair() {
+        let analysis = analysis(&[
+            "2022-01-23T14:11:52+0000 1642947112.000000/      recv: INFOTS(1642947112.000000) GAP(3..5/4:0101 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107) HEARTBEAT(#1:1..10 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107:)",
+            "2022-01-23T14:11:53+0000 1642947113.000000/      recv: GAP(12..13/0: 745ad3d:7b:1:3102 -> 5bbed783:7b:1:3107)",
+        ]);
+
+        let pair = &analysis.pairs[&(String::from(WRITER), String::from(READER))];
+
+        // Both submessages of the first line are accounted for.
+        assert_eq!(pair.heartbeats, 1);
+        assert_eq!(pair.gaps, 2);
+        assert_eq!(pair.gapped_samples, 5);
+        assert!(analysis.summarize().contains("gaps=2 gapped_samples=5"));
+    }
+
+    #[test]
+    fn multicast_heartbeat() {
+        let analysis = analysis(&[
+            "2022-01-23T14:11:52+0000 1642947112.000000/      recv: HEARTBEAT(#1:1..10 745ad3d:7b:1:3102 -> 0:0:0:0:)",
+            "2022-01-23T14:11:52+0000 1642947112.100000/      recv: ACKNACK(#1:2/0: 5bbed783:7b:1:3107 -> 745ad3d:7b:1:3102)",
+        ]);
+
+        // No pair for the unknown reader, but the lag of the reader is
+        // computed against the announced sequence number.
+        assert_eq!(analysis.pairs.len(), 1);
+        let pair = &analysis.pairs[&(String::from(WRITER), String::from(READER))];
+        assert_eq!(pair.heartbeats, 0);
+        assert_eq!(pair.max_lag, 9);
+    }
+}
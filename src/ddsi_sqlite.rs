@@ -0,0 +1,406 @@
+use crate::ddsi_analysis::LogAnalysis;
+use crate::ddsi_parser::ParserOptions;
+use crate::ddsi_topology::DdsiTopology;
+use std::error::Error;
+
+/// Save a topology, the problems found in its logs and the events of the
+/// logs to a new sqlite database, replacing any existing file.
+///
+/// The events are those of the time window of the parse, but they are not
+/// filtered: the filter only applies to the topology. The options are
+/// recorded in the `options` table.
+///
+/// # Arguments
+///
+/// * `path` - The database file to write.
+/// * `logs` - The ddsi logs to read the events of.
+/// * `options` - The options the logs were parsed with.
+/// * `filter` - The filter expression applied to the topology, if any.
+/// * `ddsi_topology` - The topology of the logs.
+/// * `analysis` - The analysis of the logs, if they were parsed.
+///
+pub fn save(
+    path: &str,
+    logs: &[&String],
+    options: &ParserOptions,
+    filter: Option<&str>,
+    ddsi_topology: &DdsiTopology,
+    analysis: Option<&LogAnalysis>,
+) -> Result<(), Box<dyn Error>> {
+    database::save(path, logs, options, filter, ddsi_topology, analysis)
+}
+
+#[cfg(feature = "sqlite")]
+mod database {
+    use crate::ddsi_analysis::LogAnalysis;
+    use crate::ddsi_event::EventReader;
+    use crate::ddsi_parser::ParserOptions;
+    use crate::ddsi_topology::{DdsiTopology, SortOrder};
+    use rusqlite::{params, Connection, Transaction};
+    use std::collections::BTreeMap;
+    use std::error::Error;
+    use std::fs::{self, File};
+    use std::io::BufReader;
+    use std::path::Path;
+
+    const SCHEMA: &str = "
+        CREATE TABLE options (
+            name TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        CREATE TABLE logs (
+            id INTEGER PRIMARY KEY,
+            filename TEXT NOT NULL UNIQUE
+        );
+        CREATE TABLE participants (
+            guid TEXT PRIMARY KEY,
+            host TEXT NOT NULL,
+            local INTEGER NOT NULL,
+            first_seen REAL,
+            last_seen REAL,
+            self_detected REAL
+        );
+        CREATE TABLE endpoints (
+            guid TEXT PRIMARY KEY,
+            participant TEXT NOT NULL REFERENCES participants (guid),
+            entity_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            topic TEXT NOT NULL,
+            partition TEXT NOT NULL,
+            alive INTEGER NOT NULL,
+            locators TEXT NOT NULL
+        );
+        CREATE TABLE qos (
+            endpoint TEXT NOT NULL REFERENCES endpoints (guid),
+            policy TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (endpoint, policy)
+        );
+        CREATE TABLE lifetimes (
+            endpoint TEXT NOT NULL REFERENCES endpoints (guid),
+            created REAL NOT NULL,
+            deleted REAL
+        );
+        CREATE TABLE events (
+            id INTEGER PRIMARY KEY,
+            log INTEGER NOT NULL REFERENCES logs (id),
+            line_number INTEGER NOT NULL,
+            timestamp REAL NOT NULL,
+            kind TEXT NOT NULL,
+            participant TEXT NOT NULL,
+            guid TEXT,
+            dst_guid TEXT,
+            topic TEXT,
+            partition TEXT,
+            fields TEXT NOT NULL
+        );
+        CREATE TABLE warnings (
+            id INTEGER PRIMARY KEY,
+            log INTEGER REFERENCES logs (id),
+            kind TEXT NOT NULL,
+            timestamp REAL,
+            thread TEXT,
+            participant TEXT,
+            guid TEXT,
+            message TEXT NOT NULL
+        );
+        CREATE INDEX endpoints_participant ON endpoints (participant);
+        CREATE INDEX endpoints_topic ON endpoints (topic);
+        CREATE INDEX lifetimes_endpoint ON lifetimes (endpoint);
+        CREATE INDEX events_guid ON events (guid);
+        CREATE INDEX events_dst_guid ON events (dst_guid);
+        CREATE INDEX events_topic ON events (topic);
+        CREATE INDEX warnings_log ON warnings (log);
+        CREATE INDEX warnings_participant ON warnings (participant);
+        CREATE INDEX warnings_guid ON warnings (guid);
+    ";
+
+    pub fn save(
+        path: &str,
+        logs: &[&String],
+        options: &ParserOptions,
+        filter: Option<&str>,
+        ddsi_topology: &DdsiTopology,
+        analysis: Option<&LogAnalysis>,
+    ) -> Result<(), Box<dyn Error>> {
+        if Path::new(path).exists() {
+            fs::remove_file(path)?;
+        }
+        let mut connection = Connection::open(path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(SCHEMA)?;
+
+        insert_options(&transaction, options, filter)?;
+        insert_topology(&transaction, ddsi_topology)?;
+        let mut log_ids = BTreeMap::new();
+        for log in logs {
+            transaction.execute("INSERT INTO logs (filename) VALUES (?1)", params![log])?;
+            log_ids.insert(log.as_str(), transaction.last_insert_rowid());
+        }
+        if let Some(analysis) = analysis {
+            insert_warnings(&transaction, analysis, &log_ids)?;
+        }
+        for (log, log_id) in &log_ids {
+            insert_events(&transaction, log, *log_id, options)?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn insert_options(
+        transaction: &Transaction,
+        options: &ParserOptions,
+        filter: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut insert_option =
+            transaction.prepare("INSERT INTO options (name, value) VALUES (?1, ?2)")?;
+        for (name, value) in [
+            ("since", options.since.map(|since| since.to_string())),
+            ("until", options.until.map(|until| until.to_string())),
+            ("filter", filter.map(String::from)),
+        ] {
+            if let Some(value) = value {
+                insert_option.execute(params![name, value])?;
+            }
+        }
+        // The events of the time window are all saved, whatever the filter.
+        insert_option.execute(params!["events", "unfiltered"])?;
+        Ok(())
+    }
+
+    fn insert_topology(
+        transaction: &Transaction,
+        ddsi_topology: &DdsiTopology,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut insert_participant =
+            transaction.prepare("INSERT INTO participants VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+        let mut insert_endpoint =
+            transaction.prepare("INSERT INTO endpoints VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")?;
+        let mut insert_qos = transaction.prepare("INSERT INTO qos VALUES (?1, ?2, ?3)")?;
+        let mut insert_lifetime =
+            transaction.prepare("INSERT INTO lifetimes VALUES (?1, ?2, ?3)")?;
+
+        for participant_id in ddsi_topology.get_participants_ids(SortOrder::Guid) {
+            let participant = ddsi_topology.get_participant(&participant_id).unwrap();
+            insert_participant.execute(params![
+                participant_id,
+                participant.get_hostname(),
+                participant.is_local(),
+                participant.get_first_seen(),
+                participant.get_last_seen(),
+                participant.get_self_detected(),
+            ])?;
+
+            for (kind, ids) in [
+                ("writer", participant.get_writers_id(SortOrder::Guid)),
+                ("reader", participant.get_readers_id(SortOrder::Guid)),
+            ] {
+                for id in ids {
+                    let qos = match kind {
+                        "writer" => participant.get_writer_qos(&id),
+                        _ => participant.get_reader_qos(&id),
+                    }
+                    .unwrap();
+                    let guid = format!("{}:{}", participant_id, id);
+                    insert_endpoint.execute(params![
+                        guid,
+                        participant_id,
+                        id,
+                        kind,
+                        qos.get_topic(),
+                        qos.get_partition(),
+                        qos.is_alive(),
+                        qos.get_locators().join(" "),
+                    ])?;
+                    for (policy, value) in qos.get_policies() {
+                        insert_qos.execute(params![guid, policy, value])?;
+                    }
+                    for (created, deleted) in qos.get_lifetimes() {
+                        insert_lifetime.execute(params![guid, created, deleted])?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert the problems found in the logs, referencing the log each was
+    /// found in when it is known.
+    fn insert_warnings(
+        transaction: &Transaction,
+        analysis: &LogAnalysis,
+        log_ids: &BTreeMap<&str, i64>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut insert_warning = transaction.prepare(
+            "INSERT INTO warnings (log, kind, timestamp, thread, participant, guid, message) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for problem in analysis.problems.get_problems() {
+            let guid = match (&problem.participant_id, &problem.endpoint_id) {
+                (Some(participant_id), Some(endpoint_id)) => {
+                    Some(format!("{}:{}", participant_id, endpoint_id))
+                }
+                _ => None,
+            };
+            let log_id = problem.log.as_deref().and_then(|log| log_ids.get(log));
+            insert_warning.execute(params![
+                log_id,
+                problem.kind.to_string(),
+                problem.timestamp,
+                problem.thread,
+                problem.participant_id,
+                guid,
+                problem.message,
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn insert_events(
+        transaction: &Transaction,
+        log: &str,
+        log_id: i64,
+        options: &ParserOptions,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut insert_event = transaction.prepare(
+            "INSERT INTO events \
+            (log, line_number, timestamp, kind, participant, guid, dst_guid, topic, partition, fields) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )?;
+        let file = File::open(log).map_err(|error| format!("{}: {}", log, error))?;
+        for event in
+            EventReader::new(BufReader::new(file)).with_window(options.since, options.until)
+        {
+            let event = event?;
+            insert_event.execute(params![
+                log_id,
+                event.line_number,
+                event.timestamp,
+                event.kind,
+                event.system_id,
+                event.get_guid(),
+                event.get_dst_guid(),
+                event.get_field("topic"),
+                event.get_field("partition"),
+                serde_json::to_string(&event.fields)?,
+            ])?;
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::ddsi_parser::{Parser, ParserOptions};
+        use crate::ddsi_test_data::{TempFile, READER_SEDP_ST0};
+
+        #[test]
+        fn save_database() {
+            let log_file = TempFile::new(
+                "sqlite.log",
+                &format!(
+                    "{}\n2022-01-23T14:11:31+0000 1642947091.000000/     lease: lease expired for participant 5bbed783:7b:1\n",
+                    READER_SEDP_ST0
+                ),
+            );
+            let path_file = TempFile::new("sqlite.db", "");
+            let log = String::from(log_file.path());
+            let path = path_file.path();
+
+            let (ddsi_topology, mut analysis) = Parser::new(ParserOptions::default())
+                .parse(File::open(&log).unwrap(), None)
+                .unwrap();
+            analysis.set_log(&log);
+            let options = ParserOptions::default();
+            save(
+                path,
+                &[&log],
+                &options,
+                None,
+                &ddsi_topology,
+                Some(&analysis),
+            )
+            .unwrap();
+            // Saving again replaces the database.
+            save(
+                path,
+                &[&log],
+                &options,
+                None,
+                &ddsi_topology,
+                Some(&analysis),
+            )
+            .unwrap();
+
+            let connection = Connection::open(path).unwrap();
+            let count =
+                |sql: &str| -> i64 { connection.query_row(sql, [], |row| row.get(0)).unwrap() };
+            assert_eq!(count("SELECT COUNT(*) FROM logs"), 1);
+            assert_eq!(count("SELECT COUNT(*) FROM participants"), 1);
+            assert_eq!(
+                count("SELECT COUNT(*) FROM endpoints WHERE topic = 'Test_command_start_cc827fa3' AND alive"),
+                1
+            );
+            assert_eq!(
+                count("SELECT COUNT(*) FROM qos WHERE endpoint = '745ad3d:7b:1:3b07'"),
+                19
+            );
+            assert_eq!(
+                count("SELECT COUNT(*) FROM lifetimes WHERE deleted IS NULL"),
+                1
+            );
+            assert_eq!(
+                count("SELECT COUNT(*) FROM events JOIN endpoints USING (guid)"),
+                1
+            );
+            assert_eq!(
+                count("SELECT COUNT(*) FROM warnings JOIN logs ON logs.id = warnings.log WHERE participant = '5bbed783:7b:1'"),
+                1
+            );
+            drop(connection);
+
+            // Events are those of the time window, the filter is recorded.
+            let options = ParserOptions {
+                since: Some(1642947090.0),
+                ..Default::default()
+            };
+            save(
+                path,
+                &[&log],
+                &options,
+                Some("kind == writer"),
+                &ddsi_topology,
+                Some(&analysis),
+            )
+            .unwrap();
+            let connection = Connection::open(path).unwrap();
+            let count =
+                |sql: &str| -> i64 { connection.query_row(sql, [], |row| row.get(0)).unwrap() };
+            assert_eq!(count("SELECT COUNT(*) FROM events"), 0);
+            assert_eq!(
+                count("SELECT COUNT(*) FROM options WHERE name = 'filter' AND value = 'kind == writer'"),
+                1
+            );
+        }
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+mod database {
+    use crate::ddsi_analysis::LogAnalysis;
+    use crate::ddsi_parser::ParserOptions;
+    use crate::ddsi_topology::DdsiTopology;
+    use std::error::Error;
+
+    pub fn save(
+        _path: &str,
+        _logs: &[&String],
+        _options: &ParserOptions,
+        _filter: Option<&str>,
+        _ddsi_topology: &DdsiTopology,
+        _analysis: Option<&LogAnalysis>,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Saving to sqlite requires building with the sqlite feature.".into())
+    }
+}
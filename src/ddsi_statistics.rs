@@ -0,0 +1,326 @@
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+
+/// Number of unrecognized line shapes reported in the summary.
+const N_SHAPES: usize = 20;
+
+/// Number of unrecognized line shapes kept when evicting the least frequent
+/// ones. Up to twice as many are counted between evictions.
+const MAX_SHAPES: usize = 1000;
+
+const LINE_REGEX: &str =
+    r"^\d{4}-\d{2}-\d{2}T\S* [0-9]*\.[0-9]*/\s*(?P<thread>[^:]*): (?P<message>.*)$";
+const ID_REGEX: &str = r"\b[0-9a-fA-F]+(:[0-9a-fA-F]+)+\b";
+const NUMBER_REGEX: &str = r"[0-9]+\.[0-9]+|\b[0-9a-fA-F]*[0-9][0-9a-fA-F]*\b|[0-9]+";
+
+/// Statistics over all the lines of a log, matched or not.
+#[derive(Debug)]
+pub struct LineStatistics {
+    line_regex: Regex,
+    id_regex: Regex,
+    number_regex: Regex,
+    n_lines: usize,
+    n_matched: usize,
+    n_problems: usize,
+    threads: BTreeMap<String, Counts>,
+    categories: BTreeMap<String, Counts>,
+    unrecognized: HashMap<String, usize>,
+    n_evicted: usize,
+}
+
+/// Number of lines and of lines matching ddsi logs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Counts {
+    lines: usize,
+    matched: usize,
+}
+
+impl LineStatistics {
+    pub fn new() -> LineStatistics {
+        LineStatistics {
+            line_regex: Regex::new(LINE_REGEX).unwrap(),
+            id_regex: Regex::new(ID_REGEX).unwrap(),
+            number_regex: Regex::new(NUMBER_REGEX).unwrap(),
+            n_lines: 0,
+            n_matched: 0,
+            n_problems: 0,
+            threads: BTreeMap::new(),
+            categories: BTreeMap::new(),
+            unrecognized: HashMap::new(),
+            n_evicted: 0,
+        }
+    }
+
+    /// Account for a log line.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The log line.
+    /// * `matched` - Whether the line was recognized as a ddsi log entry.
+    /// * `is_problem` - Whether the line was recognized as a problem. Such
+    ///   lines are not matched entries but are not reported as unrecognized.
+    ///
+    pub fn update(&mut self, line: &str, matched: bool, is_problem: bool) {
+        let (thread, message) = match self.line_regex.captures(line) {
+            Some(capture) => (
+                capture.name("thread").unwrap().as_str(),
+                capture.name("message").unwrap().as_str(),
+            ),
+            None => ("-", line),
+        };
+        let category = message
+            .split_whitespace()
+            .next()
+            .map_or("-", |word| word.trim_end_matches(':'));
+
+        let matched_count = usize::from(matched);
+
+        self.n_lines += 1;
+        self.n_matched += matched_count;
+        self.n_problems += usize::from(is_problem);
+
+        for counts in [
+            self.threads.entry(String::from(thread)).or_default(),
+            self.categories.entry(String::from(category)).or_default(),
+        ] {
+            counts.lines += 1;
+            counts.matched += matched_count;
+        }
+
+        if !matched && !is_problem {
+            let shape = format!("{}: {}", thread, self.mask(message));
+            self.add_unrecognized(shape, 1);
+        }
+    }
+
+    /// Add the statistics of another log.
+    pub fn merge(&mut self, other: LineStatistics) {
+        self.n_lines += other.n_lines;
+        self.n_matched += other.n_matched;
+        self.n_problems += other.n_problems;
+
+        for (mine, others) in [
+            (&mut self.threads, other.threads),
+            (&mut self.categories, other.categories),
+        ] {
+            for (key, counts) in others {
+                let entry = mine.entry(key).or_default();
+                entry.lines += counts.lines;
+                entry.matched += counts.matched;
+            }
+        }
+        self.n_evicted += other.n_evicted;
+        for (shape, count) in other.unrecognized {
+            self.add_unrecognized(shape, count);
+        }
+    }
+
+    /// Return the number of lines, matched or not.
+    pub fn get_n_lines(&self) -> usize {
+        self.n_lines
+    }
+
+    /// Return the number of lines matching ddsi logs.
+    pub fn get_n_matched(&self) -> usize {
+        self.n_matched
+    }
+
+    pub fn summarize(&self) -> String {
+        let mut summary = format!(
+            "\t- Line statistics, {} lines, {} matched, {} problems:\n",
+            self.n_lines, self.n_matched, self.n_problems
+        );
+
+        summary.push_str(&format!("\t\t- Threads {}:\n", self.threads.len()));
+        summary.push_str(&summarize_counts(&self.threads));
+
+        summary.push_str(&format!("\t\t- Categories {}:\n", self.categories.len()));
+        summary.push_str(&summarize_counts(&self.categories));
+
+        let mut shapes: Vec<(&String, &usize)> = self.unrecognized.iter().collect();
+        shapes.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+        summary.push_str(&format!(
+            "\t\t- Most frequent unrecognized lines, {} shapes:\n",
+            shapes.len()
+        ));
+        for (shape, count) in shapes.into_iter().take(N_SHAPES) {
+            summary.push_str(&format!("\t\t\t- {}: {}\n", count, shape));
+        }
+        if self.n_evicted > 0 {
+            summary.push_str(&format!(
+                "\t\t\t- {} lines of less frequent shapes not kept\n",
+                self.n_evicted
+            ));
+        }
+        summary
+    }
+
+    /// Count unrecognized lines of a shape, evicting the least frequent
+    /// shapes once there are too many of them so that memory stays bounded
+    /// on logs with many distinct lines.
+    fn add_unrecognized(&mut self, shape: String, count: usize) {
+        *self.unrecognized.entry(shape).or_default() += count;
+
+        if self.unrecognized.len() > 2 * MAX_SHAPES {
+            let mut shapes: Vec<(String, usize)> = self.unrecognized.drain().collect();
+            shapes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            self.n_evicted += shapes
+                .split_off(MAX_SHAPES)
+                .into_iter()
+                .map(|(_, count)| count)
+                .sum::<usize>();
+            self.unrecognized = shapes.into_iter().collect();
+        }
+    }
+
+    /// Mask ids and numbers of a message so lines of the same kind share the
+    /// same shape.
+    fn mask(&self, message: &str) -> String {
+        let masked = self.id_regex.replace_all(message, "<id>");
+        self.number_regex.replace_all(&masked, "<n>").into_owned()
+    }
+}
+
+impl Default for LineStatistics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn summarize_counts(counts: &BTreeMap<String, Counts>) -> String {
+    let mut sorted: Vec<(&String, &Counts)> = counts.iter().collect();
+    sorted.sort_by(|a, b| b.1.lines.cmp(&a.1.lines).then_with(|| a.0.cmp(b.0)));
+
+    sorted
+        .into_iter()
+        .map(|(key, counts)| {
+            format!(
+                "\t\t\t- {}: {} lines, {} matched\n",
+                key, counts.lines, counts.matched
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_and_category_histograms() {
+        let mut statistics = LineStatistics::new();
+
+        statistics.update(
+            "2022-01-23T14:08:13+0000 1642946893.209853/      main: ownip: 172.17.0.3",
+            true,
+            false,
+        );
+        statistics.update(
+            "2022-01-20T13:24:36+0000 1642685076.168332/dq.builtin: thread_cputime 1260.618874505",
+            false,
+            false,
+        );
+        statistics.update(
+            "2022-01-20T13:24:37+0000 1642685077.168332/dq.builtin: thread_cputime 1261.618874505",
+            false,
+            false,
+        );
+        statistics.update("not a ddsi log line", false, false);
+
+        assert_eq!(statistics.n_lines, 4);
+        assert_eq!(statistics.get_n_matched(), 1);
+        assert_eq!(
+            statistics.threads["dq.builtin"],
+            Counts {
+                lines: 2,
+                matched: 0
+            }
+        );
+        assert_eq!(
+            statistics.threads["main"],
+            Counts {
+                lines: 1,
+                matched: 1
+            }
+        );
+        assert_eq!(statistics.categories["ownip"].matched, 1);
+        assert_eq!(statistics.categories["thread_cputime"].lines, 2);
+        assert_eq!(statistics.unrecognized["dq.builtin: thread_cputime <n>"], 2);
+        assert_eq!(statistics.threads["-"].lines, 1);
+    }
+
+    #[test]
+    fn mask_ids_and_numbers() {
+        let statistics = LineStatistics::new();
+
+        assert_eq!(
+            statistics.mask("write_sample 745ad3d:7b:1:3007 #12 lease 5bbed783 expired"),
+            "write_sample <id> #<n> lease <n> expired"
+        );
+        assert_eq!(
+            statistics.mask("xmit lease expired after 10.5s, 3 retries"),
+            "xmit lease expired after <n>s, <n> retries"
+        );
+    }
+
+    #[test]
+    fn merge_statistics() {
+        let mut statistics = LineStatistics::new();
+        let mut other = LineStatistics::new();
+
+        statistics.update(
+            "2022-01-23T14:08:13+0000 1642946893.209853/      main: ownip: 172.17.0.3",
+            true,
+            false,
+        );
+        other.update(
+            "2022-01-23T14:08:13+0000 1642946893.209853/      main: ownip: 172.17.0.4",
+            true,
+            false,
+        );
+        statistics.merge(other);
+
+        assert_eq!(statistics.get_n_matched(), 2);
+        assert_eq!(statistics.threads["main"].lines, 2);
+    }
+
+    #[test]
+    fn bounded_unrecognized_shapes() {
+        let mut statistics = LineStatistics::new();
+
+        for _ in 0..3 {
+            statistics.update("frequent line", false, false);
+        }
+        // Shapes made of letters only, so they are not masked.
+        for i in 0..2 * MAX_SHAPES {
+            let word: String = [i / 400, i / 20 % 20, i % 20]
+                .iter()
+                .map(|digit| char::from(b'g' + *digit as u8))
+                .collect();
+            statistics.update(&word, false, false);
+        }
+
+        assert_eq!(statistics.unrecognized.len(), MAX_SHAPES);
+        assert_eq!(statistics.unrecognized["-: frequent line"], 3);
+        assert_eq!(statistics.n_evicted, MAX_SHAPES + 1);
+        assert!(statistics
+            .summarize()
+            .contains("1001 lines of less frequent shapes not kept"));
+    }
+
+    #[test]
+    fn problems_are_not_matches() {
+        let mut statistics = LineStatistics::new();
+
+        statistics.update(
+            "2022-01-23T14:11:31+0000 1642947091.000000/     lease: lease expired for participant 5bbed783:7b:1",
+            false,
+            true,
+        );
+
+        assert_eq!(statistics.get_n_matched(), 0);
+        assert_eq!(statistics.n_problems, 1);
+        assert!(statistics.unrecognized.is_empty());
+    }
+}
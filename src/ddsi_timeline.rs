@@ -0,0 +1,247 @@
+use crate::ddsi_participant::{DdsiParticipant, Qos};
+use crate::ddsi_topology::{DdsiTopology, SortOrder};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Render the discovery events of a topology as a Chrome trace-event json,
+/// viewable in Perfetto or chrome://tracing.
+///
+/// Each host is a process and each participant a thread (track) within it.
+/// Participant self-detection, endpoint creation/discovery and deletion are
+/// instant events on the participant track, and each endpoint lifetime is an
+/// async span. Lifetimes still open at the end of the log end at the last
+/// event of the topology. The CPU usage of the ddsi2 threads, if logged, is
+/// a counter per thread on the host process.
+pub fn to_chrome_trace(ddsi_topology: &DdsiTopology) -> String {
+    let participants: Vec<&DdsiParticipant> = ddsi_topology
+        .get_participants_ids(SortOrder::Host)
+        .iter()
+        .map(|participant_id| ddsi_topology.get_participant(participant_id).unwrap())
+        .collect();
+
+    let end = participants
+        .iter()
+        .filter_map(|participant| participant.get_last_seen())
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let mut pids = BTreeMap::new();
+    let mut events = vec![];
+
+    for (tid, participant) in participants.iter().enumerate() {
+        let pid = get_pid(&mut pids, &mut events, participant.get_hostname());
+        let tid = tid + 1;
+
+        events.push(json!({
+            "name": "thread_name",
+            "ph": "M",
+            "pid": pid,
+            "tid": tid,
+            "args": {"name": participant.get_system_id()},
+        }));
+
+        if let Some(self_detected) = participant.get_self_detected() {
+            events.push(instant(
+                "self detected",
+                "participant",
+                self_detected,
+                pid,
+                tid,
+                json!({}),
+            ));
+        }
+
+        let created_name = if participant.is_local() {
+            "created"
+        } else {
+            "discovered"
+        };
+
+        for (kind, id, qos) in endpoints(participant) {
+            let guid = format!("{}:{}", participant.get_system_id(), id);
+            let args = json!({
+                "guid": guid,
+                "topic": qos.get_topic(),
+                "partition": qos.get_partition(),
+            });
+
+            for (created, deleted) in qos.get_lifetimes() {
+                let span_id = format!("{}@{}", guid, created);
+                events.push(json!({
+                    "name": qos.get_topic(),
+                    "cat": kind,
+                    "ph": "b",
+                    "id": span_id,
+                    "ts": to_us(created),
+                    "pid": pid,
+                    "tid": tid,
+                    "args": args,
+                }));
+                events.push(json!({
+                    "name": qos.get_topic(),
+                    "cat": kind,
+                    "ph": "e",
+                    "id": span_id,
+                    "ts": to_us(deleted.unwrap_or(end)),
+                    "pid": pid,
+                    "tid": tid,
+                }));
+            }
+            for created in qos.get_created() {
+                events.push(instant(
+                    created_name,
+                    kind,
+                    *created,
+                    pid,
+                    tid,
+                    args.clone(),
+                ));
+            }
+            for deleted in qos.get_deleted() {
+                events.push(instant("deleted", kind, *deleted, pid, tid, args.clone()));
+            }
+        }
+    }
+
+    for (log, host, thread, series) in ddsi_topology.get_cputime().get_usage() {
+        let pid = get_pid(&mut pids, &mut events, host);
+        // Logs of the same host have counters of their own.
+        let name = if log.is_empty() {
+            format!("cpu {}", thread)
+        } else {
+            format!("cpu {} {}", log, thread)
+        };
+        for usage in series {
+            events.push(json!({
+                "name": name,
+                "cat": "cputime",
+                "ph": "C",
+                "ts": to_us(usage.start),
+                "pid": pid,
+                "args": {"usage": 100.0 * usage.usage},
+            }));
+        }
+    }
+
+    json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms",
+    })
+    .to_string()
+}
+
+/// Return the readers and writers of a participant as (kind, id, qos).
+fn endpoints(participant: &DdsiParticipant) -> Vec<(&'static str, String, &Qos)> {
+    let readers = participant
+        .get_readers_id(SortOrder::FirstSeen)
+        .into_iter()
+        .map(|id| {
+            let qos = participant.get_reader_qos(&id).unwrap();
+            ("reader", id, qos)
+        });
+    let writers = participant
+        .get_writers_id(SortOrder::FirstSeen)
+        .into_iter()
+        .map(|id| {
+            let qos = participant.get_writer_qos(&id).unwrap();
+            ("writer", id, qos)
+        });
+    readers.chain(writers).collect()
+}
+
+/// Return the process id of a host, adding its metadata event the first
+/// time the host is seen.
+fn get_pid(pids: &mut BTreeMap<String, usize>, events: &mut Vec<Value>, host: &String) -> usize {
+    let next_pid = pids.len() + 1;
+    *pids.entry(host.clone()).or_insert_with(|| {
+        events.push(json!({
+            "name": "process_name",
+            "ph": "M",
+            "pid": next_pid,
+            "args": {"name": host},
+        }));
+        next_pid
+    })
+}
+
+fn instant(
+    name: &str,
+    category: &str,
+    timestamp: f64,
+    pid: usize,
+    tid: usize,
+    args: Value,
+) -> Value {
+    json!({
+        "name": name,
+        "cat": category,
+        "ph": "i",
+        "s": "t",
+        "ts": to_us(timestamp),
+        "pid": pid,
+        "tid": tid,
+        "args": args,
+    })
+}
+
+/// Convert a log timestamp, in seconds, to the trace microseconds.
+fn to_us(timestamp: f64) -> f64 {
+    (timestamp * 1e6).round()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::{READER_QOS, READER_SEDP_ST0};
+
+    const LOG_SAMPLE: [&str; 6] = [
+        "2022-01-23T14:11:28+0000 1642947088.000000/      main: handleParticipantsSelf: found 5bbed783:7b:1 (self)",
+        READER_QOS,
+        READER_SEDP_ST0,
+        "2022-01-23T14:11:51+0000 1642947111.745674/dq.builtin: SEDP ST3 745ad3d:7b:1:3b07delete_proxy_reader (745ad3d:7b:1:3b07) - deleting",
+        "2022-01-23T14:11:52+0000 1642947112.000000/      recv: thread_cputime 10.000000000",
+        "2022-01-23T14:11:54+0000 1642947114.000000/      recv: thread_cputime 11.000000000",
+    ];
+
+    #[test]
+    fn chrome_trace() {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        for line in LOG_SAMPLE {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(line).unwrap())
+                .unwrap();
+        }
+
+        let trace: Value = serde_json::from_str(&to_chrome_trace(&ddsi_topology)).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+
+        let names: Vec<&str> = events
+            .iter()
+            .filter(|event| event["ph"] == "i")
+            .map(|event| event["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["discovered", "deleted", "self detected", "created"]);
+
+        let span_ends: Vec<f64> = events
+            .iter()
+            .filter(|event| event["ph"] == "e")
+            .map(|event| event["ts"].as_f64().unwrap())
+            .collect();
+        assert_eq!(span_ends, [1642947111745674.0, 1642947111745674.0]);
+
+        assert_eq!(
+            events
+                .iter()
+                .filter(|event| event["name"] == "thread_name")
+                .count(),
+            2
+        );
+
+        let counters: Vec<&Value> = events.iter().filter(|event| event["ph"] == "C").collect();
+        assert_eq!(counters.len(), 1);
+        assert_eq!(counters[0]["name"], "cpu recv");
+        assert_eq!(counters[0]["args"]["usage"], 50.0);
+    }
+}
@@ -1,21 +1,128 @@
+use crate::ddsi_cputime::ThreadsCpuTime;
 use crate::ddsi_log_regex::DdsiLogType;
-use crate::ddsi_participant::DdsiParticipant;
+use crate::ddsi_participant::{DdsiParticipant, Qos};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
+use std::io::Read;
+use std::net::IpAddr;
+use std::{fmt, str::FromStr};
+
+/// Version of the serialized topology format. Increase it whenever a change
+/// to the format would prevent older versions from loading it.
+pub const SCHEMA_VERSION: u64 = 2;
+
+/// Order in which participants and endpoints are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    /// Order by the timestamp of the first log entry seen.
+    #[default]
+    FirstSeen,
+    /// Order participants by hostname.
+    Host,
+    /// Order by participant/endpoint id.
+    Guid,
+    /// Order endpoints by topic and partition.
+    Topic,
+}
+
+#[derive(Debug)]
+pub struct UnknownSortOrder(String);
+
+impl Error for UnknownSortOrder {}
+
+impl fmt::Display for UnknownSortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unknown sort order {}, expected one of first-seen, host, guid or topic.",
+            self.0
+        )
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = UnknownSortOrder;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first-seen" => Ok(SortOrder::FirstSeen),
+            "host" => Ok(SortOrder::Host),
+            "guid" => Ok(SortOrder::Guid),
+            "topic" => Ok(SortOrder::Topic),
+            _ => Err(UnknownSortOrder(String::from(s))),
+        }
+    }
+}
 
 #[derive(Debug)]
+pub struct UnsupportedSchemaVersion(u64);
+
+impl Error for UnsupportedSchemaVersion {}
+
+impl fmt::Display for UnsupportedSchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Topology schema version {} is newer than the supported version {}.",
+            self.0, SCHEMA_VERSION
+        )
+    }
+}
+
+/// Number of participants, hosts, topics and endpoints of a topology.
+#[derive(Debug, Default, PartialEq)]
+pub struct TopologyCounts {
+    pub participants: usize,
+    pub hosts: usize,
+    pub topics: usize,
+    pub readers: usize,
+    pub alive_readers: usize,
+    pub writers: usize,
+    pub alive_writers: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct DdsiTopology {
-    participants: HashMap<String, DdsiParticipant>,
+    participants: BTreeMap<String, DdsiParticipant>,
     own_ip: String,
+    #[serde(default)]
+    cputime: ThreadsCpuTime,
+    /// Configuration logged before the self participant was detected.
+    #[serde(default)]
+    pending_config: BTreeMap<String, String>,
 }
 
 impl DdsiTopology {
     pub fn new() -> DdsiTopology {
         DdsiTopology {
-            participants: HashMap::new(),
+            participants: BTreeMap::new(),
             own_ip: String::from("unkwnown"),
+            cputime: ThreadsCpuTime::default(),
+            pending_config: BTreeMap::new(),
+        }
+    }
+
+    /// Load a topology previously saved as json.
+    ///
+    /// Files written before the schema version was introduced are accepted,
+    /// with the missing fields left empty.
+    pub fn from_json<R: Read>(reader: R) -> Result<DdsiTopology, Box<dyn Error>> {
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+
+        let version = value
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0);
+
+        if version > SCHEMA_VERSION {
+            return Err(Box::new(UnsupportedSchemaVersion(version)));
         }
+
+        Ok(serde_json::from_value(value)?)
     }
 
     pub fn update(&mut self, dds_log_type: DdsiLogType) -> Result<(), Box<dyn Error>> {
@@ -24,7 +131,33 @@ impl DdsiTopology {
         if let DdsiLogType::OwnIp(capture) = dds_log_type {
             self.own_ip = String::from(&capture["hostname"]);
             Ok(())
+        } else if let DdsiLogType::ThreadCputime(capture) = &dds_log_type {
+            self.cputime.add(
+                &self.own_ip,
+                &capture["thread"],
+                dds_log_type.get_timestamp()?,
+                capture["cputime"].parse()?,
+            );
+            Ok(())
+        } else if let DdsiLogType::Config(capture) = &dds_log_type {
+            let (key, value) = (
+                String::from(&capture["key"]),
+                String::from(&capture["value"]),
+            );
+            match self.get_self_participant_id() {
+                Some(participant_id) => self
+                    .participants
+                    .get_mut(&participant_id)
+                    .unwrap()
+                    .add_config(key, value),
+                None => {
+                    self.pending_config.insert(key, value);
+                }
+            }
+            Ok(())
         } else {
+            let is_self = matches!(dds_log_type, DdsiLogType::HandleParticipantsSelf(_));
+
             let participant = self
                 .participants
                 .entry(system_id)
@@ -33,26 +166,183 @@ impl DdsiTopology {
                     &self.own_ip,
                 ));
 
-            participant.update(dds_log_type)
+            let result = participant.update(dds_log_type);
+
+            // Configuration is logged at startup, before the self participant
+            // is known.
+            if is_self {
+                for (key, value) in std::mem::take(&mut self.pending_config) {
+                    participant.add_config(key, value);
+                }
+            }
+            result
+        }
+    }
+
+    /// Name the log the topology was parsed from, to tell its samples apart
+    /// from those of other logs of the same host once merged.
+    pub fn set_log(&mut self, log: &str) {
+        self.cputime.set_log(log);
+    }
+
+    /// Merge the topology parsed from another log into this one.
+    pub fn merge(&mut self, other: DdsiTopology) {
+        for (participant_id, participant) in other.participants {
+            match self.participants.get_mut(&participant_id) {
+                Some(own_participant) => own_participant.merge(participant),
+                None => {
+                    self.participants.insert(participant_id, participant);
+                }
+            }
+        }
+        for (key, value) in other.pending_config {
+            self.pending_config.entry(key).or_insert(value);
+        }
+        self.cputime.merge(other.cputime);
+    }
+
+    /// Keep only the endpoints for which the predicate, called with the
+    /// participant, the endpoint id, whether it is a writer and its QoS,
+    /// returns true. Participants left without endpoints are removed, the
+    /// configuration of the self participant being kept as pending.
+    pub fn retain_endpoints<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&DdsiParticipant, &str, bool, &Qos) -> bool,
+    {
+        let self_participant_id = self.get_self_participant_id();
+        for participant in self.participants.values_mut() {
+            participant.retain_endpoints(&mut predicate);
+        }
+        self.participants.retain(|participant_id, participant| {
+            if participant.has_endpoints() {
+                return true;
+            }
+            if self_participant_id.as_ref() == Some(participant_id) {
+                for (key, value) in participant.get_config() {
+                    self.pending_config
+                        .entry(key.clone())
+                        .or_insert_with(|| value.clone());
+                }
+            }
+            false
+        });
+    }
+
+    /// Return the ip of the node that produced the log.
+    pub fn get_own_ip(&self) -> &String {
+        &self.own_ip
+    }
+
+    /// Return the id of the log's self participant, the first one detected,
+    /// if any.
+    pub fn get_self_participant_id(&self) -> Option<String> {
+        self.participants
+            .values()
+            .filter_map(|participant| Some((participant.get_self_detected()?, participant)))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, participant)| participant.get_system_id().clone())
+    }
+
+    /// Return the configuration logged by the node, if any.
+    pub fn get_config(&self) -> Option<&BTreeMap<String, String>> {
+        let config = match self.get_self_participant_id() {
+            Some(participant_id) => self.participants[&participant_id].get_config(),
+            None => &self.pending_config,
+        };
+        if config.is_empty() {
+            None
+        } else {
+            Some(config)
         }
     }
 
+    /// Return the CPU time consumed by the ddsi2 threads.
+    pub fn get_cputime(&self) -> &ThreadsCpuTime {
+        &self.cputime
+    }
+
+    /// Return the participant with the given id.
+    pub fn get_participant(&self, participant_id: &str) -> Option<&DdsiParticipant> {
+        self.participants.get(participant_id)
+    }
+
     pub fn len(&self) -> usize {
         self.participants.len()
     }
 
-    pub fn get_participants_ids(&self) -> Vec<String> {
-        self.participants.keys().cloned().collect()
+    pub fn is_empty(&self) -> bool {
+        self.participants.is_empty()
+    }
+
+    /// Count the participants, hosts, topics and endpoints.
+    pub fn get_counts(&self) -> TopologyCounts {
+        let mut counts = TopologyCounts {
+            participants: self.participants.len(),
+            ..Default::default()
+        };
+        let mut hosts = BTreeSet::new();
+        let mut topics = BTreeSet::new();
+
+        for participant in self.participants.values() {
+            hosts.insert(participant.get_hostname());
+
+            for id in participant.get_readers_id(SortOrder::Guid) {
+                let qos = participant.get_reader_qos(&id).unwrap();
+                topics.insert(qos.get_topic());
+                counts.readers += 1;
+                counts.alive_readers += qos.is_alive() as usize;
+            }
+            for id in participant.get_writers_id(SortOrder::Guid) {
+                let qos = participant.get_writer_qos(&id).unwrap();
+                topics.insert(qos.get_topic());
+                counts.writers += 1;
+                counts.alive_writers += qos.is_alive() as usize;
+            }
+        }
+        counts.hosts = hosts.len();
+        counts.topics = topics.len();
+        counts
+    }
+
+    /// Return the participants ids in the requested order.
+    ///
+    /// Ties are always broken by the participant id, so the output is
+    /// deterministic.
+    pub fn get_participants_ids(&self, sort_order: SortOrder) -> Vec<String> {
+        let mut participants: Vec<&DdsiParticipant> = self.participants.values().collect();
+
+        match sort_order {
+            SortOrder::FirstSeen => participants.sort_by(|a, b| {
+                let first_seen = |participant: &DdsiParticipant| {
+                    participant.get_first_seen().unwrap_or(f64::INFINITY)
+                };
+                first_seen(a)
+                    .total_cmp(&first_seen(b))
+                    .then_with(|| a.get_system_id().cmp(b.get_system_id()))
+            }),
+            SortOrder::Host => participants.sort_by(|a, b| {
+                compare_hosts(a.get_hostname(), b.get_hostname())
+                    .then_with(|| a.get_system_id().cmp(b.get_system_id()))
+            }),
+            SortOrder::Guid | SortOrder::Topic => {}
+        }
+
+        participants
+            .into_iter()
+            .map(|participant| participant.get_system_id().clone())
+            .collect()
     }
 
-    pub fn summarize(&self) -> String {
+    pub fn summarize(&self, sort_order: SortOrder) -> String {
+        let participants_ids = self.get_participants_ids(sort_order);
+
         let mut summary = format!(
             "\t- Found {} participants: {:?}.\n",
             self.len(),
-            self.get_participants_ids()
+            participants_ids
         );
 
-        for participant_id in self.get_participants_ids() {
+        for participant_id in participants_ids {
             let participant = self.participants.get(&participant_id).unwrap();
 
             summary.push_str(&format!(
@@ -61,7 +351,7 @@ impl DdsiTopology {
                 participant.get_hostname()
             ));
 
-            let readers_id = participant.get_readers_id();
+            let readers_id = participant.get_readers_id(sort_order);
 
             summary.push_str(&format!("\t\t- Readers {}:\n", readers_id.len()));
 
@@ -70,7 +360,7 @@ impl DdsiTopology {
                 summary.push_str(&format!("\t\t\t- {}: {:?}\n", id, qos));
             }
 
-            let writers_id = participant.get_writers_id();
+            let writers_id = participant.get_writers_id(sort_order);
 
             summary.push_str(&format!("\t\t- Writers {}:\n", writers_id.len()));
 
@@ -78,20 +368,376 @@ impl DdsiTopology {
                 let qos = participant.get_writer_qos(&id).unwrap();
                 summary.push_str(&format!("\t\t\t- {}: {:?}\n", id, qos));
             }
+
+            let config = participant.get_config();
+            if !config.is_empty() {
+                summary.push_str(&format!("\t\t- Config {}:\n", config.len()));
+                for (key, value) in config {
+                    summary.push_str(&format!("\t\t\t- {}: {}\n", key, value));
+                }
+            }
         }
         summary
     }
 }
 
+/// Compare hostnames, numerically for ip addresses so that 172.17.0.4 comes
+/// before 172.17.0.10, and alphabetically after them for other names.
+fn compare_hosts(a: &str, b: &str) -> Ordering {
+    match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl Default for DdsiTopology {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Serialize for DdsiTopology {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        // 3 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("DdsiTopology", 2)?;
+        // 5 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("DdsiTopology", 5)?;
+        state.serialize_field("version", &SCHEMA_VERSION)?;
         state.serialize_field("participants", &self.participants)?;
         state.serialize_field("own_ip", &self.own_ip)?;
+        state.serialize_field("cputime", &self.cputime)?;
+        state.serialize_field("pending_config", &self.pending_config)?;
         state.end()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::{
+        BUILTIN_WRITER_SEDP_ST0, READER_SEDP_ST0, TRANSIENT_READER_SEDP_ST0,
+    };
+
+    const LOG_SAMPLE: [&str; 3] = [
+        TRANSIENT_READER_SEDP_ST0,
+        BUILTIN_WRITER_SEDP_ST0,
+        READER_SEDP_ST0,
+    ];
+
+    fn sample_topology() -> DdsiTopology {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        for line in LOG_SAMPLE {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(line).unwrap())
+                .unwrap();
+        }
+        ddsi_topology
+    }
+
+    #[test]
+    fn participants_sort_order() {
+        let ddsi_topology = sample_topology();
+
+        assert_eq!(
+            ddsi_topology.get_participants_ids(SortOrder::FirstSeen),
+            ["7efc2093:7b:1", "745ad3d:7b:1"]
+        );
+        assert_eq!(
+            ddsi_topology.get_participants_ids(SortOrder::Host),
+            ["7efc2093:7b:1", "745ad3d:7b:1"]
+        );
+        assert_eq!(
+            ddsi_topology.get_participants_ids(SortOrder::Guid),
+            ["745ad3d:7b:1", "7efc2093:7b:1"]
+        );
+    }
+
+    /// Return the discovery of a reader of `topic` with the given id, host
+    /// and timestamp.
+    fn reader_sedp_st0(
+        timestamp: &str,
+        system_id: &str,
+        id: &str,
+        host: &str,
+        topic: &str,
+    ) -> String {
+        format!("2022-01-23T14:11:29+0000 {}/dq.builtin: SEDP ST0 {}:{} reliable volatile reader: nile.Test.cmd.{}/Test::{} p(open) NEW (as 239.255.0.1:7401 {}:39948) QOS={{topic={},type=Test::{},presentation=0:0:0,partition={{nile.Test.cmd}},durability=0,deadline=2147483647.999999999,latency_budget=0.000000000,liveliness=0:2147483647.999999999,reliability=1:0.100000000,destination_order=0,history=0:100,resource_limits=-1:-1:-1,transport_priority=0,ownership=0,time_based_filter=0.000000000,reader_data_lifecycle=2147483647.999999999:2147483647.999999999:0:1:1,relaxed_qos_matching=0,reader_lifespan={{0,2147483647.999999999}},subscription_keys={{0,{{}}}},share={{0,}},synchronous_endpoint=0}}", timestamp, system_id, id, topic, topic, host, topic, topic)
+    }
+
+    #[test]
+    fn endpoints_sort_order() {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+        for (timestamp, id, topic) in [
+            ("1642947089.200000", "1107", "Test_c"),
+            ("1642947089.300000", "2207", "Test_a"),
+            ("1642947089.100000", "3307", "Test_b"),
+        ] {
+            let line = reader_sedp_st0(timestamp, "745ad3d:7b:1", id, "172.17.0.4", topic);
+            ddsi_topology
+                .update(ddsi_log_regex.parse(&line).unwrap())
+                .unwrap();
+        }
+        let participant = &ddsi_topology.participants["745ad3d:7b:1"];
+
+        assert_eq!(
+            participant.get_readers_id(SortOrder::FirstSeen),
+            ["3307", "1107", "2207"]
+        );
+        assert_eq!(
+            participant.get_readers_id(SortOrder::Guid),
+            ["1107", "2207", "3307"]
+        );
+        assert_eq!(
+            participant.get_readers_id(SortOrder::Topic),
+            ["2207", "3307", "1107"]
+        );
+    }
+
+    #[test]
+    fn hosts_sort_order() {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+        for (system_id, host) in [
+            ("1:7b:1", "172.17.0.10"),
+            ("2:7b:1", "172.17.0.4"),
+            ("4:7b:1", "10.0.0.1"),
+        ] {
+            let line = reader_sedp_st0("1642947089.100000", system_id, "3307", host, "Test_a");
+            ddsi_topology
+                .update(ddsi_log_regex.parse(&line).unwrap())
+                .unwrap();
+        }
+        // Without an ownip entry, the log's own participants have an unknown
+        // host.
+        ddsi_topology
+            .update(
+                ddsi_log_regex
+                    .parse("2022-01-23T14:11:29+0000 1642947089.000000/      main: handleParticipantsSelf: found 3:7b:1 (self)")
+                    .unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            ddsi_topology.get_participants_ids(SortOrder::Host),
+            ["4:7b:1", "2:7b:1", "1:7b:1", "3:7b:1"]
+        );
+    }
+
+    #[test]
+    fn topology_counts() {
+        assert_eq!(
+            sample_topology().get_counts(),
+            TopologyCounts {
+                participants: 2,
+                hosts: 2,
+                topics: 3,
+                readers: 2,
+                alive_readers: 2,
+                writers: 1,
+                alive_writers: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = sample_topology();
+        for line in [
+            "2022-01-20T13:24:36+0000 1642685076.168332/dq.builtin: thread_cputime 1260.618874505",
+            "2022-01-20T13:24:37+0000 1642685077.168332/dq.builtin: thread_cputime 1260.718874505",
+        ] {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(line).unwrap())
+                .unwrap();
+        }
+        let serialized = serde_json::to_string(&ddsi_topology).unwrap();
+
+        let loaded = DdsiTopology::from_json(serialized.as_bytes()).unwrap();
+
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), serialized);
+        assert_eq!(
+            loaded.summarize(SortOrder::Topic),
+            ddsi_topology.summarize(SortOrder::Topic)
+        );
+        assert_eq!(loaded.get_cputime().get_usage().len(), 1);
+    }
+
+    #[test]
+    fn json_pending_config() {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+        ddsi_topology
+            .update(ddsi_log_regex.parse(
+                "2022-01-23T14:08:13+0000 1642946893.209000/      main: config: Domain/Discovery/Ports/Base: 7400 {}",
+            ).unwrap())
+            .unwrap();
+        let serialized = serde_json::to_string(&ddsi_topology).unwrap();
+
+        let mut loaded = DdsiTopology::from_json(serialized.as_bytes()).unwrap();
+
+        assert_eq!(
+            loaded.get_config(),
+            Some(&BTreeMap::from([(
+                String::from("Domain/Discovery/Ports/Base"),
+                String::from("7400")
+            )]))
+        );
+
+        // Merging keeps the pending configuration of both, the first one
+        // taking precedence.
+        let mut other = DdsiTopology::new();
+        for line in [
+            "2022-01-23T14:08:13+0000 1642946893.209000/      main: config: Domain/Discovery/Ports/Base: 7410 {}",
+            "2022-01-23T14:08:13+0000 1642946893.209000/      main: config: Domain/General/NetworkInterfaceAddress/#text: auto {0}",
+        ] {
+            other
+                .update(ddsi_log_regex.parse(line).unwrap())
+                .unwrap();
+        }
+        loaded.merge(other);
+        let serialized = serde_json::to_string(&loaded).unwrap();
+        let loaded = DdsiTopology::from_json(serialized.as_bytes()).unwrap();
+
+        assert_eq!(
+            loaded.get_config(),
+            Some(&BTreeMap::from([
+                (
+                    String::from("Domain/Discovery/Ports/Base"),
+                    String::from("7400")
+                ),
+                (
+                    String::from("Domain/General/NetworkInterfaceAddress/#text"),
+                    String::from("auto")
+                ),
+            ]))
+        );
+    }
+
+    #[test]
+    fn config_attached_to_self_participant() {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        for line in [
+            "2022-01-23T14:08:13+0000 1642946893.209000/      main: config: Domain/General/NetworkInterfaceAddress/#text: auto {0}",
+            "2022-01-23T14:08:13+0000 1642946893.209853/      main: ownip: 172.17.0.3",
+            "2022-01-23T14:11:29+0000 1642947089.000000/      main: handleParticipantsSelf: found 745ad3d:7b:1 (self)",
+            "2022-01-23T14:11:29+0000 1642947089.100000/      main: config: Domain/Discovery/Ports/Base: 7400 {}",
+        ] {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(line).unwrap())
+                .unwrap();
+        }
+
+        assert_eq!(
+            ddsi_topology.get_self_participant_id().as_deref(),
+            Some("745ad3d:7b:1")
+        );
+
+        let config = ddsi_topology
+            .get_participant("745ad3d:7b:1")
+            .unwrap()
+            .get_config();
+        assert_eq!(config.len(), 2);
+        assert_eq!(
+            config["Domain/General/NetworkInterfaceAddress/#text"],
+            "auto"
+        );
+        assert_eq!(config["Domain/Discovery/Ports/Base"], "7400");
+        assert_eq!(ddsi_topology.get_config(), Some(config));
+
+        // The configuration outlives the self participant filtered out.
+        let config = config.clone();
+        ddsi_topology.retain_endpoints(|_, _, _, _| false);
+        assert!(ddsi_topology.is_empty());
+        assert_eq!(ddsi_topology.get_config(), Some(&config));
+    }
+
+    #[test]
+    fn json_without_version() {
+        let serialized = r#"{"participants":{"428f812:7b:1":{"system_id":"428f812:7b:1","hostname":"172.17.0.3","readers":{},"writers":{"2302":{"topic":"d_sampleChain","partition":"durabilityPartition","created":[1638915588.898675],"deleted":[]}},"is_master":false,"master_id":""}},"own_ip":"172.17.0.3"}"#;
+
+        let loaded = DdsiTopology::from_json(serialized.as_bytes()).unwrap();
+
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(
+            loaded
+                .get_participant("428f812:7b:1")
+                .unwrap()
+                .get_writers_id(SortOrder::Guid),
+            ["2302"]
+        );
+    }
+
+    #[test]
+    fn json_participant_never_seen() {
+        let mut ddsi_topology = DdsiTopology::new();
+        ddsi_topology.participants.insert(
+            String::from("428f812:7b:1"),
+            DdsiParticipant::new("428f812:7b:1", "172.17.0.3"),
+        );
+        let serialized = serde_json::to_string(&ddsi_topology).unwrap();
+
+        let loaded = DdsiTopology::from_json(serialized.as_bytes()).unwrap();
+
+        let participant = loaded.get_participant("428f812:7b:1").unwrap();
+        assert_eq!(participant.get_first_seen(), None);
+        assert_eq!(participant.get_last_seen(), None);
+    }
+
+    #[test]
+    fn json_newer_version() {
+        let serialized = r#"{"version":1000,"participants":{},"own_ip":"172.17.0.3"}"#;
+
+        assert!(DdsiTopology::from_json(serialized.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn summarize_is_deterministic() {
+        let summary = sample_topology().summarize(SortOrder::FirstSeen);
+
+        for _ in 0..10 {
+            assert_eq!(sample_topology().summarize(SortOrder::FirstSeen), summary);
+        }
+    }
+
+    #[test]
+    fn merge_local_view() {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = sample_topology();
+        let mut local = DdsiTopology::new();
+        local
+            .update(ddsi_log_regex.parse(
+                "2022-01-23T14:11:29+0000 1642947089.500000/    (anon): READER 745ad3d:7b:1:3b07 QOS={topic=Test_command_start_cc827fa3,type=Test::command_start_cc827fa3,presentation=0:0:0,partition={nile.Test.cmd},durability=0,durability_service=0.000000000:{0:100}:{-1:-1:-1},deadline=2147483647.999999999,latency_budget=0.000000000,liveliness=0:2147483647.999999999,reliability=1:0.100000000,destination_order=0,history=0:100,resource_limits=-1:-1:-1,transport_priority=0,lifespan=2147483647.999999999,ownership=0,time_based_filter=0.000000000,reader_data_lifecycle=2147483647.999999999:2147483647.999999999:0:1:1,relaxed_qos_matching=0,reader_lifespan={0,2147483647.999999999},subscription_keys={0,{}},share={0,},synchronous_endpoint=0}",
+            ).unwrap())
+            .unwrap();
+
+        ddsi_topology.merge(local);
+
+        let participant = ddsi_topology.get_participant("745ad3d:7b:1").unwrap();
+        assert!(participant.is_local());
+        // The endpoint only known remotely is kept, the local view of the
+        // other one takes precedence.
+        assert_eq!(
+            participant.get_readers_id(SortOrder::Guid),
+            ["3b07", "5507"]
+        );
+        assert_eq!(
+            participant
+                .get_reader_qos(&String::from("3b07"))
+                .unwrap()
+                .get_created(),
+            &[1642947089.5]
+        );
+        assert_eq!(participant.get_first_seen(), Some(1642947089.5));
+        assert_eq!(participant.get_last_seen(), Some(1642947089.904222));
+    }
+}
@@ -0,0 +1,551 @@
+use crate::ddsi_participant::{DdsiParticipant, Qos};
+use crate::ddsi_topology::{DdsiTopology, SortOrder};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, List, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeSet;
+use std::error::Error;
+
+/// Number of steps the time slider takes to go through the log.
+const TIME_STEPS: f64 = 100.0;
+
+/// Panes of the browser, in focus order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pane {
+    Participants,
+    Topics,
+    Endpoints,
+}
+
+const PANES: [Pane; 3] = [Pane::Participants, Pane::Topics, Pane::Endpoints];
+
+/// An endpoint listed in the endpoints pane.
+struct Endpoint<'a> {
+    participant: &'a DdsiParticipant,
+    id: String,
+    is_writer: bool,
+    qos: &'a Qos,
+}
+
+impl Endpoint<'_> {
+    fn get_label(&self) -> String {
+        format!(
+            "{} {}:{} {}",
+            if self.is_writer { "W" } else { "R" },
+            self.participant.get_system_id(),
+            self.id,
+            self.qos.get_topic()
+        )
+    }
+}
+
+/// State of the interactive topology browser.
+///
+/// Participants and topics are listed side by side, the endpoints pane
+/// lists the endpoints of whichever of the two was last focused, and the
+/// QoS of the selected endpoint is shown below. A search string and a time
+/// slider, moving through the endpoints lifetimes, restrict the endpoints
+/// shown.
+struct Browser<'a> {
+    ddsi_topology: &'a DdsiTopology,
+    sort_order: SortOrder,
+    focus: Pane,
+    source: Pane,
+    selected: [usize; 3],
+    search: String,
+    searching: bool,
+    time_range: Option<(f64, f64)>,
+    time: Option<f64>,
+}
+
+impl<'a> Browser<'a> {
+    fn new(ddsi_topology: &'a DdsiTopology, sort_order: SortOrder) -> Browser<'a> {
+        let mut browser = Browser {
+            ddsi_topology,
+            sort_order,
+            focus: Pane::Participants,
+            source: Pane::Participants,
+            selected: [0; 3],
+            search: String::new(),
+            searching: false,
+            time_range: None,
+            time: None,
+        };
+
+        let bounds = browser
+            .get_all_endpoints()
+            .iter()
+            .flat_map(|endpoint| endpoint.qos.get_lifetimes())
+            .flat_map(|(created, deleted)| [Some(created), deleted])
+            .flatten()
+            .fold(None, |range: Option<(f64, f64)>, timestamp| match range {
+                Some((start, end)) => Some((start.min(timestamp), end.max(timestamp))),
+                None => Some((timestamp, timestamp)),
+            });
+        browser.time_range = bounds;
+        browser
+    }
+
+    /// Handle a key press, return true to quit.
+    fn handle_key(&mut self, key: KeyCode) -> bool {
+        if self.searching {
+            match key {
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Esc => {
+                    self.searching = false;
+                    self.search.clear();
+                }
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Char(c) => self.search.push(c),
+                _ => {}
+            }
+            self.selected = [0; 3];
+            return false;
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Tab | KeyCode::Right => self.move_focus(1),
+            KeyCode::BackTab | KeyCode::Left => self.move_focus(PANES.len() - 1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(true),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(false),
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Char('t') => {
+                self.time = match self.time {
+                    Some(_) => None,
+                    None => self.time_range.map(|(_, end)| end),
+                };
+                self.selected = [0; 3];
+            }
+            KeyCode::Char('[') => self.move_time(-1.0),
+            KeyCode::Char(']') => self.move_time(1.0),
+            _ => {}
+        }
+        false
+    }
+
+    fn move_focus(&mut self, step: usize) {
+        let index = PANES
+            .iter()
+            .position(|pane| *pane == self.focus)
+            .unwrap_or(0);
+        self.focus = PANES[(index + step) % PANES.len()];
+        if self.focus != Pane::Endpoints {
+            self.source = self.focus;
+            self.selected[Pane::Endpoints as usize] = 0;
+        }
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        let len = match self.focus {
+            Pane::Participants => self.get_participants().len(),
+            Pane::Topics => self.get_topics().len(),
+            Pane::Endpoints => self.get_endpoints().len(),
+        };
+        let selected = self.get_selected(self.focus, len);
+        self.selected[self.focus as usize] = if down {
+            (selected + 1).min(len.saturating_sub(1))
+        } else {
+            selected.saturating_sub(1)
+        };
+        if self.focus != Pane::Endpoints {
+            self.selected[Pane::Endpoints as usize] = 0;
+        }
+    }
+
+    fn move_time(&mut self, steps: f64) {
+        if let (Some(time), Some((start, end))) = (self.time, self.time_range) {
+            let step = (end - start) / TIME_STEPS;
+            self.time = Some((time + steps * step).clamp(start, end));
+            self.selected[Pane::Endpoints as usize] = 0;
+        }
+    }
+
+    /// Return the selected index of a pane, kept within the list length.
+    fn get_selected(&self, pane: Pane, len: usize) -> usize {
+        self.selected[pane as usize].min(len.saturating_sub(1))
+    }
+
+    fn get_all_endpoints(&self) -> Vec<Endpoint<'a>> {
+        let mut endpoints = vec![];
+
+        for participant_id in self.ddsi_topology.get_participants_ids(self.sort_order) {
+            let participant = match self.ddsi_topology.get_participant(&participant_id) {
+                Some(participant) => participant,
+                None => continue,
+            };
+            for id in participant.get_writers_id(self.sort_order) {
+                if let Some(qos) = participant.get_writer_qos(&id) {
+                    endpoints.push(Endpoint {
+                        participant,
+                        id,
+                        is_writer: true,
+                        qos,
+                    });
+                }
+            }
+            for id in participant.get_readers_id(self.sort_order) {
+                if let Some(qos) = participant.get_reader_qos(&id) {
+                    endpoints.push(Endpoint {
+                        participant,
+                        id,
+                        is_writer: false,
+                        qos,
+                    });
+                }
+            }
+        }
+        endpoints
+    }
+
+    /// Check if an endpoint matches the search string and is alive at the
+    /// time of the slider.
+    fn is_visible(&self, endpoint: &Endpoint) -> bool {
+        let alive = self.time.is_none_or(|time| {
+            endpoint
+                .qos
+                .get_lifetimes()
+                .iter()
+                .any(|(created, deleted)| *created <= time && deleted.is_none_or(|d| d > time))
+        });
+        let search = self.search.to_lowercase();
+        let found = search.is_empty()
+            || endpoint.get_label().to_lowercase().contains(&search)
+            || endpoint
+                .participant
+                .get_hostname()
+                .to_lowercase()
+                .contains(&search);
+        alive && found
+    }
+
+    fn get_visible_endpoints(&self) -> Vec<Endpoint<'a>> {
+        self.get_all_endpoints()
+            .into_iter()
+            .filter(|endpoint| self.is_visible(endpoint))
+            .collect()
+    }
+
+    fn get_participants(&self) -> Vec<&'a DdsiParticipant> {
+        let restricted = !self.search.is_empty() || self.time.is_some();
+        let visible: BTreeSet<&String> = self
+            .get_visible_endpoints()
+            .iter()
+            .map(|endpoint| endpoint.participant.get_system_id())
+            .collect();
+
+        self.ddsi_topology
+            .get_participants_ids(self.sort_order)
+            .iter()
+            .filter_map(|participant_id| self.ddsi_topology.get_participant(participant_id))
+            .filter(|participant| !restricted || visible.contains(participant.get_system_id()))
+            .collect()
+    }
+
+    fn get_topics(&self) -> Vec<String> {
+        let topics: BTreeSet<String> = self
+            .get_visible_endpoints()
+            .iter()
+            .map(|endpoint| endpoint.qos.get_topic().clone())
+            .collect();
+        topics.into_iter().collect()
+    }
+
+    /// Return the endpoints of the selected participant, or topic.
+    fn get_endpoints(&self) -> Vec<Endpoint<'a>> {
+        let endpoints = self.get_visible_endpoints();
+
+        match self.source {
+            Pane::Topics => {
+                let topics = self.get_topics();
+                match topics.get(self.get_selected(Pane::Topics, topics.len())) {
+                    Some(topic) => endpoints
+                        .into_iter()
+                        .filter(|endpoint| endpoint.qos.get_topic() == topic)
+                        .collect(),
+                    None => vec![],
+                }
+            }
+            _ => {
+                let participants = self.get_participants();
+                match participants.get(self.get_selected(Pane::Participants, participants.len())) {
+                    Some(participant) => endpoints
+                        .into_iter()
+                        .filter(|endpoint| {
+                            endpoint.participant.get_system_id() == participant.get_system_id()
+                        })
+                        .collect(),
+                    None => vec![],
+                }
+            }
+        }
+    }
+
+    /// Return the detail lines of the selected endpoint.
+    fn get_details(&self) -> Vec<String> {
+        let endpoints = self.get_endpoints();
+        let endpoint = match endpoints.get(self.get_selected(Pane::Endpoints, endpoints.len())) {
+            Some(endpoint) => endpoint,
+            None => return vec![],
+        };
+
+        let mut details = vec![
+            format!(
+                "{} {}:{}",
+                if endpoint.is_writer {
+                    "Writer"
+                } else {
+                    "Reader"
+                },
+                endpoint.participant.get_system_id(),
+                endpoint.id
+            ),
+            format!("host: {}", endpoint.participant.get_hostname()),
+            format!("topic: {}", endpoint.qos.get_topic()),
+            format!("partition: {}", endpoint.qos.get_partition()),
+            format!("locators: {}", endpoint.qos.get_locators().join(", ")),
+        ];
+        for (created, deleted) in endpoint.qos.get_lifetimes() {
+            details.push(match deleted {
+                Some(deleted) => format!("lifetime: {:.6} - {:.6}", created, deleted),
+                None => format!("lifetime: {:.6} -", created),
+            });
+        }
+        for (policy, value) in endpoint.qos.get_policies() {
+            details.push(format!("{}={}", policy, value));
+        }
+        details
+    }
+
+    fn get_status(&self) -> String {
+        let mut status = String::new();
+
+        match (self.time, self.time_range) {
+            (Some(time), Some((start, end))) => {
+                let position = if end > start {
+                    ((time - start) / (end - start) * 20.0).round() as usize
+                } else {
+                    20
+                };
+                status.push_str(&format!(
+                    "time {:.3} [{}{}] ",
+                    time,
+                    "=".repeat(position),
+                    " ".repeat(20 - position)
+                ));
+            }
+            _ => status.push_str("time: all "),
+        }
+        if self.searching || !self.search.is_empty() {
+            status.push_str(&format!("| search: {} ", self.search));
+        }
+        status.push_str("| q quit, tab pane, / search, t time slider, [ ] move time");
+        status
+    }
+}
+
+/// Browse a topology in an interactive terminal user interface.
+pub fn browse(ddsi_topology: &DdsiTopology, sort_order: SortOrder) -> Result<(), Box<dyn Error>> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, Browser::new(ddsi_topology, sort_order));
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, mut browser: Browser) -> Result<(), Box<dyn Error>> {
+    loop {
+        terminal.draw(|frame| draw(frame, &browser))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && browser.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, browser: &Browser) {
+    let [panes_area, details_area, status_area] = Layout::vertical([
+        Constraint::Min(8),
+        Constraint::Percentage(40),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let panes_areas = Layout::horizontal([
+        Constraint::Percentage(30),
+        Constraint::Percentage(30),
+        Constraint::Percentage(40),
+    ])
+    .split(panes_area);
+
+    let participants: Vec<String> = browser
+        .get_participants()
+        .iter()
+        .map(|participant| {
+            format!(
+                "{} ({})",
+                participant.get_system_id(),
+                participant.get_hostname()
+            )
+        })
+        .collect();
+    let topics = browser.get_topics();
+    let endpoints: Vec<String> = browser
+        .get_endpoints()
+        .iter()
+        .map(|endpoint| endpoint.get_label())
+        .collect();
+
+    for ((pane, title, items), area) in [
+        (Pane::Participants, "Participants", participants),
+        (Pane::Topics, "Topics", topics),
+        (Pane::Endpoints, "Endpoints", endpoints),
+    ]
+    .into_iter()
+    .zip(panes_areas.iter())
+    {
+        let mut state = ListState::default();
+        state.select(Some(browser.get_selected(pane, items.len())));
+        let list = List::new(items.clone())
+            .block(pane_block(
+                &format!("{} ({})", title, items.len()),
+                browser.focus == pane,
+            ))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, *area, &mut state);
+    }
+
+    frame.render_widget(
+        Paragraph::new(browser.get_details().join("\n")).block(pane_block("QoS", false)),
+        details_area,
+    );
+    frame.render_widget(Paragraph::new(browser.get_status()), status_area);
+}
+
+fn pane_block(title: &str, focused: bool) -> Block<'static> {
+    let style = if focused {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    Block::default()
+        .title(String::from(title))
+        .borders(Borders::ALL)
+        .border_style(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_log_regex::DdsiLogRegex;
+    use crate::ddsi_test_data::{
+        BUILTIN_WRITER_SEDP_ST0, READER_SEDP_ST0, TRANSIENT_READER_SEDP_ST0,
+    };
+
+    /// A volatile reader, a best effort transient reader deleted later on
+    /// and a writer.
+    fn log_sample() -> [String; 4] {
+        [
+            String::from(READER_SEDP_ST0),
+            TRANSIENT_READER_SEDP_ST0.replace("reliability=1:", "reliability=0:"),
+            String::from(BUILTIN_WRITER_SEDP_ST0),
+            String::from(
+                "2022-01-23T14:11:30+0000 1642947090.004222/dq.builtin: SEDP ST3 745ad3d:7b:1:5507delete_proxy_reader (745ad3d:7b:1:5507) - deleting",
+            ),
+        ]
+    }
+
+    fn topology() -> DdsiTopology {
+        let ddsi_log_regex = DdsiLogRegex::new();
+        let mut ddsi_topology = DdsiTopology::new();
+
+        for line in log_sample() {
+            ddsi_topology
+                .update(ddsi_log_regex.parse(&line).unwrap())
+                .unwrap();
+        }
+        ddsi_topology
+    }
+
+    fn endpoint_labels(browser: &Browser) -> Vec<String> {
+        browser
+            .get_endpoints()
+            .iter()
+            .map(|endpoint| endpoint.get_label())
+            .collect()
+    }
+
+    #[test]
+    fn navigate_panes() {
+        let ddsi_topology = topology();
+        let mut browser = Browser::new(&ddsi_topology, SortOrder::Guid);
+
+        assert_eq!(browser.get_participants().len(), 2);
+        assert_eq!(browser.get_topics().len(), 3);
+        assert_eq!(endpoint_labels(&browser).len(), 2);
+
+        browser.handle_key(KeyCode::Down);
+        assert_eq!(
+            endpoint_labels(&browser),
+            ["W 7efc2093:7b:1:302 DCPSParticipant"]
+        );
+
+        browser.handle_key(KeyCode::Tab);
+        browser.handle_key(KeyCode::Down);
+        assert_eq!(
+            endpoint_labels(&browser),
+            ["R 745ad3d:7b:1:3b07 Test_command_start_cc827fa3"]
+        );
+
+        browser.handle_key(KeyCode::Tab);
+        let details = browser.get_details();
+        assert_eq!(details[0], "Reader 745ad3d:7b:1:3b07");
+        assert!(details.contains(&String::from("reliability=1:0.100000000")));
+
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &browser)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("Participants (2)"));
+        assert!(text.contains("Reader 745ad3d:7b:1:3b07"));
+
+        assert!(browser.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn search_and_time_slider() {
+        let ddsi_topology = topology();
+        let mut browser = Browser::new(&ddsi_topology, SortOrder::Guid);
+
+        for key in [
+            KeyCode::Char('/'),
+            KeyCode::Char('L'),
+            KeyCode::Char('o'),
+            KeyCode::Char('g'),
+            KeyCode::Enter,
+        ] {
+            assert!(!browser.handle_key(key));
+        }
+        assert_eq!(browser.get_participants().len(), 1);
+        assert_eq!(
+            browser.get_topics(),
+            ["Test_logevent_summaryState_782ec3fd"]
+        );
+
+        browser.handle_key(KeyCode::Char('/'));
+        browser.handle_key(KeyCode::Esc);
+        browser.handle_key(KeyCode::Char('t'));
+        assert_eq!(browser.time, Some(1642947090.004222));
+        assert_eq!(browser.get_topics().len(), 2);
+
+        browser.handle_key(KeyCode::Char('['));
+        assert_eq!(browser.get_topics(), ["DCPSParticipant"]);
+        browser.time = Some(1642947089.95);
+        assert_eq!(browser.get_topics().len(), 3);
+        assert!(browser.get_status().starts_with("time 1642947089.950 ["));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::ddsi_analysis::LogAnalysis;
+use crate::ddsi_filter::Filter;
+use crate::ddsi_metrics::LogMetrics;
+use crate::ddsi_parser::{Parser, ParserOptions};
+use crate::ddsi_problems::Problem;
+use crate::ddsi_topology::DdsiTopology;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::File;
+use std::io::{prelude::*, BufReader};
+
+/// Incremental parser of a ddsi log that is still being written.
+///
+/// Each poll parses the complete lines appended since the previous one, a
+/// line still being written is kept until its end is logged.
+pub struct LogFollower {
+    filename: String,
+    reader: BufReader<File>,
+    partial: String,
+    parser: Parser,
+    ddsi_topology: DdsiTopology,
+    analysis: LogAnalysis,
+    n_lines: usize,
+    n_reported_problems: usize,
+}
+
+impl LogFollower {
+    /// Follow a log, parsing it with the given options. Lines outside of
+    /// the time window are skipped, so a follower with `until` set stops
+    /// updating once the log goes past it.
+    pub fn new(filename: &str, options: ParserOptions) -> Result<LogFollower, Box<dyn Error>> {
+        Ok(LogFollower {
+            filename: String::from(filename),
+            reader: BufReader::new(File::open(filename)?),
+            partial: String::new(),
+            parser: Parser::new(options),
+            ddsi_topology: DdsiTopology::new(),
+            analysis: LogAnalysis::new(),
+            n_lines: 0,
+            n_reported_problems: 0,
+        })
+    }
+
+    /// Parse the lines appended to the log since the last poll, returning
+    /// the number of lines parsed.
+    pub fn poll(&mut self) -> Result<usize, Box<dyn Error>> {
+        let mut n_lines = 0;
+
+        while self.reader.read_line(&mut self.partial)? > 0 {
+            if !self.partial.ends_with('\n') {
+                break;
+            }
+            let line = self.partial.trim_end_matches(['\n', '\r']);
+            self.parser
+                .parse_line(line, &mut self.ddsi_topology, &mut self.analysis)?;
+            self.partial.clear();
+            n_lines += 1;
+        }
+        self.n_lines += n_lines;
+        Ok(n_lines)
+    }
+
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Return the problems found since the last call.
+    pub fn take_new_problems(&mut self) -> &[Problem] {
+        let start = self.n_reported_problems;
+        let problems = self.analysis.problems.get_problems();
+        self.n_reported_problems = problems.len();
+        &problems[start..]
+    }
+
+    /// Return the topology of the log, restricted to the endpoints matching
+    /// the filter, if given.
+    fn get_topology(&self, filter: Option<&Filter>) -> Cow<'_, DdsiTopology> {
+        match filter {
+            Some(filter) => {
+                let mut ddsi_topology = self.ddsi_topology.clone();
+                filter.apply(&mut ddsi_topology);
+                Cow::Owned(ddsi_topology)
+            }
+            None => Cow::Borrowed(&self.ddsi_topology),
+        }
+    }
+
+    /// Return a one line status of the log, counting only the endpoints
+    /// matching the filter, if given.
+    pub fn get_status(&self, filter: Option<&Filter>) -> String {
+        let counts = self.get_topology(filter).get_counts();
+        format!(
+            "{}: {} lines, {} participants, {} readers ({} alive), {} writers ({} alive), {} problems",
+            self.filename,
+            self.n_lines,
+            counts.participants,
+            counts.readers,
+            counts.alive_readers,
+            counts.writers,
+            counts.alive_writers,
+            self.analysis.problems.get_problems().len()
+        )
+    }
+
+    /// Return the metrics of the log, counting only the endpoints matching
+    /// the filter, if given.
+    pub fn get_metrics(&self, filter: Option<&Filter>) -> LogMetrics {
+        LogMetrics::new(
+            &self.filename,
+            self.n_lines,
+            &self.get_topology(filter),
+            &self.analysis,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddsi_test_data::{TempFile, READER_SEDP_ST0};
+    use std::fs::OpenOptions;
+
+    #[test]
+    fn follow_growing_log() {
+        let log = TempFile::new("watch.log", "");
+        let filename = log.path();
+        let mut file = OpenOptions::new().append(true).open(filename).unwrap();
+
+        let mut follower = LogFollower::new(filename, ParserOptions::default()).unwrap();
+        assert_eq!(follower.poll().unwrap(), 0);
+
+        write!(
+            file,
+            "{}\n\
+            2022-01-23T14:11:31+0000 1642947091.000000/     lease: lease expired",
+            READER_SEDP_ST0
+        )
+        .unwrap();
+        assert_eq!(follower.poll().unwrap(), 1);
+        assert!(follower.take_new_problems().is_empty());
+
+        writeln!(file, " for participant 5bbed783:7b:1").unwrap();
+        assert_eq!(follower.poll().unwrap(), 1);
+
+        let problems = follower.take_new_problems();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].participant_id.as_deref(), Some("5bbed783:7b:1"));
+        assert!(follower.take_new_problems().is_empty());
+
+        assert!(follower.get_status(None).ends_with(
+            ": 2 lines, 1 participants, 1 readers (1 alive), 0 writers (0 alive), 1 problems"
+        ));
+        let filter: Filter = "kind == writer".parse().unwrap();
+        assert!(follower
+            .get_status(Some(&filter))
+            .contains(", 0 participants, 0 readers"));
+    }
+
+    #[test]
+    fn follow_log_until() {
+        let log = TempFile::new("watch_until.log", "");
+        let filename = log.path();
+        let mut file = OpenOptions::new().append(true).open(filename).unwrap();
+        let options = ParserOptions {
+            until: Some(1642947090.0),
+            ..Default::default()
+        };
+
+        let mut follower = LogFollower::new(filename, options).unwrap();
+        writeln!(
+            file,
+            "2022-01-23T14:11:29+0000 1642947089.000000/     lease: lease expired for participant 5bbed783:7b:1\n\
+            2022-01-23T14:11:31+0000 1642947091.000000/     lease: lease expired for participant 745ad3d:7b:1"
+        )
+        .unwrap();
+        assert_eq!(follower.poll().unwrap(), 2);
+
+        let problems = follower.take_new_problems();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].participant_id.as_deref(), Some("5bbed783:7b:1"));
+    }
+}
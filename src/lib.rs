@@ -1,87 +1,1012 @@
 use indicatif::{ProgressBar, ProgressStyle};
-use regex::Captures;
-use std::collections::HashMap;
 use std::error::Error;
 use std::fs::{metadata, File};
-use std::io::{self, prelude::*, BufReader};
+use std::io::IsTerminal;
+use std::io::{self, prelude::*, BufReader, BufWriter};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
 
-mod ddsi_log_regex;
-mod ddsi_participant;
-mod ddsi_topology;
+mod ddsi_analysis;
+mod ddsi_check;
+mod ddsi_churn;
+mod ddsi_config;
+mod ddsi_cputime;
+mod ddsi_diff;
+mod ddsi_event;
+mod ddsi_event_export;
+mod ddsi_export;
+mod ddsi_filter;
+mod ddsi_flow;
+mod ddsi_graph;
+mod ddsi_latency;
+pub mod ddsi_log_regex;
+mod ddsi_metrics;
+mod ddsi_options;
+mod ddsi_parser;
+pub mod ddsi_participant;
+mod ddsi_problems;
+#[cfg(feature = "python")]
+mod ddsi_python;
+mod ddsi_reliability;
+mod ddsi_sqlite;
+mod ddsi_statistics;
+#[cfg(test)]
+mod ddsi_test_data;
+mod ddsi_timeline;
+pub mod ddsi_topology;
+#[cfg(feature = "tui")]
+mod ddsi_tui;
+mod ddsi_watch;
+
+pub use ddsi_event::{DdsiEvent, EventReader};
+pub use ddsi_event_export::EventFormat;
+pub use ddsi_export::ExportFormat;
+pub use ddsi_options::{InputOptions, SummaryExport, SummaryOptions};
+pub use ddsi_parser::{NoProgress, Parser, ParserOptions, Progress};
+pub use ddsi_topology::DdsiTopology;
+pub use ddsi_topology::SortOrder;
+
+/// Default width, in seconds, of the window used for flapping detection and
+/// churn rates.
+pub const DEFAULT_FLAP_WINDOW: f64 = 60.0;
+/// Default number of creation/deletion cycles within the flapping window for
+/// an endpoint to be considered flapping.
+pub const DEFAULT_FLAP_CYCLES: usize = 3;
+
+/// Outcome of a command, reflected in the exit code of the binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Nothing worth reporting was found.
+    Clean,
+    /// The command found differences, or problems.
+    Findings,
+}
+
+/// Input options shared by the commands processing ddsi logs.
+pub trait DdsiInputConfig {
+    /// ddsi log files, or topologies saved as json, to process. Logs of
+    /// different nodes are merged into a single topology.
+    fn get_filenames(&self) -> &[String];
+
+    /// Order in which participants and endpoints are reported.
+    fn get_sort(&self) -> SortOrder {
+        SortOrder::default()
+    }
+
+    /// Filter expression selecting the endpoints to process, e.g.
+    /// `topic =~ "logevent_.*" && reliability == best_effort`.
+    fn get_filter(&self) -> Option<&str> {
+        None
+    }
+
+    /// Ignore log entries logged before this time, in seconds since epoch.
+    fn get_since(&self) -> Option<f64> {
+        None
+    }
+
+    /// Ignore log entries logged after this time, in seconds since epoch.
+    fn get_until(&self) -> Option<f64> {
+        None
+    }
+
+    /// Fail on log entries inconsistent with the topology instead of
+    /// warning about them on stderr.
+    fn is_strict(&self) -> bool {
+        false
+    }
+
+    /// Number of logs parsed in parallel.
+    fn get_jobs(&self) -> usize {
+        1
+    }
+
+    /// Show the progress of the parse of the logs, when stderr is a
+    /// terminal.
+    fn get_progress(&self) -> bool {
+        true
+    }
+
+    /// Only report errors, neither progress nor what is being processed.
+    fn is_quiet(&self) -> bool {
+        false
+    }
+}
 
 pub trait DdsiLogConfig {
-    fn get_filename(&self) -> &str;
+    fn get_input(&self) -> &dyn DdsiInputConfig;
     fn get_output(&self) -> &str;
+
+    /// Name of the file to save the merged topology to as json, if any.
+    fn get_json(&self) -> Option<&str> {
+        Some("ddsi_topology.json")
+    }
+
+    /// Exports of the merged topology written along with the summary.
+    fn get_exports(&self) -> &[SummaryExport] {
+        &[]
+    }
+
+    /// Name of the sqlite database to save the merged topology, the
+    /// problems and the events of the logs to, if any.
+    fn get_sqlite(&self) -> Option<&str> {
+        None
+    }
+
+    /// Width, in seconds, of the window used for flapping detection and
+    /// churn rates.
+    fn get_flap_window(&self) -> f64 {
+        DEFAULT_FLAP_WINDOW
+    }
+
+    /// Number of creation/deletion cycles within the flapping window for an
+    /// endpoint to be considered flapping.
+    fn get_flap_cycles(&self) -> usize {
+        DEFAULT_FLAP_CYCLES
+    }
+}
+
+pub trait DdsiDiffConfig {
+    /// Inputs to compare, one or two ddsi log files or topologies saved as
+    /// json. Inputs are compared one by one, not merged, so the number of
+    /// jobs and the sort order are not used.
+    fn get_input(&self) -> &dyn DdsiInputConfig;
+
+    /// Times at which to take the topology of the inputs. If a single input
+    /// is given, two times are required.
+    fn get_at(&self) -> &[f64];
+
+    /// Name of the output file, the diff is printed if not given.
+    fn get_output(&self) -> Option<&str>;
+}
+
+pub trait DdsiExportConfig {
+    fn get_input(&self) -> &dyn DdsiInputConfig;
+
+    /// Format to export the topology to.
+    fn get_format(&self) -> ExportFormat;
+
+    /// Name of the output file, the export is printed if not given.
+    fn get_output(&self) -> Option<&str>;
 }
 
-pub fn run<T>(config: &T) -> Result<(), Box<dyn Error>>
+pub trait DdsiEventsConfig {
+    /// ddsi log files to read the events of. Only the time window of the
+    /// input options applies to events, a filter is rejected.
+    fn get_input(&self) -> &dyn DdsiInputConfig;
+
+    /// Format to write the events in.
+    fn get_format(&self) -> EventFormat;
+
+    /// Name of the output file.
+    fn get_output(&self) -> Option<&str>;
+}
+
+pub trait DdsiCheckConfig {
+    fn get_input(&self) -> &dyn DdsiInputConfig;
+
+    /// Name of the output file, the report is printed if not given.
+    fn get_output(&self) -> Option<&str>;
+
+    /// json file of rules to check, no finding of any built-in check is
+    /// expected if not given.
+    fn get_rules(&self) -> Option<&str> {
+        None
+    }
+
+    /// Name of the file to write a JUnit XML report to, if any.
+    fn get_junit(&self) -> Option<&str> {
+        None
+    }
+
+    /// Width, in seconds, of the window used for flapping detection.
+    fn get_flap_window(&self) -> f64 {
+        DEFAULT_FLAP_WINDOW
+    }
+
+    /// Number of creation/deletion cycles within the flapping window for an
+    /// endpoint to be reported as flapping.
+    fn get_flap_cycles(&self) -> usize {
+        DEFAULT_FLAP_CYCLES
+    }
+}
+
+pub trait DdsiStatsConfig {
+    fn get_input(&self) -> &dyn DdsiInputConfig;
+
+    /// Name of the output file, the statistics are printed if not given.
+    fn get_output(&self) -> Option<&str>;
+}
+
+pub trait DdsiQueryConfig {
+    fn get_input(&self) -> &dyn DdsiInputConfig;
+
+    /// Filter expression selecting the endpoints to list.
+    fn get_query(&self) -> &str;
+
+    /// Name of the output file, the endpoints are printed if not given.
+    fn get_output(&self) -> Option<&str> {
+        None
+    }
+}
+
+pub trait DdsiWatchConfig {
+    fn get_input(&self) -> &dyn DdsiInputConfig;
+
+    /// Time, in seconds, between checks for new lines.
+    fn get_interval(&self) -> f64 {
+        1.0
+    }
+
+    /// Address to serve Prometheus metrics of the logs on, if any, e.g.
+    /// `0.0.0.0:9100`.
+    fn get_metrics_address(&self) -> Option<&str> {
+        None
+    }
+}
+
+pub fn run<T>(config: &T) -> Result<Outcome, Box<dyn Error>>
 where
     T: DdsiLogConfig,
 {
-    println!(
-        "Processing '{}' and storing results in '{}'.",
-        &config.get_filename(),
-        &config.get_output(),
-    );
+    let quiet = config.get_input().is_quiet();
+    if !quiet {
+        eprintln!(
+            "Processing '{}' and storing results in '{}'.",
+            &config.get_input().get_filenames().join("', '"),
+            &config.get_output(),
+        );
+    }
 
-    let summary = generate_summary(&config.get_filename());
+    let (summary, ddsi_topology, analysis) = generate_summary(config)?;
 
-    println!("Writing summary to {}", &config.get_output());
+    if let Some(json) = config.get_json() {
+        let serialized = serde_json::to_string(&ddsi_topology)?;
+        write_output(Some(json), "ddsi_topology", &serialized, quiet)?;
+    }
 
-    let mut file = File::create(&config.get_output())?;
-    file.write_all(summary.as_bytes())?;
+    if let Some(sqlite) = config.get_sqlite() {
+        if !quiet {
+            eprintln!("Writing database to {}", sqlite);
+        }
+        let logs = config
+            .get_input()
+            .get_filenames()
+            .iter()
+            .filter(|filename| !is_json(filename))
+            .collect::<Vec<_>>();
+        ddsi_sqlite::save(
+            sqlite,
+            &logs,
+            &parser_options(config.get_input()),
+            config.get_input().get_filter(),
+            &ddsi_topology,
+            analysis.as_ref(),
+        )?;
+    }
 
-    Ok(())
+    for summary_export in config.get_exports() {
+        let exported = ddsi_export::export(&ddsi_topology, summary_export.format);
+        write_output(Some(&summary_export.output), "export", &exported, quiet)?;
+    }
+
+    write_output(Some(config.get_output()), "summary", &summary, quiet)?;
+
+    Ok(Outcome::Clean)
 }
 
-fn generate_summary(filename: &str) -> String {
-    let ddsi_log_regex = ddsi_log_regex::DdsiLogRegex::new();
+/// Report the differences between two topologies, with `Outcome::Findings`
+/// if any was found.
+pub fn run_diff<T>(config: &T) -> Result<Outcome, Box<dyn Error>>
+where
+    T: DdsiDiffConfig,
+{
+    let input = config.get_input();
+    if input.get_until().is_some() && !config.get_at().is_empty() {
+        return Err("diff takes the topology at --at, --until cannot be given too.".into());
+    }
+    let until = input.get_until();
+    let (before, after) = match (input.get_filenames(), config.get_at()) {
+        ([before, after], []) => ((before, until), (after, until)),
+        ([before, after], [at]) => ((before, Some(*at)), (after, Some(*at))),
+        ([before, after], [before_at, after_at]) => {
+            ((before, Some(*before_at)), (after, Some(*after_at)))
+        }
+        ([input], [before_at, after_at]) => ((input, Some(*before_at)), (input, Some(*after_at))),
+        _ => return Err("diff requires two inputs, or a single input and two --at times.".into()),
+    };
+
+    let filter = parse_filter(input.get_filter())?;
+    let load = |(filename, until): (&String, Option<f64>)| -> Result<_, Box<dyn Error>> {
+        let n_bytes = if is_json(filename) {
+            0
+        } else {
+            metadata(filename)?.len()
+        };
+        let progress = new_progress(input, n_bytes);
+        let (mut ddsi_topology, _) = load_topology(
+            filename,
+            &ParserOptions {
+                until,
+                ..parser_options(input)
+            },
+            progress.as_ref(),
+        )?;
+        if let Some(progress) = progress {
+            progress.bar.finish_and_clear();
+        }
+        if let Some(filter) = &filter {
+            filter.apply(&mut ddsi_topology);
+        }
+        Ok(ddsi_topology)
+    };
+    let before_topology = load(before)?;
+    let after_topology = load(after)?;
 
-    let file = File::open(filename).unwrap();
+    let diff = ddsi_diff::DdsiTopologyDiff::new(&before_topology, &after_topology);
+
+    let diff_summary = if diff.is_empty() {
+        String::from("\t- No differences found.\n")
+    } else {
+        diff.summarize()
+    };
+
+    let summary = format!(
+        "Diff {}{} -> {}{}:\n{}",
+        before.0,
+        before.1.map_or(String::new(), |at| format!("@{}", at)),
+        after.0,
+        after.1.map_or(String::new(), |at| format!("@{}", at)),
+        diff_summary,
+    );
+
+    write_output(config.get_output(), "diff", &summary, input.is_quiet())?;
+
+    Ok(if diff.is_empty() {
+        Outcome::Clean
+    } else {
+        Outcome::Findings
+    })
+}
+
+pub fn run_export<T>(config: &T) -> Result<Outcome, Box<dyn Error>>
+where
+    T: DdsiExportConfig,
+{
+    let (ddsi_topologies, _) = load_inputs(config.get_input())?;
+    let ddsi_topology = merge_topologies(ddsi_topologies)?;
+
+    let exported = ddsi_export::export(&ddsi_topology, config.get_format());
+
+    write_output(
+        config.get_output(),
+        "export",
+        &exported,
+        config.get_input().is_quiet(),
+    )?;
+
+    Ok(Outcome::Clean)
+}
+
+/// Write every recognized entry of the logs as an event, one row each.
+pub fn run_events<T>(config: &T) -> Result<Outcome, Box<dyn Error>>
+where
+    T: DdsiEventsConfig,
+{
+    let input = config.get_input();
+    if input.get_filenames().is_empty() {
+        return Err("No input file given.".into());
+    }
+    if input.get_filter().is_some() {
+        return Err("Events cannot be filtered, only --since and --until apply.".into());
+    }
+    let n_events = ddsi_event_export::write_events(
+        input.get_filenames(),
+        &parser_options(input),
+        config.get_format(),
+        config.get_output(),
+    )?;
+    if !input.is_quiet() {
+        if let Some(output) = config.get_output() {
+            eprintln!("Wrote {} events to {}", n_events, output);
+        }
+    }
+    Ok(Outcome::Clean)
+}
 
-    let n_lines = metadata(filename).unwrap().len();
+/// Check rules against the topology and the analysis of the logs, with
+/// `Outcome::Findings` if any rule failed.
+pub fn run_check<T>(config: &T) -> Result<Outcome, Box<dyn Error>>
+where
+    T: DdsiCheckConfig,
+{
+    let rules = match config.get_rules() {
+        Some(rules) => ddsi_check::Rules::from_json(BufReader::new(File::open(rules)?))?,
+        None => ddsi_check::Rules::default(),
+    };
+    ddsi_churn::ChurnAnalysis::check_parameters(
+        config.get_flap_window(),
+        config.get_flap_cycles(),
+    )?;
+    let (ddsi_topologies, analysis) = load_inputs(config.get_input())?;
+    let ddsi_topology = merge_topologies(ddsi_topologies)?;
 
-    let reader = BufReader::new(file);
+    let churn = ddsi_churn::ChurnAnalysis::new(
+        &ddsi_topology,
+        config.get_flap_window(),
+        config.get_flap_cycles(),
+    )?;
+    let report = ddsi_check::CheckReport::new(&rules, &ddsi_topology, analysis.as_ref(), &churn);
 
-    let mut ddsi_topology = ddsi_topology::DdsiTopology::new();
+    let quiet = config.get_input().is_quiet();
+    write_output(
+        config.get_output(),
+        "report",
+        &format!("Check:\n{}", report.summarize()),
+        quiet,
+    )?;
+    if let Some(junit) = config.get_junit() {
+        write_output(Some(junit), "JUnit report", &report.to_junit(), quiet)?;
+    }
 
-    println!("Processing {} lines", n_lines);
+    Ok(if report.is_success() {
+        Outcome::Clean
+    } else {
+        Outcome::Findings
+    })
+}
 
-    let bar = ProgressBar::new(n_lines);
+/// Report the size of the topology and statistics over the log lines.
+pub fn run_stats<T>(config: &T) -> Result<Outcome, Box<dyn Error>>
+where
+    T: DdsiStatsConfig,
+{
+    let (ddsi_topologies, analysis) = load_inputs(config.get_input())?;
+    let ddsi_topology = merge_topologies(ddsi_topologies)?;
 
-    bar.set_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed}] {bar:40.cyan/blue} {eta} {msg}")
-            .progress_chars("##-"),
+    let counts = ddsi_topology.get_counts();
+    let mut stats = format!(
+        "Statistics:\n\
+        \t- Participants: {}\n\
+        \t- Hosts: {}\n\
+        \t- Topics: {}\n\
+        \t- Readers: {} ({} alive)\n\
+        \t- Writers: {} ({} alive)\n",
+        counts.participants,
+        counts.hosts,
+        counts.topics,
+        counts.readers,
+        counts.alive_readers,
+        counts.writers,
+        counts.alive_writers,
     );
 
-    let mut n_matcher = 0;
+    if let Some(analysis) = &analysis {
+        let problems = analysis.problems.get_counts();
+        stats.push_str(&format!(
+            "\t- Problems: {}\n",
+            problems.values().sum::<usize>()
+        ));
+        for (kind, count) in problems {
+            stats.push_str(&format!("\t\t- {}: {}\n", kind, count));
+        }
+        stats.push_str(&analysis.statistics.summarize());
+    }
+
+    write_output(
+        config.get_output(),
+        "statistics",
+        &stats,
+        config.get_input().is_quiet(),
+    )?;
+
+    Ok(Outcome::Clean)
+}
+
+/// List the endpoints matching a filter expression, one per line.
+pub fn run_query<T>(config: &T) -> Result<Outcome, Box<dyn Error>>
+where
+    T: DdsiQueryConfig,
+{
+    let query: ddsi_filter::Filter = config.get_query().parse()?;
+    let (ddsi_topologies, _) = load_inputs(config.get_input())?;
+    let mut ddsi_topology = merge_topologies(ddsi_topologies)?;
+    query.apply(&mut ddsi_topology);
+
+    let sort_order = config.get_input().get_sort();
+    let mut endpoints = String::new();
+    for participant_id in ddsi_topology.get_participants_ids(sort_order) {
+        let participant = ddsi_topology.get_participant(&participant_id).unwrap();
+
+        for (kind, ids) in [
+            ("writer", participant.get_writers_id(sort_order)),
+            ("reader", participant.get_readers_id(sort_order)),
+        ] {
+            for id in ids {
+                let qos = match kind {
+                    "writer" => participant.get_writer_qos(&id),
+                    _ => participant.get_reader_qos(&id),
+                }
+                .unwrap();
+                endpoints.push_str(&format!(
+                    "{}:{}\t{}\t{}\t{}\t{}\t{}\n",
+                    participant_id,
+                    id,
+                    kind,
+                    qos.get_topic(),
+                    qos.get_partition(),
+                    participant.get_hostname(),
+                    if qos.is_alive() { "alive" } else { "deleted" },
+                ));
+            }
+        }
+    }
+
+    write_output(
+        config.get_output(),
+        "endpoints",
+        &endpoints,
+        config.get_input().is_quiet(),
+    )?;
+    Ok(Outcome::Clean)
+}
+
+/// Follow ddsi logs as they grow, reporting new problems and changes of the
+/// topology size. Never returns unless an error occurs.
+pub fn run_watch<T>(config: &T) -> Result<Outcome, Box<dyn Error>>
+where
+    T: DdsiWatchConfig,
+{
+    let input = config.get_input();
+    let filter = parse_filter(input.get_filter())?;
+    if input.get_filenames().is_empty() {
+        return Err("No input file given.".into());
+    }
+    // Logs are followed one line at a time, in turn.
+    if input.get_jobs() > 1 {
+        return Err("Logs cannot be followed in parallel, --jobs is not supported.".into());
+    }
+    if let Some(filename) = input.get_filenames().iter().find(|f| is_json(f)) {
+        return Err(format!("Cannot follow the saved topology {}.", filename).into());
+    }
+    let options = parser_options(input);
+    let mut followers = input
+        .get_filenames()
+        .iter()
+        .map(|filename| ddsi_watch::LogFollower::new(filename, options.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let interval = std::time::Duration::from_secs_f64(config.get_interval());
+    let metrics_server = config
+        .get_metrics_address()
+        .map(ddsi_metrics::MetricsServer::new)
+        .transpose()?;
+    if let Some(metrics_server) = &metrics_server {
+        if !config.get_input().is_quiet() {
+            eprintln!(
+                "Serving metrics on http://{}/metrics",
+                metrics_server.get_address()
+            );
+        }
+    }
+
+    loop {
+        for follower in &mut followers {
+            if follower.poll()? == 0 {
+                continue;
+            }
+            let filename = String::from(follower.get_filename());
+            for problem in follower.take_new_problems() {
+                println!(
+                    "{}: {} at {}: {}",
+                    filename,
+                    problem.kind,
+                    problem
+                        .timestamp
+                        .map_or(String::from("-"), |timestamp| format!("{:.6}", timestamp)),
+                    problem.message
+                );
+            }
+            println!("{}", follower.get_status(filter.as_ref()));
+        }
+        match &metrics_server {
+            Some(metrics_server) => metrics_server.serve(interval, || {
+                let metrics = followers
+                    .iter()
+                    .map(|follower| follower.get_metrics(filter.as_ref()))
+                    .collect::<Vec<_>>();
+                ddsi_metrics::render(&metrics)
+            })?,
+            None => std::thread::sleep(interval),
+        }
+    }
+}
 
-    for line in reader.lines() {
-        let line_data = &line.unwrap();
+#[cfg(feature = "tui")]
+pub fn run_tui<T>(config: &T) -> Result<Outcome, Box<dyn Error>>
+where
+    T: DdsiInputConfig,
+{
+    let (ddsi_topologies, _) = load_inputs(config)?;
+    let ddsi_topology = merge_topologies(ddsi_topologies)?;
+    ddsi_tui::browse(&ddsi_topology, config.get_sort())?;
+    Ok(Outcome::Clean)
+}
 
-        if let Some(dds_log_type) = ddsi_log_regex.parse(line_data) {
-            ddsi_topology.update(dds_log_type);
-            n_matcher += 1;
+/// Write the output of a command to a file, or print it if no file is
+/// given.
+fn write_output(
+    output: Option<&str>,
+    name: &str,
+    content: &str,
+    quiet: bool,
+) -> Result<(), Box<dyn Error>> {
+    match output {
+        Some(output) => {
+            if !quiet {
+                eprintln!("Writing {} to {}", name, output);
+            }
+            let mut file = File::create(output)?;
+            file.write_all(content.as_bytes())?;
+        }
+        None => {
+            let mut stdout = BufWriter::new(io::stdout().lock());
+            match stdout
+                .write_all(content.as_bytes())
+                .and_then(|()| stdout.flush())
+            {
+                // The output is piped to a command that stopped reading it,
+                // e.g. head.
+                Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {}
+                result => result?,
+            }
         }
-        bar.inc(line_data.len() as u64);
     }
-    bar.finish();
+    Ok(())
+}
+
+/// Generate the summary of the inputs, returning it along with their merged
+/// topology and analysis.
+fn generate_summary<T>(config: &T) -> Result<Summary, Box<dyn Error>>
+where
+    T: DdsiLogConfig,
+{
+    ddsi_churn::ChurnAnalysis::check_parameters(
+        config.get_flap_window(),
+        config.get_flap_cycles(),
+    )?;
+    let (ddsi_topologies, analysis) = load_inputs(config.get_input())?;
 
-    println!("Generating summary.");
-    let summary = ddsi_topology.summarize();
+    let (latency, config_differences) = if ddsi_topologies.len() > 1 {
+        let logs: Vec<(&str, &ddsi_topology::DdsiTopology)> = config
+            .get_input()
+            .get_filenames()
+            .iter()
+            .map(String::as_str)
+            .zip(&ddsi_topologies)
+            .collect();
+        (
+            Some(ddsi_latency::DiscoveryLatency::new(&logs)),
+            Some(ddsi_config::ConfigDifferences::new(&logs)),
+        )
+    } else {
+        (None, None)
+    };
 
-    println!("Saving ddsi_topology.");
+    let ddsi_topology = merge_topologies(ddsi_topologies)?;
 
-    let serialized = serde_json::to_string(&ddsi_topology).unwrap();
+    let churn = ddsi_churn::ChurnAnalysis::new(
+        &ddsi_topology,
+        config.get_flap_window(),
+        config.get_flap_cycles(),
+    )?;
 
-    let mut file = File::create("ddsi_topology.json").unwrap();
-    file.write_all(serialized.as_bytes()).unwrap();
+    // The top flapping offenders come first.
+    let mut summary = String::new();
+    if !churn.is_empty() {
+        summary.push_str(&churn.summarize());
+    }
+    if let Some(analysis) = &analysis {
+        summary.push_str(&analysis.problems.summarize(&ddsi_topology));
+    }
+    summary.push_str(&ddsi_topology.summarize(config.get_input().get_sort()));
+
+    if !ddsi_topology.get_cputime().is_empty() {
+        summary.push_str(&ddsi_topology.get_cputime().summarize());
+    }
+
+    if let Some(latency) = latency {
+        summary.push_str(&latency.summarize());
+    }
 
+    if let Some(config_differences) = config_differences {
+        if config_differences.is_comparable() {
+            summary.push_str(&config_differences.summarize());
+        }
+    }
+
+    if let Some(analysis) = &analysis {
+        if !analysis.reliability.is_empty() {
+            summary.push_str(&analysis.reliability.summarize());
+        }
+        if !analysis.flow.is_empty() {
+            summary.push_str(&analysis.flow.summarize(&ddsi_topology));
+        }
+        summary.push_str(&analysis.statistics.summarize());
+    }
+
+    let summary = match &analysis {
+        Some(analysis) => format!(
+            "Summary:\n\
+            \t- Found {} lines matching ddsi logs.\n\
+            {}",
+            analysis.statistics.get_n_matched(),
+            summary,
+        ),
+        None => format!("Summary:\n{}", summary),
+    };
+    Ok((summary, ddsi_topology, analysis))
+}
+
+/// Load all the inputs of a command, returning the topology of each,
+/// restricted to the endpoints matching the filter, along with the merged
+/// analysis of the inputs that were parsed from logs.
+fn load_inputs(
+    input: &(impl DdsiInputConfig + ?Sized),
+) -> Result<
+    (
+        Vec<ddsi_topology::DdsiTopology>,
+        Option<ddsi_analysis::LogAnalysis>,
+    ),
+    Box<dyn Error>,
+> {
+    let filter = parse_filter(input.get_filter())?;
+    let options = parser_options(input);
+    let quiet = input.is_quiet();
+    let mut n_bytes = 0;
+    for filename in input.get_filenames().iter().filter(|f| !is_json(f)) {
+        n_bytes += metadata(filename)?.len();
+    }
+    let progress = new_progress(input, n_bytes);
+
+    let start = Instant::now();
+    let loaded = load_topologies(
+        input.get_filenames(),
+        &options,
+        input.get_jobs(),
+        progress.as_ref(),
+    )?;
+    let elapsed = start.elapsed().as_secs_f64();
+    if let Some(progress) = progress {
+        progress.bar.finish_and_clear();
+    }
+
+    let mut ddsi_topologies = vec![];
+    let mut analysis: Option<ddsi_analysis::LogAnalysis> = None;
+    for (mut ddsi_topology, file_analysis) in loaded {
+        if let Some(filter) = &filter {
+            filter.apply(&mut ddsi_topology);
+        }
+        if let Some(file_analysis) = file_analysis {
+            match analysis.as_mut() {
+                Some(analysis) => analysis.merge(file_analysis),
+                None => analysis = Some(file_analysis),
+            }
+        }
+        ddsi_topologies.push(ddsi_topology);
+    }
+
+    if let (Some(analysis), false) = (&analysis, quiet) {
+        eprintln!(
+            "{}",
+            throughput(
+                analysis.statistics.get_n_lines(),
+                n_bytes,
+                analysis.statistics.get_n_matched(),
+                elapsed
+            )
+        );
+    }
+    Ok((ddsi_topologies, analysis))
+}
+
+/// Return the progress bar of the parse of logs, `n_bytes` bytes long, if
+/// it is to be shown.
+fn new_progress(
+    input: &(impl DdsiInputConfig + ?Sized),
+    n_bytes: u64,
+) -> Option<ProgressBarProgress> {
+    if input.get_progress() && !input.is_quiet() && n_bytes > 0 && io::stderr().is_terminal() {
+        let bar = ProgressBar::new(n_bytes);
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "[{elapsed_precise}] {bar:40.cyan/blue} {bytes}/{total_bytes} \
+                    ({bytes_per_sec}, eta {eta}) {msg}",
+                )
+                .progress_chars("##-"),
+        );
+        Some(ProgressBarProgress {
+            bar,
+            total_lines: Arc::new(AtomicU64::new(0)),
+            bytes: 0,
+            lines: 0,
+        })
+    } else {
+        None
+    }
+}
+
+/// Return the options of the parses of the inputs.
+fn parser_options(input: &(impl DdsiInputConfig + ?Sized)) -> ParserOptions {
+    ParserOptions {
+        since: input.get_since(),
+        until: input.get_until(),
+        strict: input.is_strict(),
+    }
+}
+
+/// Report the throughput of the parse of `n_lines` lines, `n_bytes` bytes
+/// long, in `elapsed` seconds.
+fn throughput(n_lines: usize, n_bytes: u64, n_matched: usize, elapsed: f64) -> String {
+    let megabytes = n_bytes as f64 / 1e6;
+    // Avoid infinite rates for inputs parsed faster than the clock resolution.
+    let elapsed = elapsed.max(1e-6);
     format!(
-        "Summary:\n\
-        \t- Found {} lines matching ddsi logs.\n\
-        {}",
-        n_matcher, summary,
+        "Parsed {} lines ({:.1} MB) in {:.2} s: {:.0} lines/s, {:.1} MB/s, {} matches.",
+        n_lines,
+        megabytes,
+        elapsed,
+        n_lines as f64 / elapsed,
+        megabytes / elapsed,
+        n_matched
     )
 }
+
+/// Topology of an input, along with the analysis of its lines if it was
+/// parsed from a log.
+type Summary = (
+    String,
+    ddsi_topology::DdsiTopology,
+    Option<ddsi_analysis::LogAnalysis>,
+);
+
+type LoadedInput = (
+    ddsi_topology::DdsiTopology,
+    Option<ddsi_analysis::LogAnalysis>,
+);
+
+/// Load several inputs with up to `jobs` of them in parallel, returning
+/// them in the order of the filenames.
+fn load_topologies(
+    filenames: &[String],
+    options: &ParserOptions,
+    jobs: usize,
+    progress: Option<&ProgressBarProgress>,
+) -> Result<Vec<LoadedInput>, Box<dyn Error>> {
+    let next = AtomicUsize::new(0);
+    let mut loaded: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.clamp(1, filenames.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut loaded = vec![];
+                    while let Some(filename) = filenames.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let result = load_topology(filename, options, progress)
+                            .map_err(|error| format!("{}: {}", filename, error));
+                        loaded.push((filename, result));
+                    }
+                    loaded
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    loaded.sort_by_key(|(filename, _)| filenames.iter().position(|f| f == *filename));
+    loaded
+        .into_iter()
+        .map(|(_, result)| result.map_err(Into::into))
+        .collect()
+}
+
+/// Merge the topologies of several logs into the first one.
+fn merge_topologies(
+    ddsi_topologies: Vec<ddsi_topology::DdsiTopology>,
+) -> Result<ddsi_topology::DdsiTopology, Box<dyn Error>> {
+    let mut ddsi_topologies = ddsi_topologies.into_iter();
+    let mut ddsi_topology = ddsi_topologies.next().ok_or("No input file given.")?;
+    for other in ddsi_topologies {
+        ddsi_topology.merge(other);
+    }
+    Ok(ddsi_topology)
+}
+
+/// Parse the filter expression, if any, before any log is processed.
+fn parse_filter(filter: Option<&str>) -> Result<Option<ddsi_filter::Filter>, Box<dyn Error>> {
+    Ok(filter.map(str::parse).transpose()?)
+}
+
+/// Check if an input is a topology saved as json, from its extension.
+fn is_json(filename: &str) -> bool {
+    Path::new(filename)
+        .extension()
+        .is_some_and(|ext| ext == "json")
+}
+
+/// Load a topology from either a ddsi log file or a topology previously
+/// saved as json, returning it along with the analysis of the log lines, if
+/// the input was parsed.
+///
+/// # Arguments
+///
+/// * `filename` - The ddsi log or json file to load, json files are
+///   recognized by their extension.
+/// * `options` - Options of the parse. Time windows are not supported for
+///   json files.
+/// * `progress` - Progress bar advanced by the parse, if any.
+///
+fn load_topology(
+    filename: &str,
+    options: &ParserOptions,
+    progress: Option<&ProgressBarProgress>,
+) -> Result<LoadedInput, Box<dyn Error>> {
+    if is_json(filename) {
+        if options.since.is_some() || options.until.is_some() {
+            return Err(
+                format!("Cannot select a time for the saved topology {}.", filename).into(),
+            );
+        }
+        let file = File::open(filename)?;
+        let ddsi_topology = ddsi_topology::DdsiTopology::from_json(BufReader::new(file))?;
+        Ok((ddsi_topology, None))
+    } else {
+        let mut parser = Parser::new(options.clone());
+        if let Some(progress) = progress {
+            parser = parser.with_progress(Box::new(progress.clone()));
+        }
+        let (mut ddsi_topology, mut analysis) = parser.parse(File::open(filename)?, None)?;
+        ddsi_topology.set_log(filename);
+        analysis.set_log(filename);
+        Ok((ddsi_topology, Some(analysis)))
+    }
+}
+
+/// Progress bar shared by the parses of the ddsi log files, each parse
+/// reporting to its own clone.
+#[derive(Clone)]
+struct ProgressBarProgress {
+    bar: ProgressBar,
+    /// Lines parsed by all the parses sharing the bar.
+    total_lines: Arc<AtomicU64>,
+    bytes: u64,
+    lines: u64,
+}
+
+/// Number of lines between updates of the lines count of the progress bar.
+const PROGRESS_LINES: u64 = 10000;
+
+impl ddsi_parser::Progress for ProgressBarProgress {
+    fn update(&mut self, bytes: u64, lines: u64) {
+        self.bar.inc(bytes - self.bytes);
+        self.bytes = bytes;
+
+        let total_lines = self
+            .total_lines
+            .fetch_add(lines - self.lines, Ordering::Relaxed)
+            + lines
+            - self.lines;
+        self.lines = lines;
+        if total_lines.is_multiple_of(PROGRESS_LINES) {
+            let elapsed = self.bar.elapsed().as_secs_f64().max(1e-6);
+            self.bar.set_message(format!(
+                "{} lines, {:.0} lines/s",
+                total_lines,
+                total_lines as f64 / elapsed
+            ));
+        }
+    }
+}